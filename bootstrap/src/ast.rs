@@ -1 +1,1007 @@
-//! ASTs for the Hail bootstrap compiler.
\ No newline at end of file
+//! ASTs for the Hail bootstrap compiler.
+
+use crate::Loc;
+
+/// An identifier.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Iden {
+    /// The name of the identifier.
+    pub name: String,
+
+    /// The location of the identifier.
+    pub loc: Loc,
+}
+
+impl Iden {
+    /// Creates a new identifier.
+    pub fn new(name: impl Into<String>, loc: Loc) -> Self {
+        Self {
+            name: name.into(),
+            loc,
+        }
+    }
+}
+
+/// A path of identifiers separated by `::`, such as `T::size_of`.
+///
+/// The `self` and `Self` keywords are represented as identifiers named `self` and `Self`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Path {
+    /// The segments of the path.  There is always at least one.
+    pub segments: Vec<Iden>,
+
+    /// The location of the path.
+    pub loc: Loc,
+}
+
+impl Path {
+    /// Returns the last segment of the path.
+    pub fn last(&self) -> &Iden {
+        self.segments.last().expect("paths always have a segment")
+    }
+}
+
+/// How an [`Attribute`] was written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttrStyle {
+    /// `#[name]` or `#[name(..)]`.
+    Bracketed,
+
+    /// `#name(..)`, written directly before a statement or expression.
+    Directive,
+}
+
+/// An attribute, such as `#[allow(unsafe_code)]` or `#allow(unsafe_code)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attribute {
+    /// How the attribute was written.
+    pub style: AttrStyle,
+
+    /// The name of the attribute.
+    pub path: Path,
+
+    /// The arguments of the attribute, if it has any.  This is always a [`TokenTree::Group`].
+    pub args: Option<TokenTree>,
+
+    /// The location of the attribute.
+    pub loc: Loc,
+}
+
+impl Attribute {
+    /// Returns the comma-separated token trees in the arguments, such as `unsafe_code` and
+    /// `missing_return` in `#allow(unsafe_code, missing_return)`.
+    pub fn arg_list(&self) -> Vec<&[TokenTree]> {
+        match &self.args {
+            Some(TokenTree::Group(_, trees, _)) if !trees.is_empty() => trees
+                .split(|tree| matches!(tree, TokenTree::Token(text, _) if text == ","))
+                .filter(|arg| !arg.is_empty())
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// The delimiters of a [`TokenTree::Group`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Delimiter {
+    /// `( .. )`
+    Paren,
+
+    /// `[ .. ]`
+    Bracket,
+
+    /// `{ .. }`
+    Brace,
+}
+
+/// The unparsed arguments of an attribute.  Each attribute gives them its own meaning.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TokenTree {
+    /// A single token, stored as its source text.
+    Token(String, Loc),
+
+    /// Token trees between delimiters.
+    Group(Delimiter, Vec<TokenTree>, Loc),
+}
+
+impl TokenTree {
+    /// Returns the location of the tree.
+    pub fn loc(&self) -> &Loc {
+        match self {
+            TokenTree::Token(_, loc) | TokenTree::Group(_, _, loc) => loc,
+        }
+    }
+}
+
+/// A line of a `///` or `//!` doc comment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DocComment {
+    /// The text after `///` or `//!`.
+    pub text: String,
+
+    /// The location of the comment.
+    pub loc: Loc,
+}
+
+/// Returns the text of the doc comment made of the lines `docs`, or `None` if there are none.
+///
+/// A space after the `///` or `//!` is removed from each line, so `/// Creates a buffer.`
+/// becomes `Creates a buffer.`.
+pub fn doc_text(docs: &[DocComment]) -> Option<String> {
+    if docs.is_empty() {
+        return None;
+    }
+
+    let lines: Vec<&str> = docs
+        .iter()
+        .map(|doc| doc.text.strip_prefix(' ').unwrap_or(&doc.text))
+        .collect();
+    Some(lines.join("\n"))
+}
+
+/// A parsed Hail source file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Module {
+    /// The file the module was parsed from.
+    pub file: u32,
+
+    /// The `//!` doc comments at the start of the file.
+    pub docs: Vec<DocComment>,
+
+    /// The items in the module.
+    pub items: Vec<Item>,
+}
+
+/// Whether an item is visible outside of its module.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Visibility {
+    /// The item is only visible inside of its module.
+    Private,
+
+    /// The item was declared `publ`; the location is that of the keyword.
+    Public(Loc),
+}
+
+/// An item, such as a function or a struct.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Item {
+    /// The `///` doc comments before the item.
+    pub docs: Vec<DocComment>,
+
+    /// The attributes of the item.
+    pub attrs: Vec<Attribute>,
+
+    /// The visibility of the item.
+    pub vis: Visibility,
+
+    /// What kind of item this is.
+    pub kind: ItemKind,
+
+    /// The location of the item.
+    pub loc: Loc,
+}
+
+impl Item {
+    /// Returns the name the item declares, if it declares one.
+    pub fn name(&self) -> Option<&Iden> {
+        match &self.kind {
+            ItemKind::Fun(fun) => Some(&fun.name),
+            ItemKind::Struct(struct_) | ItemKind::Union(struct_) => Some(&struct_.name),
+            ItemKind::Enum(enum_) => Some(&enum_.name),
+            ItemKind::Trait(trait_) => Some(&trait_.name),
+            ItemKind::Alias(alias) => Some(&alias.name),
+            ItemKind::Import(_) | ItemKind::Impl(_) | ItemKind::Error => None,
+        }
+    }
+}
+
+/// The kinds of items.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ItemKind {
+    /// `import { .. } from ..;`
+    Import(Import),
+
+    /// `fun name(..) -> T { .. }`
+    Fun(Fun),
+
+    /// `struct Name { .. }`
+    Struct(Struct),
+
+    /// `union Name { .. }`
+    Union(Struct),
+
+    /// `enum Name { .. }`
+    Enum(Enum),
+
+    /// `trait Name { .. }`
+    Trait(Trait),
+
+    /// `impl Type { .. }` or `impl Trait for Type { .. }`
+    Impl(Impl),
+
+    /// `struct Name = Type;`
+    Alias(Alias),
+
+    /// An item that couldn't be parsed.  The syntax error has already been reported, so later
+    /// passes skip it.
+    Error,
+}
+
+/// An import, such as `import { alloc, dealloc } from allocator;`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Import {
+    /// The names being imported.
+    pub names: Vec<ImportName>,
+
+    /// The module the names are imported from.
+    pub module: Path,
+}
+
+/// A single name in an [`Import`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportName {
+    /// The name in the imported module.
+    pub name: Iden,
+
+    /// The name it is bound to in this module, if renamed with `as`.
+    pub alias: Option<Iden>,
+}
+
+impl ImportName {
+    /// Returns the name this import is bound to in the importing module.
+    pub fn binding(&self) -> &Iden {
+        self.alias.as_ref().unwrap_or(&self.name)
+    }
+}
+
+/// A parameter of a mixin or `impl!` block, such as `T: Drop + Mem`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GenericParam {
+    /// The name of the parameter.
+    pub name: Iden,
+
+    /// The trait bounds on the parameter.
+    pub bounds: Vec<Bound>,
+
+    /// The location of the parameter.
+    pub loc: Loc,
+}
+
+/// A trait bound, such as `Mem` or `!Drop`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bound {
+    /// Whether the bound requires the trait to *not* be implemented.
+    pub negative: bool,
+
+    /// The trait.
+    pub path: Path,
+
+    /// The location of the bound.
+    pub loc: Loc,
+}
+
+/// A function.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fun {
+    /// The name of the function.
+    pub name: Iden,
+
+    /// The `self` parameter of a method.
+    pub receiver: Option<Receiver>,
+
+    /// The parameters of the function.
+    pub params: Vec<Param>,
+
+    /// The return type, if there is one.
+    pub ret: Option<Type>,
+
+    /// The body of the function.  Functions declared in traits may not have one.
+    pub body: Option<Block>,
+}
+
+/// The kinds of `self` parameters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReceiverKind {
+    /// `self`
+    Value,
+
+    /// `&self`
+    Ref,
+
+    /// `&mut self`
+    RefMut,
+}
+
+/// The `self` parameter of a method.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Receiver {
+    /// How `self` is received.
+    pub kind: ReceiverKind,
+
+    /// The location of the parameter.
+    pub loc: Loc,
+}
+
+/// A function parameter.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Param {
+    /// The name of the parameter.
+    pub name: Iden,
+
+    /// The type of the parameter.
+    pub ty: Type,
+
+    /// The location of the parameter.
+    pub loc: Loc,
+}
+
+/// A struct or union.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Struct {
+    /// Whether this is a mixin.
+    pub mixin: bool,
+
+    /// The name of the struct.
+    pub name: Iden,
+
+    /// The parameters of the mixin.
+    pub params: Vec<GenericParam>,
+
+    /// The fields of the struct.
+    pub fields: Vec<Field>,
+}
+
+/// A field of a struct or union.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    /// The `///` doc comments before the field.
+    pub docs: Vec<DocComment>,
+
+    /// The visibility of the field.
+    pub vis: Visibility,
+
+    /// The name of the field.
+    pub name: Iden,
+
+    /// The type of the field.
+    pub ty: Type,
+
+    /// The location of the field.
+    pub loc: Loc,
+}
+
+/// An enum.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Enum {
+    /// Whether this is a mixin.
+    pub mixin: bool,
+
+    /// The name of the enum.
+    pub name: Iden,
+
+    /// The parameters of the mixin.
+    pub params: Vec<GenericParam>,
+
+    /// The variants of the enum.
+    pub variants: Vec<Variant>,
+}
+
+/// A variant of an enum.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Variant {
+    /// The `///` doc comments before the variant.
+    pub docs: Vec<DocComment>,
+
+    /// The name of the variant.
+    pub name: Iden,
+
+    /// The explicit discriminant of the variant, if there is one.
+    pub value: Option<Expr>,
+
+    /// The location of the variant.
+    pub loc: Loc,
+}
+
+/// A trait.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trait {
+    /// Whether this is a mixin.
+    pub mixin: bool,
+
+    /// The name of the trait.
+    pub name: Iden,
+
+    /// The parameters of the mixin.
+    pub params: Vec<GenericParam>,
+
+    /// The functions declared by the trait.
+    pub items: Vec<Item>,
+}
+
+/// An `impl` block.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Impl {
+    /// The parameters of an `impl!` block.
+    pub params: Vec<GenericParam>,
+
+    /// The trait being implemented, if any.
+    pub trait_: Option<Path>,
+
+    /// The type the block implements.
+    pub target: Type,
+
+    /// The functions in the block.
+    pub items: Vec<Item>,
+}
+
+/// The keyword an [`Alias`] was declared with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AliasKind {
+    /// `struct Name = ..;`
+    Struct,
+
+    /// `union Name = ..;`
+    Union,
+
+    /// `enum Name = ..;`
+    Enum,
+
+    /// `trait Name = ..;`
+    Trait,
+}
+
+/// A named type, such as `struct DynUint32Array = DynArray!<uint32>;`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Alias {
+    /// The keyword the alias was declared with.
+    pub kind: AliasKind,
+
+    /// The name of the alias.
+    pub name: Iden,
+
+    /// The aliased type.
+    pub target: Type,
+}
+
+/// A type.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Type {
+    /// What kind of type this is.
+    pub kind: TypeKind,
+
+    /// The location of the type.
+    pub loc: Loc,
+}
+
+/// The kinds of types.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TypeKind {
+    /// A named type, such as `int32` or `Self`.
+    Path(Path),
+
+    /// An instantiated mixin, such as `DynArray!<uint32>`.
+    Mixin(Path, Vec<MixinArg>),
+
+    /// A pointer, such as `*uint8` or `*mut uint8`.  The flag is set for `mut` pointers.
+    Ptr(bool, Box<Type>),
+
+    /// A reference, such as `&T` or `&mut T`.  The flag is set for `mut` references.
+    Ref(bool, Box<Type>),
+}
+
+/// An argument of an instantiated mixin.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MixinArg {
+    /// The type argument.
+    pub ty: Type,
+
+    /// Bounds restated on the argument, as in `impl!<T: Mem> DynArray!<T: Mem>`.
+    pub bounds: Vec<Bound>,
+}
+
+/// A block of statements.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block {
+    /// The statements in the block.
+    pub stmts: Vec<Stmt>,
+
+    /// The location of the block, including its braces.
+    pub loc: Loc,
+}
+
+/// A statement.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stmt {
+    /// The attributes of the statement.
+    pub attrs: Vec<Attribute>,
+
+    /// What kind of statement this is.
+    pub kind: StmtKind,
+
+    /// The location of the statement.
+    pub loc: Loc,
+}
+
+/// The kinds of statements.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StmtKind {
+    /// `val name = value;` or `val mut name: T = value;`
+    Val(Val),
+
+    /// `target = value;` or a compound assignment such as `target += value;`
+    Assign(Assign),
+
+    /// An expression followed by `;`.
+    Expr(Expr),
+
+    /// `return;` or `return value;`
+    Return(Option<Expr>),
+
+    /// `if cond { .. } else { .. }`
+    If(If),
+
+    /// `while cond { .. }`
+    While(While),
+
+    /// `break;`
+    Break,
+
+    /// `continue;`
+    Continue,
+
+    /// A nested block.
+    Block(Block),
+
+    /// A statement that couldn't be parsed.  The syntax error has already been reported, so later
+    /// passes skip it.
+    Error,
+}
+
+/// A `val` binding.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Val {
+    /// Whether the binding was declared `mut`.
+    pub mutable: bool,
+
+    /// The name being bound.
+    pub name: Iden,
+
+    /// The declared type, if there is one.
+    pub ty: Option<Type>,
+
+    /// The initial value, if there is one.
+    pub value: Option<Expr>,
+}
+
+/// An assignment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Assign {
+    /// The place being assigned to.
+    pub target: Expr,
+
+    /// The operator of a compound assignment, such as `+` for `+=`.
+    pub op: Option<BinOp>,
+
+    /// The value being assigned.
+    pub value: Expr,
+}
+
+/// An `if` statement.
+#[derive(Clone, Debug, PartialEq)]
+pub struct If {
+    /// The condition.
+    pub cond: Expr,
+
+    /// The block run when the condition is true.
+    pub then: Block,
+
+    /// The `else` branch, if there is one.
+    pub else_: Option<Else>,
+}
+
+/// The `else` branch of an `if` statement.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Else {
+    /// `else { .. }`
+    Block(Block),
+
+    /// `else if ..`
+    If(Box<If>),
+}
+
+/// A `while` loop.
+#[derive(Clone, Debug, PartialEq)]
+pub struct While {
+    /// The condition.
+    pub cond: Expr,
+
+    /// The body of the loop.
+    pub body: Block,
+}
+
+/// An expression.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expr {
+    /// The attributes of the expression.
+    pub attrs: Vec<Attribute>,
+
+    /// What kind of expression this is.
+    pub kind: ExprKind,
+
+    /// The location of the expression.
+    pub loc: Loc,
+}
+
+impl Expr {
+    /// Removes and returns the attributes written before the expression, which may belong to a
+    /// subexpression such as `a` in `#allow(unsafe_code) a + b`.
+    pub fn take_leading_attrs(&mut self) -> Vec<Attribute> {
+        if !self.attrs.is_empty() {
+            return std::mem::take(&mut self.attrs);
+        }
+
+        match &mut self.kind {
+            ExprKind::Binary(_, lhs, _) => lhs.take_leading_attrs(),
+            ExprKind::Cast(expr, _) => expr.take_leading_attrs(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// The kinds of expressions.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExprKind {
+    /// A literal.
+    Lit(Lit),
+
+    /// A path, such as `item`, `self` or `T::size_of`.
+    Path(Path),
+
+    /// A call, such as `alloc(size)`.
+    Call(Box<Expr>, Vec<Expr>),
+
+    /// A method call, such as `array.get(0)`.
+    Method(Box<Expr>, Iden, Vec<Expr>),
+
+    /// A field access, such as `self.len`.
+    Field(Box<Expr>, Iden),
+
+    /// An index, such as `self.buf[idx]`.
+    Index(Box<Expr>, Box<Expr>),
+
+    /// A unary operation, such as `-x`.
+    Unary(UnOp, Box<Expr>),
+
+    /// A binary operation, such as `a + b`.
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+
+    /// A cast, such as `x as *mut uint8`.
+    Cast(Box<Expr>, Type),
+
+    /// A struct literal, such as `Self::{ len: 0 }`.
+    Struct(StructLit),
+
+    /// An expression that couldn't be parsed, such as a malformed literal.  The error has already
+    /// been reported, so later passes skip it.
+    Error,
+}
+
+/// A literal value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Lit {
+    /// `true` or `false`.
+    Bool(bool),
+
+    /// An integer literal.
+    Int(IntLit),
+
+    /// A floating point literal.
+    Float(FloatLit),
+
+    /// A string literal.
+    Str(String),
+
+    /// A byte string literal, such as `b"hi"`.
+    ByteStr(Vec<u8>),
+
+    /// A character literal.
+    Char(char),
+
+    /// A byte literal, such as `b'a'`.
+    Byte(u8),
+}
+
+/// An integer literal, such as `42` or `0xff_u8`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IntLit {
+    /// The value of the literal.
+    pub value: u128,
+
+    /// The type suffix, if there is one.
+    pub suffix: Option<IntTy>,
+}
+
+/// A floating point literal, such as `1.5` or `2e-3f32`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FloatLit {
+    /// The value of the literal.
+    pub value: f64,
+
+    /// The type suffix, if there is one.
+    pub suffix: Option<FloatTy>,
+}
+
+/// The builtin integer types.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IntTy {
+    /// `int8`
+    Int8,
+
+    /// `int16`
+    Int16,
+
+    /// `int32`
+    Int32,
+
+    /// `int64`
+    Int64,
+
+    /// `int`, which is as wide as a pointer.
+    Int,
+
+    /// `uint8`
+    Uint8,
+
+    /// `uint16`
+    Uint16,
+
+    /// `uint32`
+    Uint32,
+
+    /// `uint64`
+    Uint64,
+
+    /// `uint`, which is as wide as a pointer.
+    Uint,
+}
+
+impl IntTy {
+    /// Every integer type.
+    pub const ALL: [IntTy; 10] = [
+        IntTy::Int8,
+        IntTy::Int16,
+        IntTy::Int32,
+        IntTy::Int64,
+        IntTy::Int,
+        IntTy::Uint8,
+        IntTy::Uint16,
+        IntTy::Uint32,
+        IntTy::Uint64,
+        IntTy::Uint,
+    ];
+
+    /// Returns the name of the type, such as `uint8`.
+    pub fn name(self) -> &'static str {
+        match self {
+            IntTy::Int8 => "int8",
+            IntTy::Int16 => "int16",
+            IntTy::Int32 => "int32",
+            IntTy::Int64 => "int64",
+            IntTy::Int => "int",
+            IntTy::Uint8 => "uint8",
+            IntTy::Uint16 => "uint16",
+            IntTy::Uint32 => "uint32",
+            IntTy::Uint64 => "uint64",
+            IntTy::Uint => "uint",
+        }
+    }
+
+    /// Returns the type with the literal suffix `suffix`, such as `u8` for `uint8`.
+    pub fn from_suffix(suffix: &str) -> Option<Self> {
+        Some(match suffix {
+            "i8" => IntTy::Int8,
+            "i16" => IntTy::Int16,
+            "i32" => IntTy::Int32,
+            "i64" => IntTy::Int64,
+            "i" => IntTy::Int,
+            "u8" => IntTy::Uint8,
+            "u16" => IntTy::Uint16,
+            "u32" => IntTy::Uint32,
+            "u64" => IntTy::Uint64,
+            "u" => IntTy::Uint,
+            _ => return None,
+        })
+    }
+
+    /// Returns whether the type is signed.
+    pub fn signed(self) -> bool {
+        matches!(
+            self,
+            IntTy::Int8 | IntTy::Int16 | IntTy::Int32 | IntTy::Int64 | IntTy::Int
+        )
+    }
+
+    /// Returns the width of the type in bits, given the width of a pointer.
+    pub fn bits(self, ptr_bits: u32) -> u32 {
+        match self {
+            IntTy::Int8 | IntTy::Uint8 => 8,
+            IntTy::Int16 | IntTy::Uint16 => 16,
+            IntTy::Int32 | IntTy::Uint32 => 32,
+            IntTy::Int64 | IntTy::Uint64 => 64,
+            IntTy::Int | IntTy::Uint => ptr_bits,
+        }
+    }
+
+    /// Returns whether `value`, negated if `negative` is set, fits in the type.
+    pub fn fits(self, value: u128, negative: bool, ptr_bits: u32) -> bool {
+        let bits = self.bits(ptr_bits);
+        match (self.signed(), negative) {
+            (true, false) => value < 1 << (bits - 1),
+            (true, true) => value <= 1 << (bits - 1),
+            (false, false) => value < 1 << bits,
+            (false, true) => value == 0,
+        }
+    }
+}
+
+/// The builtin floating point types.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FloatTy {
+    /// `float32`
+    Float32,
+
+    /// `float64`
+    Float64,
+}
+
+impl FloatTy {
+    /// Returns the name of the type, such as `float32`.
+    pub fn name(self) -> &'static str {
+        match self {
+            FloatTy::Float32 => "float32",
+            FloatTy::Float64 => "float64",
+        }
+    }
+
+    /// Returns the type with the literal suffix `suffix`, such as `f32` for `float32`.
+    pub fn from_suffix(suffix: &str) -> Option<Self> {
+        match suffix {
+            "f32" => Some(FloatTy::Float32),
+            "f64" => Some(FloatTy::Float64),
+            _ => None,
+        }
+    }
+}
+
+/// A unary operator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnOp {
+    /// `-`
+    Neg,
+
+    /// `!`
+    Not,
+
+    /// `~`
+    BitNot,
+
+    /// `*`
+    Deref,
+
+    /// `&`
+    Ref,
+
+    /// `&mut`
+    RefMut,
+}
+
+/// A binary operator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinOp {
+    /// `+`
+    Add,
+
+    /// `-`
+    Sub,
+
+    /// `*`
+    Mul,
+
+    /// `/`
+    Div,
+
+    /// `%`
+    Rem,
+
+    /// `&`
+    BitAnd,
+
+    /// `|`
+    BitOr,
+
+    /// `^`
+    BitXor,
+
+    /// `<<`
+    Shl,
+
+    /// `>>`
+    Shr,
+
+    /// `==`
+    Eq,
+
+    /// `!=`
+    Ne,
+
+    /// `<`
+    Lt,
+
+    /// `<=`
+    Le,
+
+    /// `>`
+    Gt,
+
+    /// `>=`
+    Ge,
+
+    /// `&&`
+    And,
+
+    /// `||`
+    Or,
+}
+
+impl BinOp {
+    /// Returns the operator as it is written, such as `+`.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Rem => "%",
+            BinOp::BitAnd => "&",
+            BinOp::BitOr => "|",
+            BinOp::BitXor => "^",
+            BinOp::Shl => "<<",
+            BinOp::Shr => ">>",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!=",
+            BinOp::Lt => "<",
+            BinOp::Le => "<=",
+            BinOp::Gt => ">",
+            BinOp::Ge => ">=",
+            BinOp::And => "&&",
+            BinOp::Or => "||",
+        }
+    }
+}
+
+/// A struct literal.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StructLit {
+    /// The struct being constructed, such as `Self` or `Point`.
+    pub path: Path,
+
+    /// The field initializers.
+    pub fields: Vec<FieldInit>,
+}
+
+/// A field initializer in a struct literal.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldInit {
+    /// The field being initialized.
+    pub name: Iden,
+
+    /// The value of the field.
+    pub value: Expr,
+
+    /// Whether the value is written as just the name of the field, as in `Point::{ x, y }`, in
+    /// which case it is the variable with that name.
+    pub shorthand: bool,
+
+    /// The location of the initializer.
+    pub loc: Loc,
+}
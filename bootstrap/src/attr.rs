@@ -0,0 +1,258 @@
+//! The attributes the compiler understands.
+//!
+//! Attributes are parsed without knowing what they mean.  The [`AttributeRegistry`] holds the
+//! known attributes, which passes look up by name, and reports every attribute it doesn't know.
+
+use crate::ast::{Attribute, Module, TokenTree};
+use crate::diagnostic::{similar_name, Diagnostic, Diagnostics};
+use crate::visit::Visitor;
+use crate::Loc;
+
+/// An attribute the compiler understands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AttributeInfo {
+    /// The name of the attribute, with `::` between path segments.
+    pub name: &'static str,
+
+    /// How the attribute is written, such as `allow(lint, ..)`.
+    pub template: &'static str,
+
+    /// What the attribute does.
+    pub description: &'static str,
+}
+
+/// The attributes that are always known.
+pub const BUILTIN_ATTRIBUTES: &[AttributeInfo] = &[
+    AttributeInfo {
+        name: "allow",
+        template: "allow(lint, ..)",
+        description: "silences the named lints",
+    },
+    AttributeInfo {
+        name: "warn",
+        template: "warn(lint, ..)",
+        description: "reports the named lints as warnings",
+    },
+    AttributeInfo {
+        name: "deny",
+        template: "deny(lint, ..)",
+        description: "reports the named lints as errors",
+    },
+    AttributeInfo {
+        name: "derive",
+        template: "derive(Trait, ..)",
+        description: "implements the named traits for a struct from its fields",
+    },
+];
+
+/// The set of known attributes.
+#[derive(Clone, Debug)]
+pub struct AttributeRegistry {
+    /// The known attributes, in the order they were registered.
+    attrs: Vec<AttributeInfo>,
+}
+
+impl Default for AttributeRegistry {
+    fn default() -> Self {
+        Self {
+            attrs: BUILTIN_ATTRIBUTES.to_vec(),
+        }
+    }
+}
+
+impl AttributeRegistry {
+    /// Creates a registry of the builtin attributes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an attribute, replacing any known attribute with the same name.
+    pub fn register(&mut self, info: AttributeInfo) {
+        match self.attrs.iter_mut().find(|attr| attr.name == info.name) {
+            Some(attr) => *attr = info,
+            None => self.attrs.push(info),
+        }
+    }
+
+    /// Returns the known attribute called `name`.
+    pub fn get(&self, name: &str) -> Option<&AttributeInfo> {
+        self.attrs.iter().find(|attr| attr.name == name)
+    }
+
+    /// Returns the known attribute that `attr` refers to.
+    pub fn lookup(&self, attr: &Attribute) -> Option<&AttributeInfo> {
+        self.get(&attr_name(attr))
+    }
+
+    /// Returns the known attributes.
+    pub fn iter(&self) -> impl Iterator<Item = &AttributeInfo> {
+        self.attrs.iter()
+    }
+
+    /// Reports every attribute in `module` that isn't known.
+    pub fn check(&self, module: &Module, diagnostics: &mut Diagnostics) {
+        Checker {
+            registry: self,
+            diagnostics,
+        }
+        .visit_module(module);
+    }
+
+    /// Returns the error for the unknown attribute `attr`.
+    fn unknown(&self, attr: &Attribute) -> Diagnostic {
+        let name = attr_name(attr);
+        let diagnostic = Diagnostic::error(
+            format!("unknown attribute `{}`", name),
+            attr.path.loc.clone(),
+        )
+        .with_label("unknown attribute");
+
+        match similar_name(&name, self.attrs.iter().map(|attr| attr.name)) {
+            Some(similar) => diagnostic.with_suggestion(
+                format!("a known attribute has a similar name: `{}`", similar),
+                attr.path.loc.clone(),
+                similar,
+            ),
+            None => {
+                let mut names: Vec<String> = self
+                    .attrs
+                    .iter()
+                    .map(|attr| format!("`{}`", attr.name))
+                    .collect();
+                let list = match names.pop() {
+                    Some(last) if !names.is_empty() => format!("{} and {}", names.join(", "), last),
+                    Some(last) => last,
+                    None => "none".to_string(),
+                };
+                diagnostic.with_note(format!("the known attributes are {}", list))
+            }
+        }
+    }
+}
+
+/// Returns the name of `attr`, with `::` between path segments.
+pub fn attr_name(attr: &Attribute) -> String {
+    let segments: Vec<&str> = attr
+        .path
+        .segments
+        .iter()
+        .map(|segment| segment.name.as_str())
+        .collect();
+    segments.join("::")
+}
+
+/// Returns the names in the arguments of `attr`, such as `unsafe_code` and `missing_return` in
+/// `#allow(unsafe_code, missing_return)`, along with their locations.  `what` is what the names
+/// refer to, such as `"lint"`.  A missing list and arguments that aren't names are reported to
+/// `diagnostics` and left out.
+pub fn arg_names<'a>(
+    attr: &'a Attribute,
+    what: &str,
+    diagnostics: &mut Diagnostics,
+) -> Vec<(&'a str, &'a Loc)> {
+    let args = attr.arg_list();
+    if args.is_empty() {
+        let name = attr_name(attr);
+        let mut diagnostic = Diagnostic::error(
+            format!("`{}` expects the names of {}s", name, what),
+            attr.loc.clone(),
+        );
+        if let Some(info) = BUILTIN_ATTRIBUTES.iter().find(|info| info.name == name) {
+            diagnostic = diagnostic.with_help(format!("write it as `#{}`", info.template));
+        }
+        diagnostics.push(diagnostic);
+    }
+
+    let mut names = Vec::new();
+    for arg in args {
+        match arg {
+            [TokenTree::Token(name, loc)] if name.starts_with(char::is_alphabetic) => {
+                names.push((name.as_str(), loc));
+            }
+            _ => {
+                let loc = Loc::new(
+                    attr.loc.file,
+                    arg[0].loc().span.start..arg[arg.len() - 1].loc().span.end,
+                );
+                diagnostics.push(
+                    Diagnostic::error(format!("expected the name of a {}", what), loc)
+                        .with_label(format!("not a {} name", what)),
+                );
+            }
+        }
+    }
+    names
+}
+
+/// The visitor that reports unknown attributes.
+struct Checker<'a> {
+    /// The known attributes.
+    registry: &'a AttributeRegistry,
+
+    /// Where unknown attributes are reported.
+    diagnostics: &'a mut Diagnostics,
+}
+
+impl Visitor for Checker<'_> {
+    fn visit_attribute(&mut self, attr: &Attribute) {
+        if self.registry.lookup(attr).is_none() {
+            self.diagnostics.push(self.registry.unknown(attr));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+    use crate::source::SourceMap;
+
+    /// Returns the names in the arguments of the first attribute of `src`, and the messages of
+    /// the errors reported for it.
+    fn names(src: &str, what: &str) -> (Vec<String>, Vec<String>) {
+        let mut sources = SourceMap::new();
+        let file = sources.add("test.hl", src.to_string());
+        let mut diagnostics = Diagnostics::new();
+        let module = parser::parse(&sources, file, &mut diagnostics).expect("the source parses");
+        let names = arg_names(&module.items[0].attrs[0], what, &mut diagnostics)
+            .into_iter()
+            .map(|(name, loc)| {
+                assert_eq!(&src[loc.span.clone()], name);
+                name.to_string()
+            })
+            .collect();
+        let errors = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.clone())
+            .collect();
+        (names, errors)
+    }
+
+    #[test]
+    fn names_in_arguments() {
+        assert_eq!(
+            names("#allow(unsafe_code, shadowing,) fun f() {}", "lint"),
+            (
+                vec!["unsafe_code".to_string(), "shadowing".to_string()],
+                vec![]
+            )
+        );
+        assert_eq!(
+            names("#derive(Default, 1, a::b) struct S {}", "trait"),
+            (
+                vec!["Default".to_string()],
+                vec![
+                    "expected the name of a trait".to_string(),
+                    "expected the name of a trait".to_string()
+                ]
+            )
+        );
+        assert_eq!(
+            names("#deny() fun f() {}", "lint"),
+            (
+                vec![],
+                vec!["`deny` expects the names of lints".to_string()]
+            )
+        );
+    }
+}
@@ -0,0 +1,283 @@
+//! The conversions `as` can make.
+//!
+//! `as` converts between integers of any width, between integers and floats, between integers
+//! and pointers, between pointers and references, and from enums to integers.  A few of these
+//! may lose information, such as narrowing an integer, changing its sign or rounding it to a
+//! float, or may break memory safety, such as turning an integer into a pointer.  Those are
+//! allowed, but reported through the `unsafe_code` lint.  Anything else, such as casting a struct
+//! to an integer, is an error.
+
+use crate::ast::{FloatTy, IntTy, ItemKind};
+use crate::loader::Program;
+use crate::resolve;
+use crate::types::Ty;
+
+/// Whether a cast is allowed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Cast {
+    /// The cast is allowed.
+    Ok,
+
+    /// The cast is allowed, but may lose information or break memory safety.  It is reported
+    /// through the `unsafe_code` lint with the message and label.
+    Unsafe(String, String),
+
+    /// The cast isn't allowed.
+    Invalid,
+}
+
+/// Returns whether a value of type `from` can be cast to `to`, with `int` and `uint` being
+/// `ptr_bits` wide.  Types are formatted in messages with `name`.
+///
+/// `literal` is the value being cast and whether it is negated, if it is an integer literal
+/// whose type hasn't been inferred.  Such a literal takes the type it is cast to, so the cast
+/// only loses information if the value doesn't fit in it.
+pub fn classify(
+    program: &Program,
+    from: &Ty,
+    to: &Ty,
+    literal: Option<(u128, bool)>,
+    ptr_bits: u32,
+    name: impl Fn(&Ty) -> String,
+) -> Cast {
+    let unsafe_ = |message: String, label: &str| Cast::Unsafe(message, label.to_string());
+    match (from, to) {
+        (Ty::Error | Ty::Never, _) | (_, Ty::Error) => Cast::Ok,
+        (from, to) if from == to => Cast::Ok,
+
+        // Literals.
+        (Ty::IntVar(_), Ty::Int(int)) => match literal {
+            Some((value, negative)) if !int.fits(value, negative, ptr_bits) => unsafe_(
+                format!(
+                    "casting `{}{}` to `{}` changes its value",
+                    if negative { "-" } else { "" },
+                    value,
+                    name(to)
+                ),
+                &format!(
+                    "the value doesn't fit in `{}`, so only its low bits are kept",
+                    name(to)
+                ),
+            ),
+            _ => Cast::Ok,
+        },
+
+        // Numbers.
+        (Ty::Int(from_int), Ty::Int(to_int)) if to_int.bits(ptr_bits) < from_int.bits(ptr_bits) => {
+            unsafe_(
+                format!(
+                    "casting `{}` to `{}` may truncate the value",
+                    name(from),
+                    name(to)
+                ),
+                "the value is cut down to the low bits if it doesn't fit",
+            )
+        }
+        (Ty::Int(from_int), Ty::Int(to_int)) if from_int.signed() && !to_int.signed() => unsafe_(
+            format!(
+                "casting `{}` to `{}` may change the sign of the value",
+                name(from),
+                name(to)
+            ),
+            "negative values become large positive ones",
+        ),
+        (Ty::Int(from_int), Ty::Int(to_int))
+            if !from_int.signed()
+                && to_int.signed()
+                && to_int.bits(ptr_bits) == from_int.bits(ptr_bits) =>
+        {
+            unsafe_(
+                format!(
+                    "casting `{}` to `{}` may change the sign of the value",
+                    name(from),
+                    name(to)
+                ),
+                "values too large for the signed type become negative",
+            )
+        }
+        (Ty::Int(int), Ty::Float(float)) if value_bits(*int, ptr_bits) > mantissa_bits(*float) => {
+            unsafe_(
+                format!(
+                    "casting `{}` to `{}` may lose precision",
+                    name(from),
+                    name(to)
+                ),
+                "large values are rounded to the nearest value that fits",
+            )
+        }
+        (Ty::Float(_) | Ty::FloatVar(_), Ty::Int(_)) => unsafe_(
+            format!(
+                "casting `{}` to `{}` loses the fractional part",
+                name(from),
+                name(to)
+            ),
+            "the value is rounded toward zero, and clamped if it doesn't fit",
+        ),
+        (Ty::Float(FloatTy::Float64), Ty::Float(FloatTy::Float32)) => unsafe_(
+            format!(
+                "casting `{}` to `{}` may lose precision",
+                name(from),
+                name(to)
+            ),
+            "the value is rounded to the nearest value that fits",
+        ),
+        (from, to) if from.is_numeric() && to.is_numeric() => Cast::Ok,
+
+        // Pointers and integers.
+        (Ty::Int(_) | Ty::IntVar(_), Ty::Ptr(..)) => unsafe_(
+            format!("casting an integer to `{}` is unsafe", name(to)),
+            "nothing guarantees that this address is valid memory",
+        ),
+        (Ty::Ptr(..), Ty::Int(int)) if int.bits(ptr_bits) < ptr_bits => unsafe_(
+            format!(
+                "casting `{}` to `{}` may truncate the address",
+                name(from),
+                name(to)
+            ),
+            "pointers are wider than this integer type",
+        ),
+        (Ty::Ptr(..), Ty::Int(_)) => Cast::Ok,
+
+        // Pointers and references.
+        (Ty::Ptr(false, _) | Ty::Ref(false, _), Ty::Ptr(true, _)) => unsafe_(
+            format!("casting `{}` to `{}` is unsafe", name(from), name(to)),
+            "this allows writing through something that isn't `mut`",
+        ),
+        (Ty::Ptr(..) | Ty::Ref(..), Ty::Ptr(..)) => Cast::Ok,
+        (Ty::Ptr(..), Ty::Ref(..)) => unsafe_(
+            format!("casting `{}` to `{}` is unsafe", name(from), name(to)),
+            "the pointer may be null or point to freed memory",
+        ),
+        (Ty::Ref(false, _), Ty::Ref(true, _)) => unsafe_(
+            format!("casting `{}` to `{}` is unsafe", name(from), name(to)),
+            "this allows writing through a reference that isn't `mut`",
+        ),
+        (Ty::Ref(_, from_pointee), Ty::Ref(_, to_pointee)) if from_pointee != to_pointee => {
+            unsafe_(
+                format!("casting `{}` to `{}` is unsafe", name(from), name(to)),
+                "this reads the memory as a different type",
+            )
+        }
+        (Ty::Ref(..), Ty::Ref(..)) => Cast::Ok,
+
+        // Enums.
+        (Ty::Adt(id, _), Ty::Int(_))
+            if matches!(resolve::item(program, *id).kind, ItemKind::Enum(_)) =>
+        {
+            Cast::Ok
+        }
+
+        _ => Cast::Invalid,
+    }
+}
+
+/// Returns how many bits of `int` hold its magnitude, with `int` and `uint` being `ptr_bits`
+/// wide.
+fn value_bits(int: IntTy, ptr_bits: u32) -> u32 {
+    int.bits(ptr_bits) - int.signed() as u32
+}
+
+/// Returns how many bits of precision `float` has, counting the implicit leading bit, which is
+/// the widest integer it holds exactly.
+fn mantissa_bits(float: FloatTy) -> u32 {
+    match float {
+        FloatTy::Float32 => 24,
+        FloatTy::Float64 => 53,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Classifies the cast of `from` to `to` on a 64-bit target.
+    fn classify_64(from: Ty, to: Ty, literal: Option<(u128, bool)>) -> Cast {
+        let program = Program {
+            modules: Vec::new(),
+        };
+        classify(&program, &from, &to, literal, 64, |ty| format!("{:?}", ty))
+    }
+
+    /// Returns whether casting `from` to `to` is allowed without a lint.
+    fn ok(from: Ty, to: Ty) -> bool {
+        classify_64(from, to, None) == Cast::Ok
+    }
+
+    /// Returns whether casting `from` to `to` is reported through the `unsafe_code` lint.
+    fn lossy(from: Ty, to: Ty) -> bool {
+        matches!(classify_64(from, to, None), Cast::Unsafe(..))
+    }
+
+    #[test]
+    fn widening_integers() {
+        assert!(ok(Ty::Int(IntTy::Int8), Ty::Int(IntTy::Int64)));
+        assert!(ok(Ty::Int(IntTy::Uint8), Ty::Int(IntTy::Uint32)));
+        assert!(ok(Ty::Int(IntTy::Uint16), Ty::Int(IntTy::Int32)));
+        assert!(ok(Ty::Int(IntTy::Uint32), Ty::Int(IntTy::Int)));
+    }
+
+    #[test]
+    fn narrowing_integers() {
+        assert!(lossy(Ty::Int(IntTy::Int64), Ty::Int(IntTy::Int32)));
+        assert!(lossy(Ty::Int(IntTy::Uint16), Ty::Int(IntTy::Uint8)));
+        assert!(lossy(Ty::Int(IntTy::Int), Ty::Int(IntTy::Int32)));
+    }
+
+    #[test]
+    fn changing_signedness() {
+        assert!(lossy(Ty::Int(IntTy::Int32), Ty::Int(IntTy::Uint32)));
+        assert!(lossy(Ty::Int(IntTy::Int8), Ty::Int(IntTy::Uint64)));
+        assert!(lossy(Ty::Int(IntTy::Uint32), Ty::Int(IntTy::Int32)));
+        assert!(lossy(Ty::Int(IntTy::Uint), Ty::Int(IntTy::Int)));
+    }
+
+    #[test]
+    fn integers_to_floats() {
+        let float32 = || Ty::Float(FloatTy::Float32);
+        let float64 = || Ty::Float(FloatTy::Float64);
+        assert!(ok(Ty::Int(IntTy::Int16), float32()));
+        assert!(ok(Ty::Int(IntTy::Uint16), float32()));
+        assert!(ok(Ty::Int(IntTy::Int32), float64()));
+        assert!(lossy(Ty::Int(IntTy::Int32), float32()));
+        assert!(lossy(Ty::Int(IntTy::Int64), float32()));
+        assert!(lossy(Ty::Int(IntTy::Int64), float64()));
+        assert!(lossy(Ty::Int(IntTy::Uint64), float64()));
+    }
+
+    #[test]
+    fn floats() {
+        let float32 = || Ty::Float(FloatTy::Float32);
+        let float64 = || Ty::Float(FloatTy::Float64);
+        assert!(ok(float32(), float64()));
+        assert!(lossy(float64(), float32()));
+        assert!(lossy(float64(), Ty::Int(IntTy::Int64)));
+    }
+
+    #[test]
+    fn literals() {
+        let uint8 = || Ty::Int(IntTy::Uint8);
+        let lit = |value, negative| classify_64(Ty::IntVar(0), uint8(), Some((value, negative)));
+        assert_eq!(lit(255, false), Cast::Ok);
+        assert_eq!(lit(0, true), Cast::Ok);
+        assert!(matches!(lit(256, false), Cast::Unsafe(..)));
+        assert!(matches!(lit(1, true), Cast::Unsafe(..)));
+
+        let int8 = classify_64(Ty::IntVar(0), Ty::Int(IntTy::Int8), Some((128, true)));
+        assert_eq!(int8, Cast::Ok);
+        let uint = classify_64(Ty::IntVar(0), Ty::Int(IntTy::Uint), Some((1, true)));
+        assert!(matches!(uint, Cast::Unsafe(..)));
+    }
+
+    #[test]
+    fn pointers() {
+        let ptr = |mutable| Ty::Ptr(mutable, Box::new(Ty::Int(IntTy::Uint8)));
+        let reference = |mutable| Ty::Ref(mutable, Box::new(Ty::Int(IntTy::Uint8)));
+        assert!(ok(ptr(true), Ty::Int(IntTy::Uint)));
+        assert!(lossy(ptr(true), Ty::Int(IntTy::Uint32)));
+        assert!(lossy(Ty::Int(IntTy::Uint), ptr(false)));
+        assert!(ok(reference(true), ptr(true)));
+        assert!(lossy(reference(false), ptr(true)));
+        assert!(lossy(ptr(false), reference(false)));
+        assert_eq!(classify_64(Ty::Bool, ptr(false), None), Cast::Invalid);
+    }
+}
@@ -0,0 +1,439 @@
+//! Parsing of the `hail` command line.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::diagnostic::Format;
+use crate::lint::Level;
+use crate::target::Target;
+
+/// The help text printed by `hail --help`.
+pub const USAGE: &str = "\
+usage: hail <command> [options] <file> [-- <args>...]
+
+commands:
+    build    compile <file> into an executable
+    check    check <file> for errors without compiling it
+    run      compile <file>, then run it with <args>
+    parse    check the syntax of <file>
+
+options:
+    -o, --output <path>         write the output to <path>
+    --target <name>             compile for the target called <name>
+    -I <dir>                    look for imported modules in <dir>
+    --message-format <format>   print diagnostics as `human` text or `json`
+    --dump-ast                  print the syntax tree (`parse` only)
+    -W <lint>                   report <lint> as a warning
+    -A <lint>                   silence <lint>; `-A warnings` silences every warning
+    -D <lint>                   report <lint> as an error
+    --deny-warnings             report every warning as an error
+    -h, --help                  print this help
+    -V, --version               print the version
+
+environment:
+    HAIL_STDLIB                 the directory of the standard library";
+
+/// What the driver is asked to do with the input file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Compile the file into an executable.
+    Build,
+
+    /// Check the file for errors.
+    Check,
+
+    /// Compile the file and run the executable.
+    Run,
+
+    /// Only parse the file.
+    Parse,
+}
+
+impl Command {
+    /// Returns the command called `name`.
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "build" => Some(Command::Build),
+            "check" => Some(Command::Check),
+            "run" => Some(Command::Run),
+            "parse" => Some(Command::Parse),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the command.
+    pub fn name(self) -> &'static str {
+        match self {
+            Command::Build => "build",
+            Command::Check => "check",
+            Command::Run => "run",
+            Command::Parse => "parse",
+        }
+    }
+
+    /// Returns whether the command writes an output file.
+    fn has_output(self) -> bool {
+        matches!(self, Command::Build | Command::Run | Command::Parse)
+    }
+}
+
+/// A `-W`, `-A` or `-D` flag.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LintFlag {
+    /// The level the lint is set to.
+    pub level: Level,
+
+    /// The name of the lint, or `warnings` for every lint that is reported.
+    pub name: String,
+}
+
+impl LintFlag {
+    /// Returns the flag as it is written on the command line, without the lint name.
+    pub fn flag(&self) -> &'static str {
+        match self.level {
+            Level::Warn => "-W",
+            Level::Allow => "-A",
+            Level::Deny => "-D",
+        }
+    }
+}
+
+/// The options of a compilation.
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// What to do with the input file.
+    pub command: Command,
+
+    /// The input file.
+    pub input: PathBuf,
+
+    /// Where to write the output, if not to the default place.
+    pub output: Option<PathBuf>,
+
+    /// The target to compile for.
+    pub target: Target,
+
+    /// The `-I` directories, where imported modules are looked for after the directory of the
+    /// importing file and the standard library.
+    pub search_paths: Vec<PathBuf>,
+
+    /// How diagnostics are printed.
+    pub format: Format,
+
+    /// Whether to print the syntax tree.
+    pub dump_ast: bool,
+
+    /// The `-W`, `-A` and `-D` flags, in the order they were given.  Later flags override
+    /// earlier ones.
+    pub lints: Vec<LintFlag>,
+
+    /// Whether every warning is reported as an error.
+    pub deny_warnings: bool,
+
+    /// The arguments passed to the program by `hail run`.
+    pub args: Vec<String>,
+}
+
+/// What the command line asks for.
+#[derive(Clone, Debug)]
+pub enum Action {
+    /// Run a compilation.
+    Compile(Options),
+
+    /// Print the help text.
+    Help,
+
+    /// Print the version.
+    Version,
+}
+
+/// An invalid command line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CliError(String);
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Returns a [`CliError`] from `format!`-style arguments.
+macro_rules! cli_error {
+    ($($arg:tt)*) => {
+        CliError(format!($($arg)*))
+    };
+}
+
+/// Parses the command line arguments `args`, which don't include the program name.
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Action, CliError> {
+    let mut args = args.into_iter();
+    let command = match args.next().as_deref() {
+        None | Some("-h" | "--help" | "help") => return Ok(Action::Help),
+        Some("-V" | "--version") => return Ok(Action::Version),
+        Some(name) => {
+            Command::from_name(name).ok_or_else(|| cli_error!("unknown command `{}`", name))?
+        }
+    };
+
+    let mut input = None;
+    let mut output = None;
+    let mut target = Target::host();
+    let mut search_paths = Vec::new();
+    let mut format = Format::Human;
+    let mut dump_ast = false;
+    let mut lints = Vec::new();
+    let mut deny_warnings = false;
+    let mut program_args = Vec::new();
+
+    while let Some(arg) = args.next() {
+        // Options that take a value accept it either as the next argument or after `=`.
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = |name: &str| {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| cli_error!("`{}` expects a value", name))
+        };
+
+        match flag {
+            "-h" | "--help" => return Ok(Action::Help),
+            "-V" | "--version" => return Ok(Action::Version),
+            "-o" | "--output" => output = Some(PathBuf::from(value(flag)?)),
+            "--target" => {
+                let name = value(flag)?;
+                target = Target::find(&name).ok_or_else(|| {
+                    let names: Vec<&str> = Target::ALL.iter().map(|target| target.name).collect();
+                    cli_error!(
+                        "unknown target `{}`; the supported targets are {}",
+                        name,
+                        names.join(", ")
+                    )
+                })?;
+            }
+            "--message-format" => {
+                format = match value(flag)?.as_str() {
+                    "human" => Format::Human,
+                    "json" => Format::Json,
+                    other => {
+                        return Err(cli_error!(
+                            "unknown message format `{}`; expected `human` or `json`",
+                            other
+                        ))
+                    }
+                }
+            }
+            _ if flag.starts_with("-I") => match &flag[2..] {
+                "" => search_paths.push(PathBuf::from(value(flag)?)),
+                dir => search_paths.push(PathBuf::from(dir)),
+            },
+            "--dump-ast" => dump_ast = true,
+            "--deny-warnings" => deny_warnings = true,
+            "--" => program_args.extend(args.by_ref()),
+            _ if ["-W", "-A", "-D"]
+                .iter()
+                .any(|prefix| flag.starts_with(prefix)) =>
+            {
+                let level = match flag.as_bytes()[1] {
+                    b'W' => Level::Warn,
+                    b'A' => Level::Allow,
+                    _ => Level::Deny,
+                };
+                let name = match &flag[2..] {
+                    "" => value(flag)?,
+                    name => name.to_string(),
+                };
+                lints.push(LintFlag { level, name });
+            }
+            _ if flag.starts_with('-') => return Err(cli_error!("unknown option `{}`", flag)),
+            _ if input.is_some() => return Err(cli_error!("unexpected argument `{}`", arg)),
+            _ => input = Some(PathBuf::from(arg)),
+        }
+    }
+
+    let input = input.ok_or_else(|| cli_error!("`hail {}` expects a file", command.name()))?;
+    if dump_ast && command != Command::Parse {
+        return Err(cli_error!(
+            "`--dump-ast` can only be used with `hail parse`"
+        ));
+    }
+    if output.is_some() && !command.has_output() {
+        return Err(cli_error!(
+            "`hail {}` doesn't write any output",
+            command.name()
+        ));
+    }
+    if !program_args.is_empty() && command != Command::Run {
+        return Err(cli_error!(
+            "only `hail run` passes arguments to the program"
+        ));
+    }
+
+    Ok(Action::Compile(Options {
+        command,
+        input,
+        output,
+        target,
+        search_paths,
+        format,
+        dump_ast,
+        lints,
+        deny_warnings,
+        args: program_args,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses the command line `args`.
+    fn parse(args: &[&str]) -> Result<Action, CliError> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    /// Parses the command line `args`, which must ask for a compilation.
+    fn compile(args: &[&str]) -> Options {
+        match parse(args) {
+            Ok(Action::Compile(options)) => options,
+            other => panic!("expected a compilation, found {:?}", other),
+        }
+    }
+
+    /// Parses the command line `args`, which must be invalid, and returns the error message.
+    fn error(args: &[&str]) -> String {
+        match parse(args) {
+            Err(error) => error.to_string(),
+            Ok(action) => panic!("expected an error, found {:?}", action),
+        }
+    }
+
+    #[test]
+    fn commands() {
+        let options = compile(&["check", "main.hl"]);
+        assert_eq!(options.command, Command::Check);
+        assert_eq!(options.input, PathBuf::from("main.hl"));
+        assert_eq!(options.format, Format::Human);
+        assert!(matches!(parse(&[]), Ok(Action::Help)));
+        assert!(matches!(parse(&["build", "--help"]), Ok(Action::Help)));
+        assert!(matches!(parse(&["-V"]), Ok(Action::Version)));
+        assert_eq!(error(&["compile", "main.hl"]), "unknown command `compile`");
+    }
+
+    #[test]
+    fn input_file() {
+        assert_eq!(error(&["check"]), "`hail check` expects a file");
+        assert_eq!(
+            error(&["run", "--target", "x86"]),
+            "`hail run` expects a file"
+        );
+        assert_eq!(
+            error(&["check", "main.hl", "other.hl"]),
+            "unexpected argument `other.hl`"
+        );
+        assert_eq!(
+            error(&["check", "main.hl", "--frobnicate"]),
+            "unknown option `--frobnicate`"
+        );
+    }
+
+    #[test]
+    fn option_values() {
+        let options = compile(&[
+            "build",
+            "-o",
+            "out",
+            "--target=x86",
+            "-Ilib",
+            "-I",
+            "vendor",
+            "--message-format",
+            "json",
+            "main.hl",
+        ]);
+        assert_eq!(options.output, Some(PathBuf::from("out")));
+        assert_eq!(options.target.name, "x86");
+        assert_eq!(
+            options.search_paths,
+            [PathBuf::from("lib"), PathBuf::from("vendor")]
+        );
+        assert_eq!(options.format, Format::Json);
+
+        assert_eq!(error(&["build", "main.hl", "-o"]), "`-o` expects a value");
+        assert!(error(&["build", "main.hl", "--target", "pdp11"])
+            .starts_with("unknown target `pdp11`; the supported targets are "));
+        assert_eq!(
+            error(&["check", "main.hl", "--message-format=xml"]),
+            "unknown message format `xml`; expected `human` or `json`"
+        );
+        assert_eq!(
+            error(&["check", "main.hl", "-o", "out"]),
+            "`hail check` doesn't write any output"
+        );
+    }
+
+    #[test]
+    fn lint_flags() {
+        let options = compile(&[
+            "check",
+            "-Wunused_variable",
+            "-A",
+            "warnings",
+            "main.hl",
+            "-D",
+            "unsafe_code",
+            "--deny-warnings",
+        ]);
+        let flag = |level, name: &str| LintFlag {
+            level,
+            name: name.to_string(),
+        };
+        assert_eq!(
+            options.lints,
+            [
+                flag(Level::Warn, "unused_variable"),
+                flag(Level::Allow, "warnings"),
+                flag(Level::Deny, "unsafe_code"),
+            ]
+        );
+        assert!(options.deny_warnings);
+        assert_eq!(options.lints[2].flag(), "-D");
+
+        // The next argument is the lint name even if it looks like an option.
+        assert_eq!(
+            compile(&["check", "main.hl", "-W", "-x"]).lints,
+            [flag(Level::Warn, "-x")]
+        );
+        assert_eq!(error(&["check", "main.hl", "-A"]), "`-A` expects a value");
+    }
+
+    #[test]
+    fn dump_ast() {
+        assert!(compile(&["parse", "--dump-ast", "main.hl"]).dump_ast);
+        assert!(!compile(&["parse", "main.hl"]).dump_ast);
+        assert_eq!(
+            error(&["check", "--dump-ast", "main.hl"]),
+            "`--dump-ast` can only be used with `hail parse`"
+        );
+    }
+
+    #[test]
+    fn program_arguments() {
+        let options = compile(&["run", "main.hl", "--", "-o", "--help", "file.hl"]);
+        assert_eq!(options.input, PathBuf::from("main.hl"));
+        assert_eq!(options.output, None);
+        assert_eq!(options.args, ["-o", "--help", "file.hl"]);
+
+        assert!(compile(&["run", "main.hl", "--"]).args.is_empty());
+        assert_eq!(
+            error(&["check", "main.hl", "--", "x"]),
+            "only `hail run` passes arguments to the program"
+        );
+        assert_eq!(
+            error(&["run", "--", "main.hl"]),
+            "`hail run` expects a file"
+        );
+    }
+}
@@ -0,0 +1,594 @@
+//! Diagnostics reported to the user, and their rendering.
+//!
+//! Diagnostics are rendered either for humans, in the same style as rustc, or as JSON with one
+//! diagnostic per line for editor tooling.
+
+use std::fmt::{self, Write as _};
+use std::io::{self, Write};
+
+use crate::source::SourceMap;
+use crate::Loc;
+
+/// How serious a diagnostic is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Extra information.
+    Note,
+
+    /// A possible problem that doesn't stop compilation.
+    Warning,
+
+    /// A problem that stops compilation.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Note => "note",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// A labeled location in a diagnostic.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Label {
+    /// The location being labeled.
+    pub loc: Loc,
+
+    /// The text of the label, which may be empty.
+    pub message: String,
+}
+
+/// A suggested replacement of the source at a location.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Suggestion {
+    /// What the suggestion does, such as "convert with `as`".
+    pub message: String,
+
+    /// The source to replace.  An empty span is an insertion.
+    pub loc: Loc,
+
+    /// The replacement source.
+    pub replacement: String,
+}
+
+/// A diagnostic reported to the user.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /// How serious the diagnostic is.
+    pub severity: Severity,
+
+    /// The main message.
+    pub message: String,
+
+    /// The primary location.  Its message may be empty.
+    pub primary: Label,
+
+    /// Other relevant locations.
+    pub secondary: Vec<Label>,
+
+    /// Notes giving extra context.
+    pub notes: Vec<String>,
+
+    /// Help text explaining how to fix the problem.
+    pub help: Vec<String>,
+
+    /// Suggested replacements that fix the problem.
+    pub suggestions: Vec<Suggestion>,
+
+    /// The name of the lint that reported the diagnostic, if a lint did.  Its level is already
+    /// settled, so the `warnings` flags don't apply to it again.
+    pub lint: Option<&'static str>,
+}
+
+impl Diagnostic {
+    /// Creates a diagnostic with no labels, notes or suggestions.
+    pub fn new(severity: Severity, message: impl Into<String>, loc: Loc) -> Self {
+        Self {
+            severity,
+            message: message.into(),
+            primary: Label {
+                loc,
+                message: String::new(),
+            },
+            secondary: Vec::new(),
+            notes: Vec::new(),
+            help: Vec::new(),
+            suggestions: Vec::new(),
+            lint: None,
+        }
+    }
+
+    /// Creates an error.
+    pub fn error(message: impl Into<String>, loc: Loc) -> Self {
+        Self::new(Severity::Error, message, loc)
+    }
+
+    /// Creates a warning.
+    pub fn warning(message: impl Into<String>, loc: Loc) -> Self {
+        Self::new(Severity::Warning, message, loc)
+    }
+
+    /// Creates a note.
+    pub fn note(message: impl Into<String>, loc: Loc) -> Self {
+        Self::new(Severity::Note, message, loc)
+    }
+
+    /// Sets the label of the primary location.
+    pub fn with_label(mut self, message: impl Into<String>) -> Self {
+        self.primary.message = message.into();
+        self
+    }
+
+    /// Adds a labeled secondary location.
+    pub fn with_secondary(mut self, loc: Loc, message: impl Into<String>) -> Self {
+        self.secondary.push(Label {
+            loc,
+            message: message.into(),
+        });
+        self
+    }
+
+    /// Adds a note.
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Adds help text.
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help.push(help.into());
+        self
+    }
+
+    /// Adds a suggestion to replace the source at `loc` with `replacement`.
+    pub fn with_suggestion(
+        mut self,
+        message: impl Into<String>,
+        loc: Loc,
+        replacement: impl Into<String>,
+    ) -> Self {
+        self.suggestions.push(Suggestion {
+            message: message.into(),
+            loc,
+            replacement: replacement.into(),
+        });
+        self
+    }
+
+    /// Renders the diagnostic for humans, with source snippets.
+    pub fn render(&self, sources: &SourceMap) -> String {
+        let mut labels = vec![(&self.primary, true)];
+        labels.extend(self.secondary.iter().map(|label| (label, false)));
+
+        // Every snippet shares a gutter wide enough for the largest line number.
+        let gutter = labels
+            .iter()
+            .map(|(label, _)| sources.range(&label.loc).1.line)
+            .chain(self.suggestions.iter().map(|suggestion| {
+                let lines = suggestion.replacement.matches('\n').count();
+                sources.range(&suggestion.loc).0.line + lines
+            }))
+            .max()
+            .unwrap_or(1)
+            .to_string()
+            .len();
+        let pad = " ".repeat(gutter);
+
+        let mut out = format!("{}: {}\n", self.severity, self.message);
+        let _ = writeln!(out, "{}--> {}", pad, sources.describe(&self.primary.loc));
+        let _ = writeln!(out, "{} |", pad);
+
+        // Labels are grouped by file, starting with the file of the primary location.
+        let mut files = vec![self.primary.loc.file];
+        for (label, _) in &labels {
+            if !files.contains(&label.loc.file) {
+                files.push(label.loc.file);
+            }
+        }
+
+        for (i, &file) in files.iter().enumerate() {
+            let mut in_file: Vec<_> = labels
+                .iter()
+                .filter(|(label, _)| label.loc.file == file)
+                .collect();
+            in_file.sort_by_key(|(label, _)| label.loc.span.start);
+
+            if i > 0 {
+                let _ = writeln!(out, "{}::: {}", pad, sources.describe(&in_file[0].0.loc));
+                let _ = writeln!(out, "{} |", pad);
+            }
+
+            render_snippet(&mut out, sources, &pad, &in_file);
+        }
+
+        if !self.notes.is_empty() || !self.help.is_empty() {
+            let _ = writeln!(out, "{} |", pad);
+        }
+
+        for note in &self.notes {
+            let _ = writeln!(out, "{} = note: {}", pad, note);
+        }
+
+        for help in &self.help {
+            let _ = writeln!(out, "{} = help: {}", pad, help);
+        }
+
+        for suggestion in &self.suggestions {
+            render_suggestion(&mut out, sources, &pad, suggestion);
+        }
+
+        out
+    }
+
+    /// Renders the diagnostic as a single line of JSON.
+    pub fn to_json(&self, sources: &SourceMap) -> String {
+        let mut spans = vec![json_span(sources, &self.primary, true)];
+        spans.extend(
+            self.secondary
+                .iter()
+                .map(|label| json_span(sources, label, false)),
+        );
+
+        let suggestions: Vec<_> = self
+            .suggestions
+            .iter()
+            .map(|suggestion| {
+                format!(
+                    "{{\"message\":{},\"file\":{},\"start\":{},\"end\":{},\"replacement\":{}}}",
+                    json_str(&suggestion.message),
+                    json_str(&sources.get(suggestion.loc.file).path.display().to_string()),
+                    suggestion.loc.span.start,
+                    suggestion.loc.span.end,
+                    json_str(&suggestion.replacement),
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\"severity\":{},\"message\":{},\"spans\":[{}],\"notes\":[{}],\"help\":[{}],\"suggestions\":[{}]}}",
+            json_str(&self.severity.to_string()),
+            json_str(&self.message),
+            spans.join(","),
+            json_list(&self.notes),
+            json_list(&self.help),
+            suggestions.join(","),
+        )
+    }
+}
+
+/// Returns the width `text` takes up when rendered, with tabs expanded to four spaces.
+fn width(text: &str) -> usize {
+    text.chars().map(|c| if c == '\t' { 4 } else { 1 }).sum()
+}
+
+/// Renders the source lines of `labels`, which must all be in the same file and sorted by their
+/// start, underlining each label.
+fn render_snippet(out: &mut String, sources: &SourceMap, pad: &str, labels: &[&(&Label, bool)]) {
+    let file = sources.get(labels[0].0.loc.file);
+    let mut last_line = None;
+
+    // Each label gets its own underline row, below the line it starts on.
+    for (label, primary) in labels {
+        let (start, end) = sources.range(&label.loc);
+        if last_line != Some(start.line) {
+            if last_line.is_some_and(|last| start.line > last + 1) {
+                let _ = writeln!(out, "...");
+            }
+
+            let text = file.line(start.line).replace('\t', "    ");
+            let _ = writeln!(out, "{:>width$} | {}", start.line, text, width = pad.len());
+            last_line = Some(start.line);
+        }
+
+        // Multi-line spans are underlined up to the end of their first line.
+        let line_start = file.line_start(start.line);
+        let line = file.line(start.line);
+        let offset = label.loc.span.start - line_start;
+        let end_offset = if end.line == start.line {
+            label.loc.span.end - line_start
+        } else {
+            line.len()
+        };
+
+        let before = width(&line[..offset.min(line.len())]);
+        let len = width(&line[offset.min(line.len())..end_offset.min(line.len())]).max(1);
+        let mark = if *primary { "^" } else { "-" };
+        let _ = write!(out, "{} | {}{}", pad, " ".repeat(before), mark.repeat(len));
+        if !label.message.is_empty() {
+            let _ = write!(out, " {}", label.message);
+        }
+        out.push('\n');
+    }
+}
+
+/// Renders a suggestion, showing its line with the replacement applied.  A replacement with
+/// line breaks is shown as one row per line, each marked where it was changed.
+fn render_suggestion(out: &mut String, sources: &SourceMap, pad: &str, suggestion: &Suggestion) {
+    let file = sources.get(suggestion.loc.file);
+    let (start, end) = sources.range(&suggestion.loc);
+    let _ = writeln!(out, "help: {}", suggestion.message);
+    let _ = writeln!(out, "{} |", pad);
+
+    if start.line != end.line {
+        let _ = writeln!(out, "{} = {}", pad, suggestion.replacement);
+        return;
+    }
+
+    let line_start = file.line_start(start.line);
+    let line = file.line(start.line);
+    let offset = suggestion.loc.span.start - line_start;
+    let end_offset = suggestion.loc.span.end - line_start;
+    let patched = format!(
+        "{}{}{}",
+        &line[..offset],
+        suggestion.replacement,
+        &line[end_offset..]
+    );
+
+    let mark = if suggestion.loc.span.is_empty() {
+        "+"
+    } else {
+        "~"
+    };
+    let parts: Vec<&str> = suggestion.replacement.split('\n').collect();
+    for (index, (row, part)) in patched.split('\n').zip(&parts).enumerate() {
+        let _ = writeln!(
+            out,
+            "{:>width$} | {}",
+            start.line + index,
+            row.replace('\t', "    "),
+            width = pad.len()
+        );
+
+        // Only the first line starts partway through; the indentation the replacement ends
+        // with isn't worth marking.
+        let before = if index == 0 {
+            width(&line[..offset])
+        } else {
+            0
+        };
+        if parts.len() > 1 && part.trim().is_empty() {
+            continue;
+        }
+        let _ = writeln!(
+            out,
+            "{} | {}{}",
+            pad,
+            " ".repeat(before),
+            mark.repeat(width(part).max(1))
+        );
+    }
+}
+
+/// Renders a label as a JSON span object.
+fn json_span(sources: &SourceMap, label: &Label, primary: bool) -> String {
+    let (start, end) = sources.range(&label.loc);
+    format!(
+        "{{\"file\":{},\"start\":{},\"end\":{},\"line_start\":{},\"col_start\":{},\"line_end\":{},\"col_end\":{},\"label\":{},\"primary\":{}}}",
+        json_str(&sources.get(label.loc.file).path.display().to_string()),
+        label.loc.span.start,
+        label.loc.span.end,
+        start.line,
+        start.col,
+        end.line,
+        end.col,
+        json_str(&label.message),
+        primary,
+    )
+}
+
+/// Renders a list of strings as a JSON array's contents.
+fn json_list(items: &[String]) -> String {
+    items
+        .iter()
+        .map(|item| json_str(item))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Renders a string as a JSON string literal.
+fn json_str(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Formats `count` followed by `noun`, pluralized if needed, as in "2 arguments".
+pub fn plural(count: usize, noun: &str) -> String {
+    match count {
+        1 => format!("1 {}", noun),
+        _ => format!("{} {}s", count, noun),
+    }
+}
+
+/// Returns the candidate closest to `name`, if one is close enough to be a likely typo.  A name
+/// must keep some of its characters to be a typo, so `f` is never suggested for `y`.
+pub fn similar_name<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let len = name.chars().count();
+    let max = (len / 3).max(1);
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= max && distance < len)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Returns the Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let replace = prev[j] + (ca != cb) as usize;
+            row.push(replace.min(prev[j + 1] + 1).min(row[j] + 1));
+        }
+        prev = row;
+    }
+
+    prev[b.len()]
+}
+
+/// How diagnostics are written out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Format {
+    /// rustc-style text with source snippets.
+    #[default]
+    Human,
+
+    /// One JSON object per line.
+    Json,
+}
+
+/// A collection of diagnostics.
+#[derive(Clone, Debug, Default)]
+pub struct Diagnostics {
+    /// The diagnostics, in the order they were reported.
+    list: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    /// Creates an empty collection.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a diagnostic.
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.list.push(diagnostic);
+    }
+
+    /// Returns the diagnostics in the order they were reported.
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.list.iter()
+    }
+
+    /// Returns the diagnostics, which can be changed.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Diagnostic> {
+        self.list.iter_mut()
+    }
+
+    /// Returns how many diagnostics have the severity `severity`.
+    pub fn count(&self, severity: Severity) -> usize {
+        self.list
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    }
+
+    /// Keeps only the diagnostics for which `keep` returns true.
+    pub fn retain(&mut self, keep: impl FnMut(&Diagnostic) -> bool) {
+        self.list.retain(keep);
+    }
+
+    /// Returns whether any errors were reported.
+    pub fn has_errors(&self) -> bool {
+        self.count(Severity::Error) > 0
+    }
+
+    /// Writes every diagnostic to `out` in `format`.
+    pub fn emit(
+        &self,
+        sources: &SourceMap,
+        format: Format,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        for diagnostic in &self.list {
+            match format {
+                Format::Human => writeln!(out, "{}", diagnostic.render(sources))?,
+                Format::Json => writeln!(out, "{}", diagnostic.to_json(sources))?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Extend<Diagnostic> for Diagnostics {
+    fn extend<T: IntoIterator<Item = Diagnostic>>(&mut self, iter: T) {
+        self.list.extend(iter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Renders `suggestion`, which replaces `span` of `src` with `replacement`.
+    fn render(src: &str, span: std::ops::Range<usize>, replacement: &str) -> String {
+        let mut sources = SourceMap::new();
+        let file = sources.add("test.hl", src.to_string());
+        let suggestion = Suggestion {
+            message: "fix it".to_string(),
+            loc: Loc::new(file, span),
+            replacement: replacement.to_string(),
+        };
+        let mut out = String::new();
+        render_suggestion(&mut out, &sources, " ", &suggestion);
+        out
+    }
+
+    #[test]
+    fn suggestion_on_one_line() {
+        let out = render("val x = 1;\n", 4..5, "_x");
+        assert_eq!(out, "help: fix it\n  |\n1 | val _x = 1;\n  |     ~~\n");
+    }
+
+    #[test]
+    fn suggestion_inserting_a_line() {
+        let src = "impl A {\n    fun f() {}\n}\n";
+        let out = render(src, 13..13, "#allow(lint)\n    ");
+        assert_eq!(
+            out,
+            "help: fix it\n  |\n\
+             2 |     #allow(lint)\n  |     ++++++++++++\n\
+             3 |     fun f() {}\n"
+        );
+    }
+
+    #[test]
+    fn suggestion_inserting_a_whole_line() {
+        // Imports are suggested as a line of their own before the first item.
+        let out = render("fun main() {}\n", 0..0, "import { f } from m;\n");
+        assert_eq!(
+            out,
+            "help: fix it\n  |\n\
+             1 | import { f } from m;\n  | ++++++++++++++++++++\n\
+             2 | fun main() {}\n"
+        );
+    }
+
+    #[test]
+    fn similar_names() {
+        let names = ["length", "len", "f", "size_of", "xy"];
+        assert_eq!(similar_name("lenght", names), Some("length"));
+        assert_eq!(similar_name("lem", names), Some("len"));
+        assert_eq!(similar_name("size_if", names), Some("size_of"));
+        assert_eq!(similar_name("x", names), None);
+        assert_eq!(similar_name("y", names), None);
+        assert_eq!(similar_name("xz", names), Some("xy"));
+        assert_eq!(similar_name("capacity", names), None);
+    }
+}
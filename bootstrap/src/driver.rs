@@ -0,0 +1,436 @@
+//! The compiler driver, which runs the passes a command needs and reports the outcome.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{self, ExitCode};
+
+use crate::ast::Module;
+use crate::attr::AttributeRegistry;
+use crate::cli::{Command, Options};
+use crate::diagnostic::{plural, Diagnostic, Diagnostics, Format, Severity};
+use crate::lint::{self, Level, LintContext};
+use crate::loader::{self, Program};
+use crate::parser;
+use crate::resolve;
+use crate::source::SourceMap;
+use crate::typeck::{self, Types};
+use crate::visibility;
+use crate::Loc;
+
+/// The exit code when compilation failed.
+pub const EXIT_FAILURE: u8 = 1;
+
+/// The exit code when the command line was invalid or a file couldn't be read or written.
+pub const EXIT_USAGE: u8 = 2;
+
+/// The state of a single compilation.
+#[derive(Debug)]
+pub struct Session {
+    /// The options of the compilation.
+    pub options: Options,
+
+    /// The files read so far.
+    pub sources: SourceMap,
+
+    /// The diagnostics reported so far.
+    pub diagnostics: Diagnostics,
+
+    /// The attributes the compiler understands.
+    pub attributes: AttributeRegistry,
+
+    /// The lint levels set on the command line and by attributes.
+    pub lints: LintContext,
+}
+
+impl Session {
+    /// Creates a session with nothing read yet.
+    pub fn new(options: Options) -> Self {
+        let lints = LintContext::new(options.lints.clone(), options.deny_warnings);
+        Self {
+            options,
+            lints,
+            sources: SourceMap::new(),
+            diagnostics: Diagnostics::new(),
+            attributes: AttributeRegistry::new(),
+        }
+    }
+
+    /// Runs the command, reports its diagnostics and returns the exit code of the process.
+    ///
+    /// The exit code is 0 on success, [`EXIT_FAILURE`] when errors were reported and
+    /// [`EXIT_USAGE`] when a file couldn't be read or written.
+    pub fn run(mut self) -> ExitCode {
+        self.check_lint_flags();
+
+        let file = match self.sources.load(&self.options.input) {
+            Ok(file) => file,
+            Err(error) => {
+                self.report();
+                eprintln!(
+                    "error: couldn't read `{}`: {}",
+                    self.options.input.display(),
+                    error
+                );
+                return ExitCode::from(EXIT_USAGE);
+            }
+        };
+
+        let module = parser::parse(&self.sources, file, &mut self.diagnostics);
+        let mut dumped = Ok(());
+        let mut program = None;
+        match module {
+            Some(module) if self.options.command == Command::Parse => dumped = self.dump(&module),
+            Some(module) => {
+                let loaded = loader::load(
+                    module,
+                    &self.options.search_paths,
+                    &mut self.sources,
+                    &mut self.diagnostics,
+                );
+                let types = self.check(&loaded);
+                program = Some((loaded, types));
+            }
+            None => {}
+        }
+
+        // Parsing only fails without recovering after reporting an error.
+        let failed = self.report();
+        if let Err(error) = dumped {
+            eprintln!("error: couldn't write the syntax tree: {}", error);
+            return ExitCode::from(EXIT_USAGE);
+        }
+        if failed {
+            return ExitCode::from(EXIT_FAILURE);
+        }
+
+        match (self.options.command, program) {
+            (Command::Build | Command::Run, Some((program, types)))
+                if !self.build(&program, &types) =>
+            {
+                ExitCode::from(EXIT_FAILURE)
+            }
+            (Command::Run, Some(_)) => self.execute(),
+            _ => ExitCode::SUCCESS,
+        }
+    }
+
+    /// Reports the `-W`, `-A` and `-D` flags that name unknown lints.  The flags are added to
+    /// the source map as a file called `<command line>`, for the warnings to point at.
+    fn check_lint_flags(&mut self) {
+        if self
+            .options
+            .lints
+            .iter()
+            .all(|flag| lint::is_known(&flag.name))
+        {
+            return;
+        }
+
+        let mut line = String::new();
+        let mut names = Vec::new();
+        for flag in &self.options.lints {
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(flag.flag());
+            line.push(' ');
+            names.push((&flag.name, line.len()..line.len() + flag.name.len()));
+            line.push_str(&flag.name);
+        }
+
+        let file = self.sources.add("<command line>", line);
+        for (name, span) in names {
+            if !lint::is_known(name) {
+                let diagnostic = lint::unknown_lint(name, Loc::new(file, span));
+                self.diagnostics.push(diagnostic);
+            }
+        }
+    }
+
+    /// Runs the checks after parsing on every module of `program`, returning what they found out
+    /// about its types.
+    fn check(&mut self, program: &Program) -> Types {
+        for module in &program.modules {
+            self.attributes.check(&module.ast, &mut self.diagnostics);
+            self.lints
+                .add_module(&module.ast, &self.sources, &mut self.diagnostics);
+        }
+        visibility::check(program, &self.sources, &mut self.diagnostics);
+        let resolutions = resolve::resolve(program, &self.lints, &mut self.diagnostics);
+        typeck::check(
+            program,
+            &resolutions,
+            self.options.target,
+            &self.sources,
+            &self.lints,
+            &mut self.diagnostics,
+        )
+    }
+
+    /// Prints the syntax tree of `module` if `--dump-ast` was given.
+    fn dump(&self, module: &Module) -> io::Result<()> {
+        if !self.options.dump_ast {
+            return Ok(());
+        }
+
+        match &self.options.output {
+            Some(path) => fs::write(path, format!("{:#?}\n", module)),
+            None => writeln!(io::stdout(), "{:#?}", module),
+        }
+    }
+
+    /// Returns where the executable is written.  By default, this is the name of the input file
+    /// without its extension, in the working directory.
+    fn output_path(&self) -> PathBuf {
+        self.options.output.clone().unwrap_or_else(|| {
+            let stem = self.options.input.file_stem().unwrap_or_default();
+            PathBuf::from(stem).with_extension(std::env::consts::EXE_EXTENSION)
+        })
+    }
+
+    /// Compiles `program`, whose types are `types`, into an executable, returning whether it was
+    /// written.
+    ///
+    /// There is no backend yet, so this reports that nothing could be written.
+    fn build(&self, _program: &Program, _types: &Types) -> bool {
+        eprintln!(
+            "error: code generation for `{}` isn't implemented yet, so `{}` wasn't written",
+            self.options.target.name,
+            self.output_path().display()
+        );
+        false
+    }
+
+    /// Runs the executable written by [`Session::build`], returning its exit code.
+    fn execute(&self) -> ExitCode {
+        let path = self.output_path();
+        match process::Command::new(&path)
+            .args(&self.options.args)
+            .status()
+        {
+            Ok(status) => match status.code() {
+                Some(code) => ExitCode::from(code as u8),
+                None => ExitCode::from(EXIT_FAILURE),
+            },
+            Err(error) => {
+                eprintln!("error: couldn't run `{}`: {}", path.display(), error);
+                ExitCode::from(EXIT_USAGE)
+            }
+        }
+    }
+
+    /// Applies the `warnings` flags to the warnings that didn't come from a lint.  Lints
+    /// already have their levels, which take every flag into account in order.
+    fn apply_warning_flags(&mut self) {
+        let warnings = self
+            .options
+            .lints
+            .iter()
+            .rev()
+            .find(|flag| flag.name == lint::WARNINGS)
+            .map(|flag| flag.level);
+        let other_warning = |diagnostic: &Diagnostic| {
+            diagnostic.severity == Severity::Warning && diagnostic.lint.is_none()
+        };
+        if warnings == Some(Level::Allow) {
+            self.diagnostics
+                .retain(|diagnostic| !other_warning(diagnostic));
+        }
+        if warnings == Some(Level::Deny) || self.options.deny_warnings {
+            for diagnostic in self.diagnostics.iter_mut() {
+                if other_warning(diagnostic) {
+                    diagnostic.severity = Severity::Error;
+                }
+            }
+        }
+    }
+
+    /// Applies the `warnings` flags, then prints the diagnostics and a summary of them.
+    /// Returns whether any errors were reported.
+    fn report(&mut self) -> bool {
+        self.apply_warning_flags();
+
+        let mut stderr = io::stderr().lock();
+        // Failing to print diagnostics leaves no way to report anything, so it is ignored.
+        let _ = self
+            .diagnostics
+            .emit(&self.sources, self.options.format, &mut stderr);
+
+        let errors = self.diagnostics.count(Severity::Error);
+        let warnings = self.diagnostics.count(Severity::Warning);
+        if self.options.format == Format::Json {
+            // Tools reading JSON count the diagnostics themselves.
+            return errors > 0;
+        }
+
+        if warnings > 0 {
+            let _ = writeln!(stderr, "warning: {} emitted", plural(warnings, "warning"));
+        }
+        if errors > 0 {
+            let _ = writeln!(
+                stderr,
+                "error: aborting due to {}",
+                plural(errors, "previous error")
+            );
+        }
+
+        errors > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{self, Action};
+
+    /// Checks `src` with the command line `args`, and returns the severity and message of every
+    /// diagnostic that would be printed.
+    fn check_src(args: &[&str], src: &str) -> Vec<(Severity, String)> {
+        let args = ["check", "test.hl"].iter().chain(args);
+        let Ok(Action::Compile(options)) = cli::parse_args(args.map(|arg| arg.to_string())) else {
+            panic!("the command line is valid");
+        };
+        let mut session = Session::new(options);
+        session.check_lint_flags();
+        let file = session.sources.add("test.hl", src.to_string());
+        let module = parser::parse(&session.sources, file, &mut session.diagnostics)
+            .expect("the source parses");
+        let program = loader::load(module, &[], &mut session.sources, &mut session.diagnostics);
+        session.check(&program);
+        session.apply_warning_flags();
+        session
+            .diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.message.clone()))
+            .collect()
+    }
+
+    const SRC: &str = "\
+#allow(no_such_lint)
+fun f() {
+    val x = 1;
+}
+";
+
+    /// The warning for the unused variable in [`SRC`].
+    fn unused(severity: Severity) -> (Severity, String) {
+        (severity, "unused variable `x`".to_string())
+    }
+
+    /// The warning for the unknown lint in [`SRC`], which doesn't come from a lint.
+    fn unknown(severity: Severity) -> (Severity, String) {
+        (severity, "unknown lint `no_such_lint`".to_string())
+    }
+
+    #[test]
+    fn later_lint_flags_win_over_warnings() {
+        assert_eq!(
+            check_src(&["-A", "warnings", "-W", "unused_variable"], SRC),
+            [unused(Severity::Warning)]
+        );
+        assert_eq!(
+            check_src(&["-W", "unused_variable", "-A", "warnings"], SRC),
+            []
+        );
+        assert_eq!(
+            check_src(&["-D", "warnings", "-W", "unused_variable"], SRC),
+            [unknown(Severity::Error), unused(Severity::Warning)]
+        );
+    }
+
+    #[test]
+    fn syntax_errors_hide_lints_in_their_function() {
+        let src = "\
+struct P { x: int32 }
+
+fun f(a: int32) -> P {
+    val b = 1;
+    val b = 2;
+    return P::{ x: a +* };
+}
+
+fun g() {
+    val c = 3;
+}
+";
+        let messages: Vec<_> = check_src(&["-W", "shadowing"], src)
+            .into_iter()
+            .map(|(severity, message)| format!("{}: {}", severity, message))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "error: expected an expression, found `}`",
+                "error: expected one of `break`, `continue`, `else`, `enum`, `fun` or 14 other tokens, found `;`",
+                "warning: unused variable `c`",
+            ]
+        );
+    }
+
+    #[test]
+    fn expression_attributes_cover_casts() {
+        let src = "\
+fun f() -> uint8 {
+    val _y = #allow(unsafe_code) 300 as uint8;
+    return 300 as uint8;
+}
+";
+        assert_eq!(
+            check_src(&[], src),
+            [(
+                Severity::Warning,
+                "casting `300` to `uint8` changes its value".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn deny_warnings_applies_to_every_warning() {
+        assert_eq!(
+            check_src(&["--deny-warnings"], SRC),
+            [unknown(Severity::Error), unused(Severity::Error)]
+        );
+        assert_eq!(
+            check_src(&[], SRC),
+            [unknown(Severity::Warning), unused(Severity::Warning)]
+        );
+    }
+
+    #[test]
+    fn unknown_lint_flags() {
+        let misspelled = |severity| (severity, "unknown lint `unused_varible`".to_string());
+        assert_eq!(
+            check_src(&["-W", "unused_varible", "-A", "unused_variable"], SRC),
+            [misspelled(Severity::Warning), unknown(Severity::Warning)]
+        );
+        assert_eq!(
+            check_src(&["-Wunused_varible", "--deny-warnings"], ""),
+            [misspelled(Severity::Error)]
+        );
+        assert_eq!(
+            check_src(&["-A", "warnings", "-D", "unused_varible"], ""),
+            []
+        );
+    }
+
+    #[test]
+    fn unknown_lint_flags_point_at_the_command_line() {
+        let args = ["check", "test.hl", "-A", "warnings", "-D", "unsafe_cod"];
+        let Ok(Action::Compile(options)) = cli::parse_args(args.map(|arg| arg.to_string())) else {
+            panic!("the command line is valid");
+        };
+        let mut session = Session::new(options);
+        session.check_lint_flags();
+
+        let [diagnostic] = &session.diagnostics.iter().collect::<Vec<_>>()[..] else {
+            panic!("expected one diagnostic");
+        };
+        let loc = &diagnostic.primary.loc;
+        let file = session.sources.get(loc.file);
+        assert_eq!(file.path.to_str(), Some("<command line>"));
+        assert_eq!(file.src, "-A warnings -D unsafe_cod");
+        assert_eq!(&file.src[loc.span.clone()], "unsafe_cod");
+        assert_eq!(diagnostic.suggestions[0].replacement, "unsafe_code");
+    }
+}
@@ -0,0 +1,198 @@
+//! Control flow.
+//!
+//! A function that returns a value but can reach the end of its body without a `return` returns
+//! the default value of its return type there, and is reported through the `missing_return` lint.
+//! If the type has no default value, that is an error instead.
+
+use crate::ast::*;
+use crate::visit::{self, Visitor};
+
+/// Returns whether the end of `block` can be reached.  `diverges` tells whether an expression
+/// never finishes, such as a call to `panic`.
+pub fn falls_through(block: &Block, diverges: &impl Fn(&Expr) -> bool) -> bool {
+    block.stmts.iter().all(|stmt| completes(stmt, diverges))
+}
+
+/// Returns whether `stmt` can finish and let the statement after it run.
+fn completes(stmt: &Stmt, diverges: &impl Fn(&Expr) -> bool) -> bool {
+    match &stmt.kind {
+        StmtKind::Return(_) | StmtKind::Break | StmtKind::Continue => false,
+        StmtKind::Expr(expr) => !diverges(expr),
+        StmtKind::Val(val) => !val.value.as_ref().is_some_and(diverges),
+        StmtKind::Assign(assign) => !diverges(&assign.value),
+        StmtKind::If(if_) => if_completes(if_, diverges),
+        // Only a loop that always runs again has to be left with `break`.
+        StmtKind::While(while_) => match while_.cond.kind {
+            ExprKind::Lit(Lit::Bool(true)) => breaks(&while_.body),
+            _ => !diverges(&while_.cond),
+        },
+        StmtKind::Block(block) => falls_through(block, diverges),
+        // A statement that failed to parse may have been a `return`, and its syntax error is
+        // reported already.
+        StmtKind::Error => false,
+    }
+}
+
+/// Returns whether the `if` statement `if_` can finish.
+fn if_completes(if_: &If, diverges: &impl Fn(&Expr) -> bool) -> bool {
+    if diverges(&if_.cond) {
+        return false;
+    }
+    match &if_.else_ {
+        None => true,
+        Some(Else::Block(block)) => {
+            falls_through(&if_.then, diverges) || falls_through(block, diverges)
+        }
+        Some(Else::If(else_if)) => {
+            falls_through(&if_.then, diverges) || if_completes(else_if, diverges)
+        }
+    }
+}
+
+/// Returns whether `body`, the body of a loop, has a `break` that leaves it.
+fn breaks(body: &Block) -> bool {
+    /// Finds `break` statements outside of nested loops.
+    struct Finder(bool);
+
+    impl Visitor for Finder {
+        fn visit_stmt(&mut self, stmt: &Stmt) {
+            match &stmt.kind {
+                StmtKind::Break => self.0 = true,
+                // A `break` in a nested loop leaves that loop instead.
+                StmtKind::While(_) => {}
+                _ => visit::walk_stmt(self, stmt),
+            }
+        }
+    }
+
+    let mut finder = Finder(false);
+    finder.visit_block(body);
+    finder.0
+}
+
+/// Returns whether `block` has a `return` statement with a value.
+pub fn returns_value(block: &Block) -> bool {
+    /// Finds `return` statements with values.
+    struct Finder(bool);
+
+    impl Visitor for Finder {
+        fn visit_stmt(&mut self, stmt: &Stmt) {
+            match &stmt.kind {
+                StmtKind::Return(Some(_)) => self.0 = true,
+                _ => visit::walk_stmt(self, stmt),
+            }
+        }
+    }
+
+    let mut finder = Finder(false);
+    finder.visit_block(block);
+    finder.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::Diagnostics;
+    use crate::parser;
+    use crate::source::SourceMap;
+
+    /// Parses `src` and returns the body of its first function.
+    fn body(src: &str) -> Block {
+        let mut sources = SourceMap::new();
+        let file = sources.add("test.hl", src.to_string());
+        let mut diagnostics = Diagnostics::new();
+        let module = parser::parse(&sources, file, &mut diagnostics).expect("the source parses");
+        assert!(diagnostics.iter().next().is_none());
+        match module.items.into_iter().next().map(|item| item.kind) {
+            Some(ItemKind::Fun(Fun {
+                body: Some(body), ..
+            })) => body,
+            _ => panic!("the source starts with a function"),
+        }
+    }
+
+    /// Returns whether the end of the first function in `src` can be reached, treating calls to
+    /// `panic` as diverging.
+    fn falls(src: &str) -> bool {
+        let diverges = |expr: &Expr| match &expr.kind {
+            ExprKind::Call(callee, _) => {
+                matches!(&callee.kind, ExprKind::Path(path) if path.last().name == "panic")
+            }
+            _ => false,
+        };
+        falls_through(&body(src), &diverges)
+    }
+
+    #[test]
+    fn straight_line() {
+        assert!(falls("fun f() -> int32 { val x = 1; }"));
+        assert!(!falls("fun f() -> int32 { return 1; }"));
+        assert!(!falls("fun f() -> int32 { { return 1; } }"));
+        assert!(!falls("fun f() -> int32 { panic(); }"));
+        assert!(!falls("fun f() -> int32 { val x = panic(); }"));
+    }
+
+    #[test]
+    fn branches() {
+        assert!(falls("fun f(c: bool) -> int32 { if c { return 1; } }"));
+        assert!(falls(
+            "fun f(c: bool) -> int32 { if c { return 1; } else { } }"
+        ));
+        assert!(!falls(
+            "fun f(c: bool) -> int32 { if c { return 1; } else { return 2; } }"
+        ));
+        assert!(!falls(
+            "fun f(c: bool) -> int32 { if c { return 1; } else { panic(); } }"
+        ));
+        assert!(falls(
+            "fun f(c: bool) -> int32 { if c { return 1; } else if c { return 2; } }"
+        ));
+        assert!(!falls(
+            "fun f(c: bool) -> int32 { if c { return 1; } else if c { return 2; } else { return 3; } }"
+        ));
+        assert!(!falls("fun f() -> int32 { if panic() { } }"));
+    }
+
+    #[test]
+    fn loops() {
+        assert!(falls("fun f(c: bool) -> int32 { while c { return 1; } }"));
+        assert!(!falls("fun f() -> int32 { while true { } }"));
+        assert!(!falls("fun f() -> int32 { while true { return 1; } }"));
+        assert!(falls(
+            "fun f(c: bool) -> int32 { while true { if c { break; } } }"
+        ));
+        assert!(!falls(
+            "fun f(c: bool) -> int32 { while true { while c { break; } } }"
+        ));
+        assert!(!falls("fun f() -> int32 { while panic() { } }"));
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let mut sources = SourceMap::new();
+        let file = sources.add(
+            "test.hl",
+            "fun f(a: int32) -> int32 { return a +* ; }".to_string(),
+        );
+        let mut diagnostics = Diagnostics::new();
+        let module = parser::parse(&sources, file, &mut diagnostics).expect("the parser recovers");
+        assert!(diagnostics.has_errors());
+        let Some(ItemKind::Fun(Fun {
+            body: Some(body), ..
+        })) = module.items.first().map(|item| &item.kind)
+        else {
+            panic!("the source starts with a function");
+        };
+        assert!(!falls_through(body, &|_| false));
+    }
+
+    #[test]
+    fn return_values() {
+        assert!(returns_value(&body("fun f() -> int32 { return 1; }")));
+        assert!(returns_value(&body(
+            "fun f(c: bool) -> int32 { while c { if c { return 1; } } }"
+        )));
+        assert!(!returns_value(&body("fun f() { return; }")));
+        assert!(!returns_value(&body("fun f() -> int32 { }")));
+    }
+}
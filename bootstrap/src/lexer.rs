@@ -0,0 +1,1014 @@
+//! The lexer for Hail.
+//!
+//! The lexer turns source text into a stream of [`Token`]s, each tagged with the [`Loc`] it was
+//! read from.  It is used as an external lexer by the LALRPOP grammar, which receives the tokens
+//! as `(start, token, end)` triples, with errors turned into [`Token::Invalid`] so that the
+//! parser can recover from them.
+
+use std::fmt;
+
+use crate::ast::{FloatTy, IntTy};
+use crate::diagnostic::Diagnostic;
+use crate::Loc;
+
+/// A token of Hail source code.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token<'input> {
+    /// The `publ` keyword.
+    Publ,
+
+    /// The `fun` keyword.
+    Fun,
+
+    /// The `val` keyword.
+    Val,
+
+    /// The `mut` keyword.
+    Mut,
+
+    /// The `mixin` keyword.
+    Mixin,
+
+    /// The `struct` keyword.
+    Struct,
+
+    /// The `enum` keyword.
+    Enum,
+
+    /// The `union` keyword.
+    Union,
+
+    /// The `trait` keyword.
+    Trait,
+
+    /// The `impl` keyword.
+    Impl,
+
+    /// The `for` keyword.
+    For,
+
+    /// The `import` keyword.
+    Import,
+
+    /// The `from` keyword.
+    From,
+
+    /// The `as` keyword.
+    As,
+
+    /// The `return` keyword.
+    Return,
+
+    /// The `if` keyword.
+    If,
+
+    /// The `else` keyword.
+    Else,
+
+    /// The `while` keyword.
+    While,
+
+    /// The `break` keyword.
+    Break,
+
+    /// The `continue` keyword.
+    Continue,
+
+    /// The `true` keyword.
+    True,
+
+    /// The `false` keyword.
+    False,
+
+    /// The `self` keyword.
+    SelfValue,
+
+    /// The `Self` keyword.
+    SelfType,
+
+    /// An identifier.
+    Iden(&'input str),
+
+    /// An integer literal, as written in the source.
+    Int(&'input str),
+
+    /// A floating point literal, as written in the source.
+    Float(&'input str),
+
+    /// A string literal as written in the source, such as `"hi\n"`, `b"hi"` or `r#"hi"#`.
+    /// Escape codes are decoded by the [`literal`](crate::literal) module.
+    Str(&'input str),
+
+    /// A character literal as written in the source, such as `'a'` or `b'\n'`.
+    Char(&'input str),
+
+    /// A `///` doc comment, holding the text after the slashes.  It documents the item, field
+    /// or variant after it.
+    DocComment(&'input str),
+
+    /// A `//!` doc comment, holding the text after the `!`.  It documents the file it is in.
+    InnerDocComment(&'input str),
+
+    /// `(`
+    LParen,
+
+    /// `)`
+    RParen,
+
+    /// `{`
+    LBrace,
+
+    /// `}`
+    RBrace,
+
+    /// `[`
+    LBracket,
+
+    /// `]`
+    RBracket,
+
+    /// `,`
+    Comma,
+
+    /// `;`
+    Semi,
+
+    /// `:`
+    Colon,
+
+    /// `::`
+    ColonColon,
+
+    /// `::{`, which opens a struct literal.
+    ColonColonBrace,
+
+    /// `.`
+    Dot,
+
+    /// `->`
+    Arrow,
+
+    /// `#`
+    Hash,
+
+    /// `!`
+    Bang,
+
+    /// `!<`, which opens the argument list of a mixin.
+    BangLt,
+
+    /// `=`
+    Eq,
+
+    /// `==`
+    EqEq,
+
+    /// `!=`
+    Ne,
+
+    /// `<`
+    Lt,
+
+    /// `<=`
+    Le,
+
+    /// `>`
+    Gt,
+
+    /// `>=`
+    Ge,
+
+    /// `+`
+    Plus,
+
+    /// `-`
+    Minus,
+
+    /// `*`
+    Star,
+
+    /// `/`
+    Slash,
+
+    /// `%`
+    Percent,
+
+    /// `&`
+    Amp,
+
+    /// `&&`
+    AmpAmp,
+
+    /// `|`
+    Pipe,
+
+    /// `||`
+    PipePipe,
+
+    /// `^`
+    Caret,
+
+    /// `~`
+    Tilde,
+
+    /// `<<`
+    Shl,
+
+    /// `>>`
+    Shr,
+
+    /// `+=`
+    PlusEq,
+
+    /// `-=`
+    MinusEq,
+
+    /// `*=`
+    StarEq,
+
+    /// `/=`
+    SlashEq,
+
+    /// `%=`
+    PercentEq,
+
+    /// `&=`
+    AmpEq,
+
+    /// `|=`
+    PipeEq,
+
+    /// `^=`
+    CaretEq,
+
+    /// `<<=`
+    ShlEq,
+
+    /// `>>=`
+    ShrEq,
+
+    /// Source text that couldn't be lexed.  The parser reports the error and recovers from it
+    /// like any other unexpected token.
+    Invalid(LexError),
+}
+
+impl<'input> Token<'input> {
+    /// Returns the keyword token for `iden`, if it is a keyword.
+    pub fn keyword(iden: &str) -> Option<Self> {
+        Some(match iden {
+            "publ" => Token::Publ,
+            "fun" => Token::Fun,
+            "val" => Token::Val,
+            "mut" => Token::Mut,
+            "mixin" => Token::Mixin,
+            "struct" => Token::Struct,
+            "enum" => Token::Enum,
+            "union" => Token::Union,
+            "trait" => Token::Trait,
+            "impl" => Token::Impl,
+            "for" => Token::For,
+            "import" => Token::Import,
+            "from" => Token::From,
+            "as" => Token::As,
+            "return" => Token::Return,
+            "if" => Token::If,
+            "else" => Token::Else,
+            "while" => Token::While,
+            "break" => Token::Break,
+            "continue" => Token::Continue,
+            "true" => Token::True,
+            "false" => Token::False,
+            "self" => Token::SelfValue,
+            "Self" => Token::SelfType,
+            _ => return None,
+        })
+    }
+
+    /// Describes the token for error messages, such as "keyword `fun`" or "`;`".
+    pub fn describe(&self) -> String {
+        match self {
+            Token::Invalid(_) => "invalid token".to_string(),
+            Token::DocComment(_) => "doc comment".to_string(),
+            Token::InnerDocComment(_) => "inner doc comment".to_string(),
+            Token::Iden(name) => format!("identifier `{}`", name),
+            Token::Int(_) | Token::Float(_) | Token::Str(_) | Token::Char(_) => {
+                format!("literal `{}`", self)
+            }
+            _ if Token::keyword(&self.to_string()).is_some() => format!("keyword `{}`", self),
+            _ => format!("`{}`", self),
+        }
+    }
+}
+
+impl fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Token::Publ => "publ",
+            Token::Fun => "fun",
+            Token::Val => "val",
+            Token::Mut => "mut",
+            Token::Mixin => "mixin",
+            Token::Struct => "struct",
+            Token::Enum => "enum",
+            Token::Union => "union",
+            Token::Trait => "trait",
+            Token::Impl => "impl",
+            Token::For => "for",
+            Token::Import => "import",
+            Token::From => "from",
+            Token::As => "as",
+            Token::Return => "return",
+            Token::If => "if",
+            Token::Else => "else",
+            Token::While => "while",
+            Token::Break => "break",
+            Token::Continue => "continue",
+            Token::True => "true",
+            Token::False => "false",
+            Token::SelfValue => "self",
+            Token::SelfType => "Self",
+            Token::Iden(text)
+            | Token::Int(text)
+            | Token::Float(text)
+            | Token::Str(text)
+            | Token::Char(text) => text,
+            Token::LParen => "(",
+            Token::RParen => ")",
+            Token::LBrace => "{",
+            Token::RBrace => "}",
+            Token::LBracket => "[",
+            Token::RBracket => "]",
+            Token::Comma => ",",
+            Token::Semi => ";",
+            Token::Colon => ":",
+            Token::ColonColon => "::",
+            Token::ColonColonBrace => "::{",
+            Token::Dot => ".",
+            Token::Arrow => "->",
+            Token::Hash => "#",
+            Token::Bang => "!",
+            Token::BangLt => "!<",
+            Token::Eq => "=",
+            Token::EqEq => "==",
+            Token::Ne => "!=",
+            Token::Lt => "<",
+            Token::Le => "<=",
+            Token::Gt => ">",
+            Token::Ge => ">=",
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Star => "*",
+            Token::Slash => "/",
+            Token::Percent => "%",
+            Token::Amp => "&",
+            Token::AmpAmp => "&&",
+            Token::Pipe => "|",
+            Token::PipePipe => "||",
+            Token::Caret => "^",
+            Token::Tilde => "~",
+            Token::Shl => "<<",
+            Token::Shr => ">>",
+            Token::PlusEq => "+=",
+            Token::MinusEq => "-=",
+            Token::StarEq => "*=",
+            Token::SlashEq => "/=",
+            Token::PercentEq => "%=",
+            Token::AmpEq => "&=",
+            Token::PipeEq => "|=",
+            Token::CaretEq => "^=",
+            Token::ShlEq => "<<=",
+            Token::ShrEq => ">>=",
+            Token::Invalid(_) => "<invalid>",
+            Token::DocComment(text) => return write!(f, "///{}", text),
+            Token::InnerDocComment(text) => return write!(f, "//!{}", text),
+        };
+
+        f.write_str(text)
+    }
+}
+
+/// The kind of a [`LexError`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum LexErrorKind {
+    /// A character that cannot start any token.
+    UnexpectedChar(char),
+
+    /// A string literal without a closing quote.
+    UnterminatedStr,
+
+    /// A character literal without a closing quote.
+    UnterminatedChar,
+
+    /// A block comment without a closing `*/`.
+    UnterminatedComment,
+
+    /// An escape code that doesn't exist, such as `\q`.
+    UnknownEscape(char),
+
+    /// A `\x` escape that isn't followed by two hexadecimal digits.
+    InvalidHexEscape,
+
+    /// A `\x` escape above `\x7f` outside of a byte literal.
+    HexEscapeOutOfRange,
+
+    /// A malformed `\u{...}` escape, or one that isn't a valid character.
+    InvalidUnicodeEscape,
+
+    /// A `\u{...}` escape in a byte literal.
+    UnicodeEscapeInBytes,
+
+    /// A character that isn't ASCII in a byte literal.
+    NonAsciiInBytes(char),
+
+    /// A character literal with nothing in it.
+    EmptyChar,
+
+    /// A character literal with more than one character in it.
+    LongChar,
+
+    /// A number literal without any digits, such as `0x`.
+    EmptyNumber,
+
+    /// A digit that isn't valid in the literal's radix, such as the `2` in `0b102`.
+    InvalidDigit(char, u32),
+
+    /// A literal suffix that isn't a type, or is the wrong kind of type.
+    InvalidSuffix(String),
+
+    /// An integer literal that doesn't fit in its type.  The type is `None` for literals too
+    /// large for any integer type.
+    IntOutOfRange(Option<IntTy>),
+
+    /// A floating point literal that doesn't fit in its type.
+    FloatOutOfRange(FloatTy),
+}
+
+/// An error produced while lexing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LexError {
+    /// What went wrong.
+    pub kind: LexErrorKind,
+
+    /// Where it went wrong.
+    pub loc: Loc,
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            LexErrorKind::UnexpectedChar(c) => {
+                write!(f, "unexpected character `{}`", c.escape_debug())
+            }
+            LexErrorKind::UnterminatedStr => f.write_str("unterminated string literal"),
+            LexErrorKind::UnterminatedChar => f.write_str("unterminated character literal"),
+            LexErrorKind::UnterminatedComment => f.write_str("unterminated block comment"),
+            LexErrorKind::UnknownEscape(c) => {
+                write!(f, "unknown escape `\\{}`", c.escape_debug())
+            }
+            LexErrorKind::InvalidHexEscape => {
+                f.write_str("`\\x` must be followed by two hexadecimal digits")
+            }
+            LexErrorKind::HexEscapeOutOfRange => {
+                f.write_str("`\\x` escapes above `\\x7f` are only allowed in byte literals")
+            }
+            LexErrorKind::InvalidUnicodeEscape => f.write_str("invalid unicode escape"),
+            LexErrorKind::UnicodeEscapeInBytes => {
+                f.write_str("unicode escapes aren't allowed in byte literals")
+            }
+            LexErrorKind::NonAsciiInBytes(c) => {
+                write!(f, "non-ASCII character `{}` in byte literal", c)
+            }
+            LexErrorKind::EmptyChar => f.write_str("empty character literal"),
+            LexErrorKind::LongChar => {
+                f.write_str("character literal may only contain one character")
+            }
+            LexErrorKind::EmptyNumber => f.write_str("number literal has no digits"),
+            LexErrorKind::InvalidDigit(digit, radix) => {
+                write!(f, "invalid digit `{}` in base {} literal", digit, radix)
+            }
+            LexErrorKind::InvalidSuffix(suffix) => {
+                write!(f, "invalid suffix `{}` for number literal", suffix)
+            }
+            LexErrorKind::IntOutOfRange(Some(ty)) => {
+                write!(f, "literal out of range for `{}`", ty.name())
+            }
+            LexErrorKind::IntOutOfRange(None) => f.write_str("integer literal is too large"),
+            LexErrorKind::FloatOutOfRange(ty) => {
+                write!(f, "literal out of range for `{}`", ty.name())
+            }
+        }
+    }
+}
+
+impl From<LexError> for Diagnostic {
+    fn from(error: LexError) -> Self {
+        let message = error.to_string();
+        let diagnostic = Diagnostic::error(message, error.loc);
+
+        match error.kind {
+            LexErrorKind::UnterminatedStr | LexErrorKind::UnterminatedChar => {
+                diagnostic.with_label("missing a closing quote")
+            }
+            LexErrorKind::UnterminatedComment => diagnostic.with_label("missing a closing `*/`"),
+            LexErrorKind::UnknownEscape(_) => diagnostic.with_label("unknown escape").with_help(
+                "valid escapes are `\\n`, `\\r`, `\\t`, `\\\\`, `\\\"`, `\\'`, `\\0`, `\\xNN` and `\\u{..}`",
+            ),
+            LexErrorKind::HexEscapeOutOfRange => diagnostic
+                .with_label("out of range")
+                .with_help("use a `\\u{..}` escape for characters above `\\x7f`"),
+            LexErrorKind::NonAsciiInBytes(_) => diagnostic
+                .with_label("not ASCII")
+                .with_help("use a `\\xNN` escape instead"),
+            LexErrorKind::InvalidSuffix(_) => diagnostic.with_label("invalid suffix").with_help(
+                "integer suffixes are `i8`, `i16`, `i32`, `i64`, `i`, `u8`, `u16`, `u32`, `u64` and `u`, and float suffixes are `f32` and `f64`",
+            ),
+            LexErrorKind::IntOutOfRange(Some(ty)) => {
+                diagnostic.with_label(format!("doesn't fit in `{}`", ty.name()))
+            }
+            _ => diagnostic,
+        }
+    }
+}
+
+/// A token in the form expected by the LALRPOP parser.
+pub type Spanned<'input> = Result<(usize, Token<'input>, usize), LexError>;
+
+/// The lexer for a single Hail source file.
+#[derive(Clone, Debug)]
+pub struct Lexer<'input> {
+    /// The file being lexed.
+    file: u32,
+
+    /// The source text being lexed.
+    src: &'input str,
+
+    /// The byte offset of the next character.
+    pos: usize,
+
+    /// How many `!<` argument lists are currently open.  Inside of them, `>` always closes the
+    /// innermost list instead of being joined into `>>` or `>=`.  Argument lists only hold types,
+    /// so `;`, `{` and `}` close any left open by a syntax error.
+    generic_depth: usize,
+}
+
+impl<'input> Lexer<'input> {
+    /// Creates a lexer for the source text `src` of `file`.
+    pub fn new(file: u32, src: &'input str) -> Self {
+        Self {
+            file,
+            src,
+            pos: 0,
+            generic_depth: 0,
+        }
+    }
+
+    /// Returns the next token and its location, or `None` at the end of the source.
+    pub fn next_token(&mut self) -> Option<Result<(Token<'input>, Loc), LexError>> {
+        if let Err(err) = self.skip_trivia() {
+            return Some(Err(err));
+        }
+
+        let start = self.pos;
+        let token = match (self.peek()?, self.peek_at(1), self.peek_at(2)) {
+            (b'b', Some(b'"'), _) => self.quoted(start, 1, b'"').map(Token::Str),
+            (b'b', Some(b'\''), _) => self.quoted(start, 1, b'\'').map(Token::Char),
+            (b'r', Some(b'"' | b'#'), _) if self.raw_quote(1).is_some() => {
+                self.raw(start, 1).map(Token::Str)
+            }
+            (b'b', Some(b'r'), Some(b'"' | b'#')) if self.raw_quote(2).is_some() => {
+                self.raw(start, 2).map(Token::Str)
+            }
+            (b'a'..=b'z' | b'A'..=b'Z' | b'_', ..) => Ok(self.iden(start)),
+            (b'0'..=b'9', ..) => Ok(self.number(start)),
+            (b'"', ..) => self.quoted(start, 0, b'"').map(Token::Str),
+            (b'\'', ..) => self.quoted(start, 0, b'\'').map(Token::Char),
+            (b'/', Some(b'/'), Some(b'/' | b'!')) => Ok(self.doc_comment(start)),
+            _ => self.punct(start),
+        };
+
+        Some(token.map(|token| (token, self.loc(start))))
+    }
+
+    /// Returns the location from `start` to the current position.
+    fn loc(&self, start: usize) -> Loc {
+        Loc::new(self.file, start..self.pos)
+    }
+
+    /// Returns the byte at the current position.
+    fn peek(&self) -> Option<u8> {
+        self.peek_at(0)
+    }
+
+    /// Returns the byte `offset` bytes after the current position.
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.src.as_bytes().get(self.pos + offset).copied()
+    }
+
+    /// Advances past every byte matching `pred`.
+    fn eat_while(&mut self, pred: impl Fn(u8) -> bool) {
+        while self.peek().is_some_and(&pred) {
+            self.pos += 1;
+        }
+    }
+
+    /// Skips whitespace and comments.
+    fn skip_trivia(&mut self) -> Result<(), LexError> {
+        loop {
+            match (self.peek(), self.peek_at(1)) {
+                (Some(c), _) if c.is_ascii_whitespace() => self.pos += 1,
+                (Some(b'/'), Some(b'/')) if !self.at_doc_comment() => {
+                    self.eat_while(|c| c != b'\n')
+                }
+                (Some(b'/'), Some(b'*')) => self.block_comment()?,
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Returns whether a doc comment starts at the current position.  Comments starting with
+    /// four or more slashes are regular comments, so they can be used as separators.
+    fn at_doc_comment(&self) -> bool {
+        let rest = &self.src[self.pos..];
+        rest.starts_with("//!") || (rest.starts_with("///") && !rest.starts_with("////"))
+    }
+
+    /// Skips a block comment.  Block comments nest, so `/* a /* b */ c */` is one comment.
+    fn block_comment(&mut self) -> Result<(), LexError> {
+        let start = self.pos;
+        let mut depth = 0;
+        loop {
+            match (self.peek(), self.peek_at(1)) {
+                (Some(b'/'), Some(b'*')) => {
+                    depth += 1;
+                    self.pos += 2;
+                }
+                (Some(b'*'), Some(b'/')) => {
+                    depth -= 1;
+                    self.pos += 2;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                (Some(_), _) => self.pos += 1,
+                (None, _) => {
+                    return Err(LexError {
+                        kind: LexErrorKind::UnterminatedComment,
+                        loc: Loc::new(self.file, start..start + 2),
+                    })
+                }
+            }
+        }
+    }
+
+    /// Lexes a `///` or `//!` doc comment.
+    fn doc_comment(&mut self, start: usize) -> Token<'input> {
+        self.eat_while(|c| c != b'\n');
+        let text = self.src[start + 3..self.pos].trim_end_matches('\r');
+        match self.src.as_bytes()[start + 2] {
+            b'!' => Token::InnerDocComment(text),
+            _ => Token::DocComment(text),
+        }
+    }
+
+    /// Lexes an identifier or keyword.
+    fn iden(&mut self, start: usize) -> Token<'input> {
+        self.eat_while(|c| c.is_ascii_alphanumeric() || c == b'_');
+
+        let text = &self.src[start..self.pos];
+        Token::keyword(text).unwrap_or(Token::Iden(text))
+    }
+
+    /// Lexes an integer or floating point literal, including any radix prefix and type suffix.
+    ///
+    /// This only finds the extent of the literal; its digits and suffix are checked by the
+    /// [`literal`](crate::literal) module.
+    fn number(&mut self, start: usize) -> Token<'input> {
+        let is_digit = |c: u8| c.is_ascii_digit() || c == b'_';
+
+        if self.peek() == Some(b'0') && matches!(self.peek_at(1), Some(b'x' | b'o' | b'b')) {
+            let hex = self.peek_at(1) == Some(b'x');
+            self.pos += 2;
+            self.eat_while(|c| is_digit(c) || (hex && c.is_ascii_hexdigit()));
+            self.eat_while(|c| c.is_ascii_alphanumeric() || c == b'_');
+            return Token::Int(&self.src[start..self.pos]);
+        }
+
+        self.eat_while(is_digit);
+
+        let mut float = false;
+        if self.peek() == Some(b'.') && self.peek_at(1).is_some_and(|c| c.is_ascii_digit()) {
+            float = true;
+            self.pos += 1;
+            self.eat_while(is_digit);
+        }
+
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            let sign = matches!(self.peek_at(1), Some(b'+' | b'-')) as usize;
+            if self.peek_at(1 + sign).is_some_and(|c| c.is_ascii_digit()) {
+                float = true;
+                self.pos += 1 + sign;
+                self.eat_while(is_digit);
+            }
+        }
+
+        self.eat_while(|c| c.is_ascii_alphanumeric() || c == b'_');
+
+        let text = &self.src[start..self.pos];
+        if float {
+            Token::Float(text)
+        } else {
+            Token::Int(text)
+        }
+    }
+
+    /// Lexes a literal delimited by `quote` after a prefix of `prefix` bytes, returning its text.
+    fn quoted(&mut self, start: usize, prefix: usize, quote: u8) -> Result<&'input str, LexError> {
+        self.pos += prefix + 1;
+
+        loop {
+            match self.peek() {
+                Some(c) if c == quote => break,
+                Some(b'\\') if self.peek_at(1).is_some() => self.pos += 2,
+                Some(_) => self.pos += 1,
+                None => {
+                    let kind = match quote {
+                        b'"' => LexErrorKind::UnterminatedStr,
+                        _ => LexErrorKind::UnterminatedChar,
+                    };
+
+                    return Err(LexError {
+                        kind,
+                        loc: self.loc(start),
+                    });
+                }
+            }
+        }
+
+        self.pos += 1;
+        Ok(&self.src[start..self.pos])
+    }
+
+    /// If a raw string's hashes and opening quote start `offset` bytes from the current position,
+    /// returns how many hashes there are.
+    fn raw_quote(&self, offset: usize) -> Option<usize> {
+        let hashes = self.src.as_bytes()[self.pos + offset..]
+            .iter()
+            .take_while(|&&c| c == b'#')
+            .count();
+
+        (self.peek_at(offset + hashes) == Some(b'"')).then_some(hashes)
+    }
+
+    /// Lexes a raw string such as `r#"..."#` after a prefix of `prefix` bytes, returning its text.
+    fn raw(&mut self, start: usize, prefix: usize) -> Result<&'input str, LexError> {
+        let hashes = self.raw_quote(prefix).unwrap_or_default();
+        self.pos += prefix + hashes + 1;
+
+        let close = format!("\"{}", "#".repeat(hashes));
+        match self.src[self.pos..].find(&close) {
+            Some(end) => {
+                self.pos += end + close.len();
+                Ok(&self.src[start..self.pos])
+            }
+            None => {
+                self.pos = self.src.len();
+                Err(LexError {
+                    kind: LexErrorKind::UnterminatedStr,
+                    loc: self.loc(start),
+                })
+            }
+        }
+    }
+
+    /// Lexes a punctuation token.
+    fn punct(&mut self, start: usize) -> Result<Token<'input>, LexError> {
+        let rest = &self.src.as_bytes()[start..];
+
+        // Inside of a mixin's argument list, `>` is always on its own so that nested lists like
+        // `A!<B!<T>>` close properly.
+        if rest[0] == b'>' && self.generic_depth > 0 {
+            self.generic_depth -= 1;
+            self.pos += 1;
+            return Ok(Token::Gt);
+        }
+
+        let (token, len) = match rest {
+            [b':', b':', b'{', ..] => (Token::ColonColonBrace, 3),
+            [b'<', b'<', b'=', ..] => (Token::ShlEq, 3),
+            [b'>', b'>', b'=', ..] => (Token::ShrEq, 3),
+            [b':', b':', ..] => (Token::ColonColon, 2),
+            [b'-', b'>', ..] => (Token::Arrow, 2),
+            [b'!', b'<', ..] => (Token::BangLt, 2),
+            [b'!', b'=', ..] => (Token::Ne, 2),
+            [b'=', b'=', ..] => (Token::EqEq, 2),
+            [b'<', b'=', ..] => (Token::Le, 2),
+            [b'>', b'=', ..] => (Token::Ge, 2),
+            [b'<', b'<', ..] => (Token::Shl, 2),
+            [b'>', b'>', ..] => (Token::Shr, 2),
+            [b'&', b'&', ..] => (Token::AmpAmp, 2),
+            [b'|', b'|', ..] => (Token::PipePipe, 2),
+            [b'+', b'=', ..] => (Token::PlusEq, 2),
+            [b'-', b'=', ..] => (Token::MinusEq, 2),
+            [b'*', b'=', ..] => (Token::StarEq, 2),
+            [b'/', b'=', ..] => (Token::SlashEq, 2),
+            [b'%', b'=', ..] => (Token::PercentEq, 2),
+            [b'&', b'=', ..] => (Token::AmpEq, 2),
+            [b'|', b'=', ..] => (Token::PipeEq, 2),
+            [b'^', b'=', ..] => (Token::CaretEq, 2),
+            [b'(', ..] => (Token::LParen, 1),
+            [b')', ..] => (Token::RParen, 1),
+            [b'{', ..] => (Token::LBrace, 1),
+            [b'}', ..] => (Token::RBrace, 1),
+            [b'[', ..] => (Token::LBracket, 1),
+            [b']', ..] => (Token::RBracket, 1),
+            [b',', ..] => (Token::Comma, 1),
+            [b';', ..] => (Token::Semi, 1),
+            [b':', ..] => (Token::Colon, 1),
+            [b'.', ..] => (Token::Dot, 1),
+            [b'#', ..] => (Token::Hash, 1),
+            [b'!', ..] => (Token::Bang, 1),
+            [b'=', ..] => (Token::Eq, 1),
+            [b'<', ..] => (Token::Lt, 1),
+            [b'>', ..] => (Token::Gt, 1),
+            [b'+', ..] => (Token::Plus, 1),
+            [b'-', ..] => (Token::Minus, 1),
+            [b'*', ..] => (Token::Star, 1),
+            [b'/', ..] => (Token::Slash, 1),
+            [b'%', ..] => (Token::Percent, 1),
+            [b'&', ..] => (Token::Amp, 1),
+            [b'|', ..] => (Token::Pipe, 1),
+            [b'^', ..] => (Token::Caret, 1),
+            [b'~', ..] => (Token::Tilde, 1),
+            _ => {
+                let c = self.src[start..].chars().next().unwrap_or_default();
+                self.pos += c.len_utf8();
+                return Err(LexError {
+                    kind: LexErrorKind::UnexpectedChar(c),
+                    loc: self.loc(start),
+                });
+            }
+        };
+
+        match token {
+            Token::BangLt => self.generic_depth += 1,
+            Token::Semi | Token::LBrace | Token::RBrace => self.generic_depth = 0,
+            _ => {}
+        }
+
+        self.pos += len;
+        Ok(token)
+    }
+}
+
+impl<'input> Iterator for Lexer<'input> {
+    type Item = Spanned<'input>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (token, loc) = match self.next_token()? {
+            Ok(token) => token,
+            Err(error) => {
+                let loc = error.loc.clone();
+                (Token::Invalid(error), loc)
+            }
+        };
+
+        Some(Ok((loc.span.start, token, loc.span.end)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lexes `src`, panicking on the first error.
+    fn tokens(src: &str) -> Vec<Token<'_>> {
+        let mut lexer = Lexer::new(0, src);
+        std::iter::from_fn(|| lexer.next_token())
+            .map(|token| token.unwrap().0)
+            .collect()
+    }
+
+    /// Lexes `src` and returns the kind of the first error.
+    fn error(src: &str) -> LexErrorKind {
+        let mut lexer = Lexer::new(0, src);
+        std::iter::from_fn(|| lexer.next_token())
+            .find_map(Result::err)
+            .unwrap()
+            .kind
+    }
+
+    #[test]
+    fn keywords_and_identifiers() {
+        assert_eq!(
+            tokens("publ fun main() -> Self { self_ }"),
+            [
+                Token::Publ,
+                Token::Fun,
+                Token::Iden("main"),
+                Token::LParen,
+                Token::RParen,
+                Token::Arrow,
+                Token::SelfType,
+                Token::LBrace,
+                Token::Iden("self_"),
+                Token::RBrace,
+            ]
+        );
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(
+            tokens(r##"12u8 1.5 "a\"b" 'c' b"x" r#"y"#"##),
+            [
+                Token::Int("12u8"),
+                Token::Float("1.5"),
+                Token::Str(r#""a\"b""#),
+                Token::Char("'c'"),
+                Token::Str(r#"b"x""#),
+                Token::Str(r##"r#"y"#"##),
+            ]
+        );
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(
+            tokens("a // b\n/* c /* d */ e */ f //// g\n/// h\n//! i"),
+            [
+                Token::Iden("a"),
+                Token::Iden("f"),
+                Token::DocComment(" h"),
+                Token::InnerDocComment(" i"),
+            ]
+        );
+        assert_eq!(error("/* a /* b */"), LexErrorKind::UnterminatedComment);
+    }
+
+    #[test]
+    fn generic_arguments_close_one_at_a_time() {
+        assert_eq!(
+            tokens("a!<b!<c>> >> d >= e"),
+            [
+                Token::Iden("a"),
+                Token::BangLt,
+                Token::Iden("b"),
+                Token::BangLt,
+                Token::Iden("c"),
+                Token::Gt,
+                Token::Gt,
+                Token::Shr,
+                Token::Iden("d"),
+                Token::Ge,
+                Token::Iden("e"),
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_generic_arguments() {
+        assert_eq!(
+            tokens("B!<int32; a >= b >> c"),
+            [
+                Token::Iden("B"),
+                Token::BangLt,
+                Token::Iden("int32"),
+                Token::Semi,
+                Token::Iden("a"),
+                Token::Ge,
+                Token::Iden("b"),
+                Token::Shr,
+                Token::Iden("c"),
+            ]
+        );
+        assert_eq!(
+            tokens("A!<B { a >= b }"),
+            [
+                Token::Iden("A"),
+                Token::BangLt,
+                Token::Iden("B"),
+                Token::LBrace,
+                Token::Iden("a"),
+                Token::Ge,
+                Token::Iden("b"),
+                Token::RBrace,
+            ]
+        );
+    }
+
+    #[test]
+    fn locations() {
+        let mut lexer = Lexer::new(3, "  foo +=");
+        let (_, loc) = lexer.next_token().unwrap().unwrap();
+        assert_eq!(loc, Loc::new(3, 2..5));
+        let (token, loc) = lexer.next_token().unwrap().unwrap();
+        assert_eq!((token, loc), (Token::PlusEq, Loc::new(3, 6..8)));
+        assert!(lexer.next_token().is_none());
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error("a $ b"), LexErrorKind::UnexpectedChar('$'));
+        assert_eq!(error("\"abc"), LexErrorKind::UnterminatedStr);
+    }
+}
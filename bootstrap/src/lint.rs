@@ -0,0 +1,553 @@
+//! Lints, and the levels they are reported at.
+//!
+//! A lint is a check for code that is allowed but possibly wrong.  Each lint has a default
+//! [`Level`], which the command line can change for the whole compilation, and which `#allow`,
+//! `#warn` and `#deny` attributes can change for the item, statement or expression they are
+//! written on.  Passes report lints through [`LintContext::emit`], which picks the level that
+//! applies at the lint's location.
+
+use std::fmt;
+
+use crate::ast::{Attribute, Expr, Item, ItemKind, Module, Stmt};
+use crate::attr::{arg_names, attr_name};
+use crate::cli::LintFlag;
+use crate::diagnostic::{similar_name, Diagnostic, Diagnostics, Severity};
+use crate::source::SourceMap;
+use crate::visit::{self, Visitor};
+use crate::Loc;
+
+/// How a lint is reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// The lint isn't reported.
+    Allow,
+
+    /// The lint is reported as a warning.
+    Warn,
+
+    /// The lint is reported as an error.
+    Deny,
+}
+
+impl Level {
+    /// Returns the level set by the attribute called `name`.
+    pub fn from_attr(name: &str) -> Option<Self> {
+        match name {
+            "allow" => Some(Level::Allow),
+            "warn" => Some(Level::Warn),
+            "deny" => Some(Level::Deny),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Level::Allow => "allow",
+            Level::Warn => "warn",
+            Level::Deny => "deny",
+        })
+    }
+}
+
+/// A lint.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Lint {
+    /// The name used in attributes and on the command line.
+    pub name: &'static str,
+
+    /// The level of the lint when nothing changes it.
+    pub default: Level,
+
+    /// What the lint checks for.
+    pub description: &'static str,
+}
+
+/// Code that may be unsafe, such as lossy casts or raw memory access.
+pub const UNSAFE_CODE: Lint = Lint {
+    name: "unsafe_code",
+    default: Level::Warn,
+    description: "code that may cause undefined behavior",
+};
+
+/// Functions that return a value but have a path without a `return`.
+pub const MISSING_RETURN: Lint = Lint {
+    name: "missing_return",
+    default: Level::Warn,
+    description: "functions that never return a value",
+};
+
+/// Variables that are never used.
+pub const UNUSED_VARIABLE: Lint = Lint {
+    name: "unused_variable",
+    default: Level::Warn,
+    description: "variables that are never used",
+};
+
+/// Variables declared with the name of another variable that is still in scope.
+pub const SHADOWING: Lint = Lint {
+    name: "shadowing",
+    default: Level::Warn,
+    description: "variables that hide an earlier variable with the same name",
+};
+
+/// Values of types that implement `Drop` that are never dropped or moved.
+pub const MISSING_DROP: Lint = Lint {
+    name: "missing_drop",
+    default: Level::Allow,
+    description: "values that go out of scope without being dropped",
+};
+
+/// Every lint.
+pub const LINTS: &[&Lint] = &[
+    &UNSAFE_CODE,
+    &MISSING_RETURN,
+    &UNUSED_VARIABLE,
+    &SHADOWING,
+    &MISSING_DROP,
+];
+
+/// The name that refers to every lint at once.
+pub const WARNINGS: &str = "warnings";
+
+/// Returns the lint called `name`.
+pub fn find(name: &str) -> Option<&'static Lint> {
+    LINTS.iter().copied().find(|lint| lint.name == name)
+}
+
+/// Returns whether `name` names a lint or [`WARNINGS`].
+pub fn is_known(name: &str) -> bool {
+    name == WARNINGS || find(name).is_some()
+}
+
+/// Returns a suggestion of a lint name close to the unknown `name`.
+pub fn similar(name: &str) -> Option<&'static str> {
+    similar_name(
+        name,
+        LINTS
+            .iter()
+            .map(|lint| lint.name)
+            .chain(std::iter::once(WARNINGS)),
+    )
+}
+
+/// A lint level set by an attribute.
+#[derive(Clone, Debug)]
+struct LevelAttr {
+    /// The name of the lint, or [`WARNINGS`].
+    lint: String,
+
+    /// The level it is set to.
+    level: Level,
+
+    /// The location of the attribute.
+    loc: Loc,
+}
+
+/// An item, statement or expression, with the lint levels its attributes set.
+#[derive(Clone, Debug)]
+struct Scope {
+    /// The location of the node.
+    loc: Loc,
+
+    /// The levels set by the attributes on the node.
+    levels: Vec<LevelAttr>,
+
+    /// Where an `#allow` attribute for the node is inserted, and the indentation to put after
+    /// it, or `None` if the attribute goes on the same line.  Expressions don't get one.
+    insert: Option<(usize, Option<String>)>,
+}
+
+impl Scope {
+    /// Returns whether the node contains `loc`.
+    fn contains(&self, loc: &Loc) -> bool {
+        self.loc.file == loc.file
+            && self.loc.span.start <= loc.span.start
+            && loc.span.end <= self.loc.span.end
+    }
+}
+
+/// Where the level of a lint came from.
+#[derive(Clone, Debug, PartialEq)]
+enum LevelSource {
+    /// The default level of the lint.
+    Default,
+
+    /// A flag on the command line.
+    Flag(String),
+
+    /// An attribute.
+    Attr(Loc),
+}
+
+/// The lint levels of a compilation.
+#[derive(Clone, Debug, Default)]
+pub struct LintContext {
+    /// The `-W`, `-A` and `-D` flags, in order.
+    flags: Vec<LintFlag>,
+
+    /// Whether warnings are turned into errors.
+    deny_warnings: bool,
+
+    /// The nodes lints may be reported in, from every module added so far.
+    scopes: Vec<Scope>,
+}
+
+impl LintContext {
+    /// Creates a context from the command line flags.
+    pub fn new(flags: Vec<LintFlag>, deny_warnings: bool) -> Self {
+        Self {
+            flags,
+            deny_warnings,
+            scopes: Vec::new(),
+        }
+    }
+
+    /// Records the lint attributes in `module`, reporting malformed ones and unknown lint names.
+    pub fn add_module(
+        &mut self,
+        module: &Module,
+        sources: &SourceMap,
+        diagnostics: &mut Diagnostics,
+    ) {
+        let mut collector = Collector {
+            sources,
+            diagnostics,
+            scopes: Vec::new(),
+        };
+        collector.visit_module(module);
+
+        // Scopes are searched from the outside in, so sort them by nesting.  A node always starts
+        // at or before its children and ends at or after them.
+        self.scopes.extend(collector.scopes);
+        self.scopes.sort_by(|a, b| {
+            (a.loc.file, a.loc.span.start, b.loc.span.end).cmp(&(
+                b.loc.file,
+                b.loc.span.start,
+                a.loc.span.end,
+            ))
+        });
+    }
+
+    /// Returns the level of `lint` at `loc`, and where it was set.
+    ///
+    /// [`WARNINGS`] only changes the level of lints that would otherwise be reported, so it
+    /// doesn't turn on lints that are allowed by default unless they were turned on by name.
+    fn level(&self, lint: &Lint, loc: &Loc) -> (Level, LevelSource) {
+        let mut level = (lint.default, LevelSource::Default);
+        let applies = |name: &str, current: Level| {
+            name == lint.name
+                || (name == WARNINGS && (lint.default != Level::Allow || current != Level::Allow))
+        };
+        for flag in &self.flags {
+            if applies(&flag.name, level.0) {
+                let name = format!("-{} {}", flag_letter(flag.level), flag.name);
+                level = (flag.level, LevelSource::Flag(name));
+            }
+        }
+
+        for scope in self.scopes.iter().filter(|scope| scope.contains(loc)) {
+            for attr in &scope.levels {
+                if applies(&attr.lint, level.0) {
+                    level = (attr.level, LevelSource::Attr(attr.loc.clone()));
+                }
+            }
+        }
+
+        level
+    }
+
+    /// Returns whether `lint` is reported at `loc`.  Passes can use this to skip work for lints
+    /// that are allowed.
+    pub fn enabled(&self, lint: &Lint, loc: &Loc) -> bool {
+        self.level(lint, loc).0 != Level::Allow
+    }
+
+    /// Reports `diagnostic` for `lint` at the level that applies to its primary location.
+    ///
+    /// The diagnostic's severity is replaced, it is tagged with the lint, and it gets a note on
+    /// where its level was set and a suggestion of the attribute that would silence it.
+    pub fn emit(&self, lint: &Lint, mut diagnostic: Diagnostic, diagnostics: &mut Diagnostics) {
+        let loc = diagnostic.primary.loc.clone();
+        let (mut level, source) = self.level(lint, &loc);
+        let denied_warning = level == Level::Warn && self.deny_warnings;
+        if denied_warning {
+            level = Level::Deny;
+        }
+
+        diagnostic.severity = match level {
+            Level::Allow => return,
+            Level::Warn => Severity::Warning,
+            Level::Deny => Severity::Error,
+        };
+        diagnostic.lint = Some(lint.name);
+
+        let set_by_attr = matches!(source, LevelSource::Attr(_));
+        diagnostic = match source {
+            LevelSource::Default => diagnostic.with_note(format!(
+                "`#{}({})` is on by default",
+                lint.default, lint.name
+            )),
+            LevelSource::Flag(flag) => {
+                diagnostic.with_note(format!("`{}` was given on the command line", flag))
+            }
+            LevelSource::Attr(attr) => {
+                diagnostic.with_secondary(attr, "the lint level is set here")
+            }
+        };
+        if denied_warning {
+            diagnostic = diagnostic.with_note("`--deny-warnings` turns warnings into errors");
+        }
+
+        // An attribute added before the one that set the level would be overridden by it.
+        if set_by_attr {
+            diagnostics.push(diagnostic.with_help(format!(
+                "to silence this, set `{}` to `allow` there instead",
+                lint.name
+            )));
+            return;
+        }
+
+        // Silence the lint on the innermost statement or item, which is the last one sorted.
+        let insert = self
+            .scopes
+            .iter()
+            .rev()
+            .filter(|scope| scope.contains(&loc))
+            .find_map(|scope| scope.insert.as_ref());
+        if let Some((offset, indent)) = insert {
+            let replacement = match indent {
+                Some(indent) => format!("#allow({})\n{}", lint.name, indent),
+                None => format!("#allow({}) ", lint.name),
+            };
+            diagnostic = diagnostic.with_suggestion(
+                format!("to silence this, add `#allow({})`", lint.name),
+                Loc::new(loc.file, *offset..*offset),
+                replacement,
+            );
+        }
+
+        diagnostics.push(diagnostic);
+    }
+}
+
+/// Returns the letter of the command line flag that sets `level`.
+fn flag_letter(level: Level) -> char {
+    match level {
+        Level::Allow => 'A',
+        Level::Warn => 'W',
+        Level::Deny => 'D',
+    }
+}
+
+/// The visitor that records the scope of every item, statement and attributed expression.
+struct Collector<'a> {
+    /// The files of the module.
+    sources: &'a SourceMap,
+
+    /// Where malformed lint attributes are reported.
+    diagnostics: &'a mut Diagnostics,
+
+    /// The scopes found so far.
+    scopes: Vec<Scope>,
+}
+
+impl Collector<'_> {
+    /// Returns the lint levels set by `attrs`, reporting malformed ones.
+    fn levels(&mut self, attrs: &[Attribute]) -> Vec<LevelAttr> {
+        let mut levels = Vec::new();
+        for attr in attrs {
+            let Some(level) = Level::from_attr(&attr_name(attr)) else {
+                continue;
+            };
+
+            for (name, loc) in arg_names(attr, "lint", self.diagnostics) {
+                if !is_known(name) {
+                    self.diagnostics.push(unknown_lint(name, loc.clone()));
+                    continue;
+                }
+
+                levels.push(LevelAttr {
+                    lint: name.to_string(),
+                    level,
+                    loc: attr.loc.clone(),
+                });
+            }
+        }
+
+        levels
+    }
+
+    /// Returns the indentation of the line `offset` is on in `file`.
+    fn indent(&self, file: u32, offset: usize) -> String {
+        let source = self.sources.get(file);
+        let line = source.line(source.line_col(offset).line);
+        line[..line.len() - line.trim_start().len()].to_string()
+    }
+}
+
+impl Visitor for Collector<'_> {
+    fn visit_item(&mut self, item: &Item) {
+        let levels = self.levels(&item.attrs);
+        if !matches!(item.kind, ItemKind::Error) {
+            let indent = self.indent(item.loc.file, item.loc.span.start);
+            self.scopes.push(Scope {
+                loc: item.loc.clone(),
+                levels,
+                insert: Some((item.loc.span.start, Some(indent))),
+            });
+        }
+
+        visit::walk_item(self, item);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        let levels = self.levels(&stmt.attrs);
+        self.scopes.push(Scope {
+            loc: stmt.loc.clone(),
+            levels,
+            insert: Some((stmt.loc.span.start, None)),
+        });
+
+        visit::walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        if !expr.attrs.is_empty() {
+            let levels = self.levels(&expr.attrs);
+            let start = expr.attrs[0].loc.span.start;
+            self.scopes.push(Scope {
+                loc: Loc::new(expr.loc.file, start..expr.loc.span.end),
+                levels,
+                insert: None,
+            });
+        }
+
+        visit::walk_expr(self, expr);
+    }
+}
+
+/// Returns the warning for the unknown lint `name` at `loc`.
+pub fn unknown_lint(name: &str, loc: Loc) -> Diagnostic {
+    let diagnostic = Diagnostic::warning(format!("unknown lint `{}`", name), loc.clone())
+        .with_label("unknown lint");
+    match similar(name) {
+        Some(similar) => diagnostic.with_suggestion(
+            format!("a lint with a similar name exists: `{}`", similar),
+            loc,
+            similar,
+        ),
+        None => diagnostic,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    const SRC: &str = "\
+#deny(warnings)
+fun a() {
+    val x = 1;
+}
+
+#allow(unused_variable)
+fun b() {
+    #warn(unused_variable) val y = 2;
+    val z = 3;
+}
+
+fun c() {
+    val w = 4;
+}
+";
+
+    /// Returns the lint context for [`SRC`] with `flags`, and the file of the source.
+    fn context(flags: &[(Level, &str)]) -> (LintContext, u32) {
+        let mut sources = SourceMap::new();
+        let file = sources.add("test.hl", SRC.to_string());
+        let mut diagnostics = Diagnostics::new();
+        let module = parser::parse(&sources, file, &mut diagnostics).expect("the source parses");
+        let flags = flags
+            .iter()
+            .map(|&(level, name)| LintFlag {
+                level,
+                name: name.to_string(),
+            })
+            .collect();
+        let mut lints = LintContext::new(flags, false);
+        lints.add_module(&module, &sources, &mut diagnostics);
+        assert!(diagnostics.iter().next().is_none());
+        (lints, file)
+    }
+
+    /// Returns the level of `lint` at the first `needle` in [`SRC`].
+    fn level(lints: &LintContext, file: u32, lint: &Lint, needle: &str) -> Level {
+        let start = SRC.find(needle).expect("the needle is in the source");
+        lints
+            .level(lint, &Loc::new(file, start..start + needle.len()))
+            .0
+    }
+
+    #[test]
+    fn defaults() {
+        let (lints, file) = context(&[]);
+        assert_eq!(level(&lints, file, &UNUSED_VARIABLE, "val w"), Level::Warn);
+        assert_eq!(level(&lints, file, &MISSING_DROP, "val w"), Level::Allow);
+    }
+
+    #[test]
+    fn later_flags_win() {
+        let (lints, file) =
+            context(&[(Level::Deny, "unused_variable"), (Level::Allow, "warnings")]);
+        assert_eq!(level(&lints, file, &UNUSED_VARIABLE, "val w"), Level::Allow);
+
+        let (lints, file) =
+            context(&[(Level::Allow, "warnings"), (Level::Deny, "unused_variable")]);
+        assert_eq!(level(&lints, file, &UNUSED_VARIABLE, "val w"), Level::Deny);
+        assert_eq!(level(&lints, file, &SHADOWING, "val w"), Level::Allow);
+    }
+
+    #[test]
+    fn warnings_flag_skips_allowed_lints() {
+        let (lints, file) = context(&[(Level::Deny, "warnings")]);
+        assert_eq!(level(&lints, file, &UNUSED_VARIABLE, "val w"), Level::Deny);
+        assert_eq!(level(&lints, file, &MISSING_DROP, "val w"), Level::Allow);
+
+        let (lints, file) = context(&[(Level::Warn, "missing_drop"), (Level::Deny, "warnings")]);
+        assert_eq!(level(&lints, file, &MISSING_DROP, "val w"), Level::Deny);
+    }
+
+    #[test]
+    fn attributes_override_flags() {
+        let (lints, file) = context(&[(Level::Deny, "unused_variable")]);
+        assert_eq!(level(&lints, file, &UNUSED_VARIABLE, "val z"), Level::Allow);
+        assert_eq!(level(&lints, file, &UNUSED_VARIABLE, "val w"), Level::Deny);
+        let start = SRC.find("val w").expect("the needle is in the source");
+        assert!(matches!(
+            lints
+                .level(&UNUSED_VARIABLE, &Loc::new(file, start..start + 5))
+                .1,
+            LevelSource::Flag(_)
+        ));
+    }
+
+    #[test]
+    fn inner_attributes_override_outer_ones() {
+        let (lints, file) = context(&[]);
+        assert_eq!(level(&lints, file, &UNUSED_VARIABLE, "val y"), Level::Warn);
+        assert_eq!(level(&lints, file, &UNUSED_VARIABLE, "val z"), Level::Allow);
+    }
+
+    #[test]
+    fn warnings_attribute_skips_allowed_lints() {
+        let (lints, file) = context(&[]);
+        assert_eq!(level(&lints, file, &UNUSED_VARIABLE, "val x"), Level::Deny);
+        assert_eq!(level(&lints, file, &MISSING_DROP, "val x"), Level::Allow);
+
+        let (lints, file) = context(&[(Level::Warn, "missing_drop")]);
+        assert_eq!(level(&lints, file, &MISSING_DROP, "val x"), Level::Deny);
+    }
+}
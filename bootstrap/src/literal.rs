@@ -0,0 +1,506 @@
+//! Decoding of literals.
+//!
+//! The lexer only finds where a literal starts and ends.  This module turns the literal's text
+//! into a value, and reports malformed literals at the exact part of the literal that is wrong.
+
+use crate::ast::{Expr, ExprKind, FloatLit, FloatTy, IntLit, IntTy, Lit};
+use crate::lexer::{LexError, LexErrorKind};
+use crate::Loc;
+
+/// The widest pointer of any supported target.  The target isn't known while parsing, so
+/// literals of the pointer-sized types `int` and `uint` are checked against this width.
+pub const MAX_PTR_BITS: u32 = 64;
+
+/// Returns the location of `range` within the literal at `loc`.
+fn sub_loc(loc: &Loc, range: std::ops::Range<usize>) -> Loc {
+    Loc::new(
+        loc.file,
+        loc.span.start + range.start..loc.span.start + range.end,
+    )
+}
+
+/// Returns an error of `kind` at `range` within the literal at `loc`.
+fn error(kind: LexErrorKind, loc: &Loc, range: std::ops::Range<usize>) -> LexError {
+    LexError {
+        kind,
+        loc: sub_loc(loc, range),
+    }
+}
+
+/// Decodes a string literal such as `"hi\n"`, `b"hi"`, `r#"hi"#` or `br"hi"`.
+pub fn parse_str(text: &str, loc: Loc) -> Result<Lit, LexError> {
+    let bytes = text.starts_with('b');
+    let prefix = bytes as usize;
+
+    let chars = if text[prefix..].starts_with('r') {
+        // Raw strings have no escapes, so only the quotes and hashes need to be stripped.
+        let hashes = text[prefix + 1..]
+            .bytes()
+            .take_while(|&c| c == b'#')
+            .count();
+        let start = prefix + hashes + 2;
+        let body = &text[start..text.len() - hashes - 1];
+
+        if bytes {
+            if let Some((i, c)) = body.char_indices().find(|(_, c)| !c.is_ascii()) {
+                let i = start + i;
+                return Err(error(
+                    LexErrorKind::NonAsciiInBytes(c),
+                    &loc,
+                    i..i + c.len_utf8(),
+                ));
+            }
+        }
+
+        body.chars().collect()
+    } else {
+        unescape(text, prefix + 1, bytes, &loc)?
+    };
+
+    Ok(if bytes {
+        Lit::ByteStr(chars.into_iter().map(|c| c as u8).collect())
+    } else {
+        Lit::Str(chars.into_iter().collect())
+    })
+}
+
+/// Decodes a character literal such as `'a'`, `'\u{1F600}'` or `b'\n'`.
+pub fn parse_char(text: &str, loc: Loc) -> Result<Lit, LexError> {
+    let bytes = text.starts_with('b');
+    let chars = unescape(text, bytes as usize + 1, bytes, &loc)?;
+
+    match (chars.as_slice(), bytes) {
+        ([c], false) => Ok(Lit::Char(*c)),
+        ([c], true) => Ok(Lit::Byte(*c as u8)),
+        ([], _) => Err(error(LexErrorKind::EmptyChar, &loc, 0..text.len())),
+        _ => Err(error(LexErrorKind::LongChar, &loc, 0..text.len())),
+    }
+}
+
+/// Decodes the escape codes in the quoted literal `text`, whose body starts at `start`.
+///
+/// In byte literals, each returned character is a byte value.
+fn unescape(text: &str, start: usize, bytes: bool, loc: &Loc) -> Result<Vec<char>, LexError> {
+    let body = &text[start..text.len() - 1];
+    let mut chars = body.char_indices().map(|(i, c)| (start + i, c)).peekable();
+    let mut out = Vec::new();
+
+    while let Some((i, c)) = chars.next() {
+        if c != '\\' {
+            if bytes && !c.is_ascii() {
+                return Err(error(
+                    LexErrorKind::NonAsciiInBytes(c),
+                    loc,
+                    i..i + c.len_utf8(),
+                ));
+            }
+
+            out.push(c);
+            continue;
+        }
+
+        let (_, escape) = chars
+            .next()
+            .expect("the lexer never ends a literal with a backslash");
+        let c = match escape {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '\\' => '\\',
+            '"' => '"',
+            '\'' => '\'',
+            'x' => {
+                let mut value = 0;
+                let mut end = i + 2;
+                for _ in 0..2 {
+                    match chars.next_if(|(_, c)| c.is_ascii_hexdigit()) {
+                        Some((j, digit)) => {
+                            value = value * 16 + digit.to_digit(16).unwrap_or_default();
+                            end = j + 1;
+                        }
+                        None => return Err(error(LexErrorKind::InvalidHexEscape, loc, i..end)),
+                    }
+                }
+
+                if !bytes && value > 0x7f {
+                    return Err(error(LexErrorKind::HexEscapeOutOfRange, loc, i..end));
+                }
+
+                char::from_u32(value).unwrap_or_default()
+            }
+            'u' if bytes => return Err(error(LexErrorKind::UnicodeEscapeInBytes, loc, i..i + 2)),
+            'u' => {
+                let mut end = i + 2;
+                if chars.next_if(|&(_, c)| c == '{').is_none() {
+                    return Err(error(LexErrorKind::InvalidUnicodeEscape, loc, i..end));
+                }
+
+                let mut value: u32 = 0;
+                let mut digits = 0;
+                loop {
+                    match chars.next() {
+                        Some((j, '}')) => {
+                            end = j + 1;
+                            break;
+                        }
+                        Some((j, digit)) if digit.is_ascii_hexdigit() && digits < 6 => {
+                            value = value * 16 + digit.to_digit(16).unwrap_or_default();
+                            digits += 1;
+                            end = j + 1;
+                        }
+                        Some((j, c)) => {
+                            end = j + c.len_utf8();
+                            return Err(error(LexErrorKind::InvalidUnicodeEscape, loc, i..end));
+                        }
+                        None => return Err(error(LexErrorKind::InvalidUnicodeEscape, loc, i..end)),
+                    }
+                }
+
+                match char::from_u32(value) {
+                    Some(c) if digits > 0 => c,
+                    _ => return Err(error(LexErrorKind::InvalidUnicodeEscape, loc, i..end)),
+                }
+            }
+            c => {
+                let end = i + 1 + c.len_utf8();
+                return Err(error(LexErrorKind::UnknownEscape(c), loc, i..end));
+            }
+        };
+
+        out.push(c);
+    }
+
+    Ok(out)
+}
+
+/// Decodes an integer literal such as `1_000`, `0xffu8` or `0b1010`.
+///
+/// Integer literals with a float suffix, such as `1f32`, are decoded as floating point literals.
+/// The value isn't checked against the suffix here; see [`check_int`].
+pub fn parse_int(text: &str, loc: Loc) -> Result<Lit, LexError> {
+    let (radix, prefix) = match text.get(..2) {
+        Some("0x") => (16, 2),
+        Some("0o") => (8, 2),
+        Some("0b") => (2, 2),
+        _ => (10, 0),
+    };
+
+    // The suffix starts at the first character that can't be a digit.  In octal and binary,
+    // out-of-range decimal digits are still treated as digits so they can be reported as such.
+    let body = &text[prefix..];
+    let digits_len = body
+        .find(|c: char| {
+            let digit = match radix {
+                16 => c.is_ascii_hexdigit(),
+                _ => c.is_ascii_digit(),
+            };
+            !digit && c != '_'
+        })
+        .unwrap_or(body.len());
+    let (digits, suffix) = body.split_at(digits_len);
+
+    let mut value: u128 = 0;
+    let mut empty = true;
+    for (i, c) in digits.char_indices().filter(|&(_, c)| c != '_') {
+        let digit = c.to_digit(radix).ok_or_else(|| LexError {
+            kind: LexErrorKind::InvalidDigit(c, radix),
+            loc: sub_loc(&loc, prefix + i..prefix + i + 1),
+        })?;
+
+        value = value
+            .checked_mul(radix as u128)
+            .and_then(|value| value.checked_add(digit as u128))
+            .ok_or_else(|| LexError {
+                kind: LexErrorKind::IntOutOfRange(None),
+                loc: loc.clone(),
+            })?;
+        empty = false;
+    }
+
+    if empty {
+        return Err(LexError {
+            kind: LexErrorKind::EmptyNumber,
+            loc,
+        });
+    }
+
+    if suffix.is_empty() {
+        return Ok(Lit::Int(IntLit {
+            value,
+            suffix: None,
+        }));
+    }
+
+    if let Some(ty) = IntTy::from_suffix(suffix) {
+        return Ok(Lit::Int(IntLit {
+            value,
+            suffix: Some(ty),
+        }));
+    }
+
+    match FloatTy::from_suffix(suffix) {
+        Some(ty) if radix == 10 => float(value as f64, Some(ty), loc),
+        _ => Err(LexError {
+            kind: LexErrorKind::InvalidSuffix(suffix.to_string()),
+            loc: sub_loc(&loc, text.len() - suffix.len()..text.len()),
+        }),
+    }
+}
+
+/// Decodes a floating point literal such as `1.5`, `2e-3` or `0.25f32`.
+pub fn parse_float(text: &str, loc: Loc) -> Result<Lit, LexError> {
+    let bytes = text.as_bytes();
+    let digit_at = |i: usize| bytes.get(i).is_some_and(|c| c.is_ascii_digit());
+
+    // Find where the suffix starts.  An `e` is only an exponent if digits follow it.
+    let mut end = 0;
+    while end < bytes.len() {
+        match bytes[end] {
+            b'0'..=b'9' | b'_' | b'.' => end += 1,
+            b'e' | b'E' if digit_at(end + 1) => end += 1,
+            b'e' | b'E' if matches!(bytes.get(end + 1), Some(b'+' | b'-')) && digit_at(end + 2) => {
+                end += 2
+            }
+            _ => break,
+        }
+    }
+
+    let (body, suffix) = text.split_at(end);
+    let value = body.replace('_', "").parse::<f64>().map_err(|_| LexError {
+        kind: LexErrorKind::EmptyNumber,
+        loc: loc.clone(),
+    })?;
+
+    let suffix = match suffix {
+        "" => None,
+        _ => Some(FloatTy::from_suffix(suffix).ok_or_else(|| LexError {
+            kind: LexErrorKind::InvalidSuffix(suffix.to_string()),
+            loc: sub_loc(&loc, end..text.len()),
+        })?),
+    };
+
+    float(value, suffix, loc)
+}
+
+/// Creates a floating point literal, checking that it fits in its type.
+fn float(value: f64, suffix: Option<FloatTy>, loc: Loc) -> Result<Lit, LexError> {
+    let ty = suffix.unwrap_or(FloatTy::Float64);
+    let infinite = match ty {
+        FloatTy::Float32 => (value as f32).is_infinite(),
+        FloatTy::Float64 => value.is_infinite(),
+    };
+
+    if infinite {
+        return Err(LexError {
+            kind: LexErrorKind::FloatOutOfRange(ty),
+            loc,
+        });
+    }
+
+    Ok(Lit::Float(FloatLit { value, suffix }))
+}
+
+/// Checks that an integer literal, negated if `negative` is set, fits in `ty`, with `int` and
+/// `uint` being `ptr_bits` wide.
+///
+/// Unsuffixed literals are checked against the widest integer types when parsing, and against
+/// their inferred type once it is known.  Literals of `int` and `uint` are checked against the
+/// target's pointer width once that is known.
+pub fn check_int(
+    lit: &IntLit,
+    ty: Option<IntTy>,
+    negative: bool,
+    ptr_bits: u32,
+    loc: &Loc,
+) -> Result<(), LexError> {
+    let fits = match ty {
+        Some(ty) => ty.fits(lit.value, negative, ptr_bits),
+        None if negative => IntTy::Int64.fits(lit.value, true, ptr_bits),
+        None => IntTy::Uint64.fits(lit.value, false, ptr_bits),
+    };
+
+    if fits {
+        Ok(())
+    } else {
+        Err(LexError {
+            kind: LexErrorKind::IntOutOfRange(ty),
+            loc: loc.clone(),
+        })
+    }
+}
+
+/// Checks `expr` against its own suffix if it is an integer literal.  `negative` is set when the
+/// literal is directly negated, as in `-128i8`.
+pub fn check_int_expr(expr: &Expr, negative: bool) -> Result<(), LexError> {
+    match &expr.kind {
+        ExprKind::Lit(Lit::Int(lit)) => {
+            check_int(lit, lit.suffix, negative, MAX_PTR_BITS, &expr.loc)
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The location of a literal at the start of file 0.
+    fn loc(text: &str) -> Loc {
+        Loc::new(0, 0..text.len())
+    }
+
+    /// Returns the kind and span of the error decoding `text` with `parse`.
+    fn error_at(
+        parse: fn(&str, Loc) -> Result<Lit, LexError>,
+        text: &str,
+    ) -> (LexErrorKind, std::ops::Range<usize>) {
+        let error = parse(text, loc(text)).expect_err("the literal is malformed");
+        (error.kind, error.loc.span)
+    }
+
+    #[test]
+    fn escapes() {
+        let lit = parse_str(r#""a\n\t\r\0\\\"\'""#, loc("")).unwrap();
+        assert_eq!(lit, Lit::Str("a\n\t\r\0\\\"'".to_string()));
+        let lit = parse_str(r#""\x41\u{1F600}""#, loc("")).unwrap();
+        assert_eq!(lit, Lit::Str("A\u{1F600}".to_string()));
+        assert_eq!(parse_char(r"'\u{e9}'", loc("")).unwrap(), Lit::Char('é'));
+        assert_eq!(parse_char(r"b'\xff'", loc("")).unwrap(), Lit::Byte(0xff));
+        let lit = parse_str(r#"b"\x00\xff""#, loc("")).unwrap();
+        assert_eq!(lit, Lit::ByteStr(vec![0, 0xff]));
+    }
+
+    #[test]
+    fn raw_strings() {
+        let lit = parse_str(r###"r#"a\n"b"#"###, loc("")).unwrap();
+        assert_eq!(lit, Lit::Str(r#"a\n"b"#.to_string()));
+        let lit = parse_str(r#"br"\x""#, loc("")).unwrap();
+        assert_eq!(lit, Lit::ByteStr(b"\\x".to_vec()));
+    }
+
+    #[test]
+    fn malformed_escapes() {
+        assert_eq!(
+            error_at(parse_str, r#""ab\q""#),
+            (LexErrorKind::UnknownEscape('q'), 3..5)
+        );
+        assert_eq!(
+            error_at(parse_str, r#""\x4""#),
+            (LexErrorKind::InvalidHexEscape, 1..4)
+        );
+        assert_eq!(
+            error_at(parse_str, r#""\x80""#),
+            (LexErrorKind::HexEscapeOutOfRange, 1..5)
+        );
+        assert_eq!(
+            error_at(parse_str, r#""\u{110000}""#),
+            (LexErrorKind::InvalidUnicodeEscape, 1..11)
+        );
+        assert_eq!(
+            error_at(parse_str, r#""\u{}""#),
+            (LexErrorKind::InvalidUnicodeEscape, 1..5)
+        );
+        assert_eq!(
+            error_at(parse_str, r#"b"\u{41}""#),
+            (LexErrorKind::UnicodeEscapeInBytes, 2..4)
+        );
+        assert_eq!(
+            error_at(parse_str, "b\"é\""),
+            (LexErrorKind::NonAsciiInBytes('é'), 2..4)
+        );
+    }
+
+    #[test]
+    fn chars() {
+        assert_eq!(parse_char("'a'", loc("")).unwrap(), Lit::Char('a'));
+        assert_eq!(error_at(parse_char, "''"), (LexErrorKind::EmptyChar, 0..2));
+        assert_eq!(error_at(parse_char, "'ab'"), (LexErrorKind::LongChar, 0..4));
+    }
+
+    #[test]
+    fn integers() {
+        let int = |text: &str| match parse_int(text, loc(text)).unwrap() {
+            Lit::Int(lit) => (lit.value, lit.suffix),
+            lit => panic!("expected an integer, found {:?}", lit),
+        };
+        assert_eq!(int("1_000"), (1000, None));
+        assert_eq!(int("0xffu8"), (255, Some(IntTy::Uint8)));
+        assert_eq!(int("0o17"), (15, None));
+        assert_eq!(int("0b1010i64"), (10, Some(IntTy::Int64)));
+        assert_eq!(
+            parse_int("2f32", loc("2f32")).unwrap(),
+            Lit::Float(FloatLit {
+                value: 2.0,
+                suffix: Some(FloatTy::Float32)
+            })
+        );
+
+        assert_eq!(
+            error_at(parse_int, "0b102"),
+            (LexErrorKind::InvalidDigit('2', 2), 4..5)
+        );
+        assert_eq!(
+            error_at(parse_int, "12u7"),
+            (LexErrorKind::InvalidSuffix("u7".to_string()), 2..4)
+        );
+        assert_eq!(error_at(parse_int, "0x"), (LexErrorKind::EmptyNumber, 0..2));
+        assert_eq!(
+            error_at(parse_int, "0x1_0000_0000_0000_0000_0000_0000_0000_0000"),
+            (LexErrorKind::IntOutOfRange(None), 0..43)
+        );
+    }
+
+    #[test]
+    fn integer_ranges() {
+        let fits = |value, ty, negative, ptr_bits| {
+            let lit = IntLit {
+                value,
+                suffix: None,
+            };
+            check_int(&lit, ty, negative, ptr_bits, &loc("")).is_ok()
+        };
+        assert!(fits(255, Some(IntTy::Uint8), false, 64));
+        assert!(!fits(256, Some(IntTy::Uint8), false, 64));
+        assert!(fits(128, Some(IntTy::Int8), true, 64));
+        assert!(!fits(128, Some(IntTy::Int8), false, 64));
+        assert!(fits(0, Some(IntTy::Uint), true, 64));
+        assert!(!fits(1, Some(IntTy::Uint), true, 64));
+        assert!(fits(u32::MAX as u128, Some(IntTy::Uint), false, 32));
+        assert!(!fits(1 << 32, Some(IntTy::Uint), false, 32));
+        assert!(fits(1 << 32, Some(IntTy::Uint), false, 64));
+
+        // Unsuffixed literals are checked against the widest types.
+        assert!(fits(u64::MAX as u128, None, false, 64));
+        assert!(!fits(1 << 64, None, false, 64));
+        assert!(fits(1 << 63, None, true, 64));
+        assert!(!fits((1 << 63) + 1, None, true, 64));
+    }
+
+    #[test]
+    fn floats() {
+        let float = |text: &str| parse_float(text, loc(text)).unwrap();
+        assert_eq!(
+            float("1.5"),
+            Lit::Float(FloatLit {
+                value: 1.5,
+                suffix: None
+            })
+        );
+        assert_eq!(
+            float("2e-3f32"),
+            Lit::Float(FloatLit {
+                value: 2e-3,
+                suffix: Some(FloatTy::Float32)
+            })
+        );
+        assert_eq!(
+            error_at(parse_float, "1e39f32"),
+            (LexErrorKind::FloatOutOfRange(FloatTy::Float32), 0..7)
+        );
+        assert_eq!(
+            error_at(parse_float, "1.0f16"),
+            (LexErrorKind::InvalidSuffix("f16".to_string()), 3..6)
+        );
+    }
+}
@@ -0,0 +1,443 @@
+//! Loading of the modules a program imports.
+//!
+//! `import { .. } from a::b;` imports from the module `a::b`, which is the file `a/b.hl` in the
+//! first root that has it.  The roots are the directory of the importing file, then the standard
+//! library, then each `-I` directory.  Every file is loaded once, however many modules import
+//! it, and gets its own id in the [`SourceMap`].
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::ast::{self, ImportName, Item, ItemKind, Module};
+use crate::diagnostic::{similar_name, Diagnostic, Diagnostics};
+use crate::parser;
+use crate::source::SourceMap;
+use crate::Loc;
+
+/// The extension of Hail source files.
+pub const EXTENSION: &str = "hl";
+
+/// The environment variable that overrides the directory of the standard library.
+pub const STDLIB_VAR: &str = "HAIL_STDLIB";
+
+/// Returns the directory of the standard library: the value of [`STDLIB_VAR`] if it is set,
+/// otherwise the `library` directory of the source tree the compiler was built from.
+pub fn stdlib_dir() -> PathBuf {
+    match std::env::var_os(STDLIB_VAR) {
+        Some(dir) => PathBuf::from(dir),
+        None => canonical(&Path::new(env!("CARGO_MANIFEST_DIR")).join("../library")),
+    }
+}
+
+/// A loaded module.
+#[derive(Clone, Debug)]
+pub struct LoadedModule {
+    /// The name of the module, such as `fmt`.  The root module is named after its file.
+    pub name: String,
+
+    /// The syntax tree of the module.
+    pub ast: Module,
+
+    /// The names brought in by the module's imports, in the order they were written.
+    pub imports: Vec<ResolvedImport>,
+}
+
+/// A name brought in by an import, along with the item it refers to.
+#[derive(Clone, Debug)]
+pub struct ResolvedImport {
+    /// The name as written in the import.
+    pub name: ImportName,
+
+    /// The file id of the module the name is imported from.
+    pub module: u32,
+
+    /// The index of the item in that module.
+    pub item: usize,
+}
+
+/// A program: the root module and every module it imports, directly or not.
+#[derive(Clone, Debug)]
+pub struct Program {
+    /// The modules, each after the modules it imports, so the root module comes last.
+    pub modules: Vec<LoadedModule>,
+}
+
+impl Program {
+    /// Returns the module the program was loaded from.
+    pub fn root(&self) -> &LoadedModule {
+        self.modules
+            .last()
+            .expect("a program always has a root module")
+    }
+
+    /// Returns the module loaded from the file with the id `file`.
+    pub fn get(&self, file: u32) -> Option<&LoadedModule> {
+        self.modules.iter().find(|module| module.ast.file == file)
+    }
+
+    /// Returns the item that `import` refers to.
+    pub fn item(&self, import: &ResolvedImport) -> &Item {
+        &self
+            .get(import.module)
+            .expect("imports refer to loaded modules")
+            .ast
+            .items[import.item]
+    }
+}
+
+/// Loads every module that `root` imports, directly or not, and returns the program made of
+/// them.  `search_paths` are the `-I` directories.
+///
+/// Missing modules, import cycles and imports of names a module doesn't define are reported to
+/// `diagnostics`, and the imports involved are left out of the program.
+pub fn load(
+    root: Module,
+    search_paths: &[PathBuf],
+    sources: &mut SourceMap,
+    diagnostics: &mut Diagnostics,
+) -> Program {
+    let path = &sources.get(root.file).path;
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut files = HashMap::new();
+    files.insert(canonical(path), FileState::Loading);
+
+    let mut loader = Loader {
+        search_paths,
+        stdlib: stdlib_dir(),
+        sources,
+        diagnostics,
+        files,
+        stack: Vec::new(),
+        modules: Vec::new(),
+    };
+    loader.load_module(name, root, None);
+
+    Program {
+        modules: loader.modules,
+    }
+}
+
+/// How far along a file is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FileState {
+    /// The file's imports are being loaded.
+    Loading,
+
+    /// The file has been loaded, and has the given id.
+    Loaded(u32),
+
+    /// The file couldn't be read or parsed, which has already been reported.
+    Failed,
+}
+
+/// A module whose imports are being loaded.
+#[derive(Debug)]
+struct Pending {
+    /// The name of the module.
+    name: String,
+
+    /// The canonical path of the module's file.
+    path: PathBuf,
+
+    /// The path in the import that loaded the module, or `None` for the root module.
+    import: Option<Loc>,
+}
+
+/// The state of [`load`].
+struct Loader<'a> {
+    /// The `-I` directories.
+    search_paths: &'a [PathBuf],
+
+    /// The directory of the standard library.
+    stdlib: PathBuf,
+
+    /// Where loaded files are added.
+    sources: &'a mut SourceMap,
+
+    /// Where errors are reported.
+    diagnostics: &'a mut Diagnostics,
+
+    /// The files seen so far, by their canonical paths.
+    files: HashMap<PathBuf, FileState>,
+
+    /// The modules whose imports are being loaded, innermost last.
+    stack: Vec<Pending>,
+
+    /// The modules loaded so far.
+    modules: Vec<LoadedModule>,
+}
+
+impl Loader<'_> {
+    /// Loads the imports of `module`, then adds it to the loaded modules.  `import` is the path in
+    /// the import that loaded it.
+    fn load_module(&mut self, name: String, module: Module, import: Option<Loc>) {
+        let path = canonical(&self.sources.get(module.file).path);
+        self.stack.push(Pending {
+            name,
+            path: path.clone(),
+            import,
+        });
+
+        let dir = self
+            .sources
+            .get(module.file)
+            .path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let mut imports = Vec::new();
+        for item in &module.items {
+            let ItemKind::Import(import) = &item.kind else {
+                continue;
+            };
+            let Some(file) = self.import(&dir, &import.module) else {
+                continue;
+            };
+            for name in &import.names {
+                if let Some(item) = self.lookup(file, name) {
+                    imports.push(ResolvedImport {
+                        name: name.clone(),
+                        module: file,
+                        item,
+                    });
+                }
+            }
+        }
+
+        let name = self.stack.pop().expect("the module was pushed").name;
+        self.files.insert(path, FileState::Loaded(module.file));
+        self.modules.push(LoadedModule {
+            name,
+            ast: module,
+            imports,
+        });
+    }
+
+    /// Loads the module at `path`, imported from a file in `dir`, and returns its file id.
+    /// Returns `None` if it couldn't be loaded, which has been reported.
+    fn import(&mut self, dir: &Path, path: &ast::Path) -> Option<u32> {
+        let name = module_name(path);
+        let candidates: Vec<PathBuf> = self
+            .roots(dir)
+            .iter()
+            .map(|root| module_path(root, path))
+            .collect();
+        let Some(found) = candidates.iter().find(|candidate| candidate.is_file()) else {
+            let diagnostic = self.unknown_module(path, &candidates);
+            self.diagnostics.push(diagnostic);
+            return None;
+        };
+
+        let canonical = canonical(found);
+        match self.files.get(&canonical) {
+            Some(FileState::Loaded(file)) => return Some(*file),
+            Some(FileState::Failed) => return None,
+            Some(FileState::Loading) => {
+                let diagnostic = self.cycle(&name, &canonical, path.loc.clone());
+                self.diagnostics.push(diagnostic);
+                return None;
+            }
+            None => {}
+        }
+
+        let file = match self.sources.load(found) {
+            Ok(file) => file,
+            Err(error) => {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        format!("couldn't read module `{}`: {}", name, error),
+                        path.loc.clone(),
+                    )
+                    .with_label(format!("imported from `{}`", found.display())),
+                );
+                self.files.insert(canonical, FileState::Failed);
+                return None;
+            }
+        };
+
+        let Some(module) = parser::parse(self.sources, file, self.diagnostics) else {
+            self.files.insert(canonical, FileState::Failed);
+            return None;
+        };
+        self.files.insert(canonical, FileState::Loading);
+        self.load_module(name, module, Some(path.loc.clone()));
+        Some(file)
+    }
+
+    /// Returns the directories modules are looked for in, from a file in `dir`.
+    fn roots(&self, dir: &Path) -> Vec<PathBuf> {
+        let mut roots = vec![dir.to_path_buf(), self.stdlib.clone()];
+        roots.extend(self.search_paths.iter().cloned());
+        roots
+    }
+
+    /// Returns the index of the item that `name` imports from the module in `file`.  Returns
+    /// `None` and reports an error if the module doesn't define it.
+    fn lookup(&mut self, file: u32, name: &ImportName) -> Option<usize> {
+        let module = self
+            .modules
+            .iter()
+            .find(|module| module.ast.file == file)
+            .expect("imported modules are loaded first");
+        let items = &module.ast.items;
+        if let Some(index) = items
+            .iter()
+            .position(|item| item.name().is_some_and(|iden| iden.name == name.name.name))
+        {
+            return Some(index);
+        }
+
+        // The name may be an item that couldn't be parsed, which has already been reported.
+        if items.iter().any(|item| item.kind == ItemKind::Error) {
+            return None;
+        }
+
+        let iden = &name.name;
+        let mut diagnostic = Diagnostic::error(
+            format!(
+                "module `{}` has no item called `{}`",
+                module.name, iden.name
+            ),
+            iden.loc.clone(),
+        )
+        .with_label(format!("not found in `{}`", module.name));
+
+        let reexported = module
+            .imports
+            .iter()
+            .any(|import| import.name.binding().name == iden.name);
+        if reexported {
+            diagnostic = diagnostic.with_note(format!(
+                "`{}` imports `{}`, but imported names can't be imported from it in turn",
+                module.name, iden.name
+            ));
+        } else if let Some(similar) = similar_name(
+            &iden.name,
+            items
+                .iter()
+                .filter_map(|item| item.name())
+                .map(|iden| iden.name.as_str()),
+        ) {
+            diagnostic = diagnostic.with_suggestion(
+                format!("an item with a similar name exists: `{}`", similar),
+                iden.loc.clone(),
+                similar,
+            );
+        }
+
+        self.diagnostics.push(diagnostic);
+        None
+    }
+
+    /// Returns the error for an import of the module at `path`, which isn't at any of the
+    /// `candidates`.
+    fn unknown_module(&self, path: &ast::Path, candidates: &[PathBuf]) -> Diagnostic {
+        let name = module_name(path);
+        let mut looked: Vec<String> = candidates
+            .iter()
+            .map(|candidate| format!("`{}`", candidate.display()))
+            .collect();
+        let last_looked = looked.pop().expect("there is always a root");
+        let looked = match looked.is_empty() {
+            true => last_looked,
+            false => format!("{} and {}", looked.join(", "), last_looked),
+        };
+        let diagnostic = Diagnostic::error(format!("unknown module `{}`", name), path.loc.clone())
+            .with_label(format!("no module called `{}`", name))
+            .with_note(format!("looked for it at {}", looked));
+
+        // Suggest a module with a similar name next to one of the places that were looked at.
+        let last = path.segments.last().expect("paths have a segment");
+        let mut siblings = Vec::new();
+        for dir in candidates.iter().filter_map(|candidate| candidate.parent()) {
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().is_some_and(|ext| ext == EXTENSION) {
+                    if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+                        siblings.push(stem.to_string());
+                    }
+                }
+            }
+        }
+
+        match similar_name(&last.name, siblings.iter().map(String::as_str)) {
+            Some(similar) => diagnostic.with_suggestion(
+                format!("a module with a similar name exists: `{}`", similar),
+                last.loc.clone(),
+                similar,
+            ),
+            None => diagnostic
+                .with_help("add the directory the module is in to the search path with `-I <dir>`"),
+        }
+    }
+
+    /// Returns the error for an import at `loc` of the module `name`, whose file at `path` is
+    /// still loading.
+    fn cycle(&self, name: &str, path: &Path, loc: Loc) -> Diagnostic {
+        let start = self
+            .stack
+            .iter()
+            .position(|pending| pending.path == path)
+            .unwrap_or(0);
+        let cycle = &self.stack[start..];
+        let importer = &self.stack.last().expect("the importer is loading").name;
+
+        let mut diagnostic = Diagnostic::error(
+            format!("cycle detected when importing module `{}`", name),
+            loc,
+        )
+        .with_label(format!(
+            "`{}` imports `{}`, which is still loading",
+            importer, name
+        ));
+        for pair in cycle.windows(2) {
+            if let Some(import) = &pair[1].import {
+                diagnostic = diagnostic.with_secondary(
+                    import.clone(),
+                    format!("`{}` imports `{}` here", pair[0].name, pair[1].name),
+                );
+            }
+        }
+
+        let mut names: Vec<String> = cycle
+            .iter()
+            .map(|pending| format!("`{}`", pending.name))
+            .collect();
+        names.push(format!("`{}`", name));
+        diagnostic
+            .with_note(format!("the cycle is {}", names.join(" -> ")))
+            .with_help("move the items both modules need into a module of their own")
+    }
+}
+
+/// Returns the name of the module at `path`, such as `a::b`.
+fn module_name(path: &ast::Path) -> String {
+    let segments: Vec<&str> = path
+        .segments
+        .iter()
+        .map(|segment| segment.name.as_str())
+        .collect();
+    segments.join("::")
+}
+
+/// Returns where the module at `path` would be under `root`.
+fn module_path(root: &Path, path: &ast::Path) -> PathBuf {
+    let mut file = root.to_path_buf();
+    for segment in &path.segments {
+        file.push(&segment.name);
+    }
+    file.set_extension(EXTENSION);
+    file
+}
+
+/// Returns the canonical form of `path`, or `path` itself if it doesn't exist.
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
@@ -4,14 +4,34 @@
 #![deny(missing_debug_implementations)]
 
 use std::ops::Range;
+use std::process::ExitCode;
 
 use lalrpop_util::lalrpop_mod;
 
 pub mod ast;
-lalrpop_mod!(#[allow(missing_docs)] #[allow(missing_debug_implementations)] pub grammar);
+pub mod attr;
+pub mod cast;
+pub mod cli;
+pub mod diagnostic;
+pub mod driver;
+pub mod flow;
+lalrpop_mod!(#[allow(missing_docs)] #[allow(missing_debug_implementations)] #[allow(clippy::all)] pub grammar);
+pub mod lexer;
+pub mod lint;
+pub mod literal;
+pub mod loader;
+pub mod mixin;
+pub mod parser;
+pub mod resolve;
+pub mod source;
+pub mod target;
+pub mod typeck;
+pub mod types;
+pub mod visibility;
+pub mod visit;
 
 /// A source location.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Loc {
     /// The file of the location.
     pub file: u32,
@@ -28,6 +48,20 @@ impl Loc {
     }
 }
 
-fn main() {
-    println!("Hello, world!");
+fn main() -> ExitCode {
+    match cli::parse_args(std::env::args().skip(1)) {
+        Ok(cli::Action::Compile(options)) => driver::Session::new(options).run(),
+        Ok(cli::Action::Help) => {
+            println!("{}", cli::USAGE);
+            ExitCode::SUCCESS
+        }
+        Ok(cli::Action::Version) => {
+            println!("hail {}", env!("CARGO_PKG_VERSION"));
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("error: {}\n\n{}", error, cli::USAGE);
+            ExitCode::from(driver::EXIT_USAGE)
+        }
+    }
 }
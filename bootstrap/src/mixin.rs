@@ -0,0 +1,724 @@
+//! Mixin instantiation.
+//!
+//! A mixin is a struct, enum, union or trait declared with `mixin` and parameterized over types,
+//! such as `DynArray!<T: Mem>`.  Each distinct argument list it is instantiated with, such as
+//! `DynArray!<uint32>`, makes one instance, which is a copy of the mixin with its parameters
+//! replaced by the arguments.  Instantiating it again with the same arguments reuses the instance.
+//!
+//! The bounds on the parameters are checked where the mixin is instantiated.  Instantiations
+//! inside a mixin, such as a field of type `DynArray!<T>`, only get concrete arguments once the
+//! mixin itself is instantiated, so they are checked then, and errors in them point back through
+//! every instantiation that led to them.
+//!
+//! An `impl!` block applies to the instances whose arguments satisfy its bounds, which can be
+//! negative, as in `T: !Drop`.  Exactly one `impl!` block of each trait must apply to an
+//! instance, so that which one its functions come from is never ambiguous.
+//!
+//! The functions of `impl!` blocks are checked generically, and again for each instance their
+//! block is chosen for, with its arguments.  That second check expands the instantiations in the
+//! functions, and the errors only it finds point back through the instantiations like the
+//! errors of nested instantiations do.
+
+use std::collections::HashMap;
+
+use crate::ast::{Bound, Impl, ItemKind, Type, TypeKind};
+use crate::diagnostic::Diagnostic;
+use crate::loader::Program;
+use crate::resolve::{self, ItemId, Res};
+use crate::typeck::{item_fun, Checker};
+use crate::types::Ty;
+use crate::Loc;
+
+/// How deeply instantiations may nest inside other instances before expansion gives up, which
+/// stops mixins that instantiate themselves with ever larger arguments.
+pub const EXPANSION_LIMIT: usize = 32;
+
+/// The id of an instance of a mixin.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct InstanceId(pub usize);
+
+/// An instance of a mixin for one argument list.
+#[derive(Clone, Debug)]
+pub struct Instance {
+    /// The mixin.
+    pub item: ItemId,
+
+    /// The arguments, none of which refer to generic parameters.
+    pub args: Vec<Ty>,
+
+    /// Where the instance was first asked for.
+    pub loc: Loc,
+
+    /// The instance whose expansion asked for this one, if it was asked for inside a mixin.
+    pub parent: Option<InstanceId>,
+}
+
+impl Instance {
+    /// Returns the type of the instance.
+    pub fn ty(&self) -> Ty {
+        Ty::Adt(self.item, self.args.clone())
+    }
+}
+
+/// Every instance of every mixin in a program.
+#[derive(Clone, Debug, Default)]
+pub struct Instances {
+    /// The instances, in the order they were first asked for.
+    instances: Vec<Instance>,
+
+    /// The ids of the instances, by mixin and arguments.
+    ids: HashMap<(ItemId, Vec<Ty>), InstanceId>,
+}
+
+impl Instances {
+    /// Returns the instance `id`.
+    pub fn get(&self, id: InstanceId) -> &Instance {
+        &self.instances[id.0]
+    }
+
+    /// Returns the instance of the mixin `item` for `args`, if there is one.
+    pub fn find(&self, item: ItemId, args: &[Ty]) -> Option<InstanceId> {
+        self.ids.get(&(item, args.to_vec())).copied()
+    }
+
+    /// Returns the instance of `instance.item` for `instance.args`, adding `instance` if there
+    /// isn't one yet.  The flag is set if it was added.
+    pub fn insert(&mut self, instance: Instance) -> (InstanceId, bool) {
+        let key = (instance.item, instance.args.clone());
+        if let Some(&id) = self.ids.get(&key) {
+            return (id, false);
+        }
+
+        let id = InstanceId(self.instances.len());
+        self.instances.push(instance);
+        self.ids.insert(key, id);
+        (id, true)
+    }
+
+    /// Returns the number of instances.
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    /// Returns whether there are no instances.
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    /// Returns every instance, in the order they were first asked for.
+    pub fn iter(&self) -> impl Iterator<Item = (InstanceId, &Instance)> {
+        self.instances
+            .iter()
+            .enumerate()
+            .map(|(index, instance)| (InstanceId(index), instance))
+    }
+
+    /// Returns how many instances `id` is nested in, which is 0 for an instance asked for
+    /// outside of any mixin.
+    pub fn depth(&self, id: InstanceId) -> usize {
+        let mut depth = 0;
+        let mut parent = self.get(id).parent;
+        while let Some(id) = parent {
+            depth += 1;
+            parent = self.get(id).parent;
+        }
+        depth
+    }
+
+    /// Returns the instance that was asked for outside of any mixin and whose expansion led to
+    /// `id`.
+    pub fn root(&self, mut id: InstanceId) -> InstanceId {
+        while let Some(parent) = self.get(id).parent {
+            id = parent;
+        }
+        id
+    }
+
+    /// Adds to `diagnostic`, for an error inside the expansion of `id`, where `id` and every
+    /// instance whose expansion led to it were asked for.
+    pub fn backtrace(
+        &self,
+        program: &Program,
+        id: InstanceId,
+        mut diagnostic: Diagnostic,
+    ) -> Diagnostic {
+        let mut next = Some(id);
+        let mut last = None;
+        while let Some(id) = next {
+            let instance = self.get(id);
+            next = instance.parent;
+            // A mixin that instantiates itself would repeat the same location over and over.
+            if last == Some(&instance.loc) {
+                continue;
+            }
+            last = Some(&instance.loc);
+            diagnostic = diagnostic.with_secondary(
+                instance.loc.clone(),
+                format!(
+                    "in this instantiation of `{}`",
+                    instance.ty().display(program)
+                ),
+            );
+        }
+        diagnostic
+    }
+}
+
+/// Returns whether `item` is declared with `mixin`.
+pub fn is_mixin(program: &Program, item: ItemId) -> bool {
+    match &resolve::item(program, item).kind {
+        ItemKind::Struct(struct_) | ItemKind::Union(struct_) => struct_.mixin,
+        ItemKind::Enum(enum_) => enum_.mixin,
+        ItemKind::Trait(trait_) => trait_.mixin,
+        _ => false,
+    }
+}
+
+/// Returns whether `ty` is concrete enough to instantiate a mixin with: it doesn't refer to
+/// generic parameters or `Self`, and has no errors.
+pub fn is_concrete(ty: &Ty) -> bool {
+    match ty {
+        Ty::Param(..) | Ty::SelfParam(_) | Ty::Error | Ty::IntVar(_) | Ty::FloatVar(_) => false,
+        Ty::Ptr(_, pointee) | Ty::Ref(_, pointee) => is_concrete(pointee),
+        Ty::Adt(_, args) => args.iter().all(is_concrete),
+        Ty::Fun(params, ret) => params.iter().all(is_concrete) && is_concrete(ret),
+        Ty::Int(_) | Ty::Float(_) | Ty::Bool | Ty::Char | Ty::Str | Ty::Unit | Ty::Never => true,
+    }
+}
+
+/// Returns whether `found`, found by checking the functions of an `impl!` block for an instance,
+/// is the same as `reported`, which checking them generically or for another instance already
+/// reported.  Errors that mention the arguments of the instance are its own.
+fn same_error(reported: &Diagnostic, found: &Diagnostic) -> bool {
+    reported.severity == found.severity
+        && reported.message == found.message
+        && reported.primary.loc == found.primary.loc
+}
+
+impl<'a> Checker<'a> {
+    /// Expands every instance of every mixin, including the instances that expanding them asks
+    /// for.
+    pub(crate) fn expand_instances(&mut self) {
+        let mut next = 0;
+        while next < self.types.instances.len() {
+            let id = InstanceId(next);
+            next += 1;
+
+            // Expansion stops at the first mixin that would instantiate itself without end.
+            let instance = self.types.instances.get(id);
+            if self.types.instances.depth(id) >= EXPANSION_LIMIT {
+                let root = self.types.instances.get(self.types.instances.root(id));
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        format!(
+                            "`{}` instantiates mixins without end",
+                            root.ty().display(self.program)
+                        ),
+                        instance.loc.clone(),
+                    )
+                    .with_label("instantiated here with ever larger arguments")
+                    .with_secondary(root.loc.clone(), "in this instantiation")
+                    .with_note(format!(
+                        "instantiations can only be nested {} deep",
+                        EXPANSION_LIMIT
+                    )),
+                );
+                break;
+            }
+
+            let (item, args) = (instance.item, instance.args.clone());
+            self.file = item.file;
+            match &resolve::item(self.program, item).kind {
+                ItemKind::Struct(struct_) | ItemKind::Union(struct_) => {
+                    for field in &struct_.fields {
+                        self.expand_type(&field.ty, item, &args, id);
+                    }
+                    self.select_impls(id);
+                    self.instance_bodies(id);
+                }
+                ItemKind::Enum(_) => {
+                    self.select_impls(id);
+                    self.instance_bodies(id);
+                }
+                ItemKind::Trait(trait_) => {
+                    for fun in trait_.items.iter().filter_map(|item| match &item.kind {
+                        ItemKind::Fun(fun) => Some(fun),
+                        _ => None,
+                    }) {
+                        let types = fun.params.iter().map(|param| &param.ty);
+                        for ty in types.chain(&fun.ret) {
+                            self.expand_type(ty, item, &args, id);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Checks the functions of the `impl!` blocks chosen for the instance `id` with the
+    /// instance's arguments, which expands the instantiations in them.
+    ///
+    /// The functions have been checked generically already, so only errors that checking them
+    /// generically didn't find are reported, pointing back through the instantiations that led
+    /// to them.
+    fn instance_bodies(&mut self, id: InstanceId) {
+        let mut impls: Vec<ItemId> = self
+            .types
+            .trait_impls
+            .iter()
+            .filter(|((instance, _), _)| *instance == id)
+            .map(|(_, impl_)| *impl_)
+            .chain(
+                self.types
+                    .inherent_impls
+                    .get(&id)
+                    .into_iter()
+                    .flatten()
+                    .copied(),
+            )
+            .collect();
+        impls.sort_by_key(|impl_| (impl_.file, impl_.index));
+
+        let instance = self.types.instances.get(id).clone();
+        let ty = instance.ty();
+        let generic = std::mem::take(&mut *self.diagnostics);
+        for impl_ in impls {
+            let item = resolve::item(self.program, impl_);
+            let ItemKind::Impl(impl_ast) = &item.kind else {
+                continue;
+            };
+            if resolve::generic_params(item).is_empty() {
+                continue;
+            }
+            let Some(args) = self.impl_args(impl_, &ty) else {
+                continue;
+            };
+
+            self.file = impl_.file;
+            self.instance = Some((impl_, args, id));
+            for fun in impl_ast.items.iter().filter_map(item_fun) {
+                self.fun(fun, Some(ty.clone()));
+            }
+            self.instance = None;
+        }
+
+        let found = std::mem::replace(&mut *self.diagnostics, generic);
+        for diagnostic in found.iter() {
+            if self
+                .diagnostics
+                .iter()
+                .any(|reported| same_error(reported, diagnostic))
+            {
+                continue;
+            }
+            // Errors in instantiations have their backtraces already.
+            let diagnostic = match diagnostic.secondary.iter().any(|l| l.loc == instance.loc) {
+                true => diagnostic.clone(),
+                false => self
+                    .types
+                    .instances
+                    .backtrace(self.program, id, diagnostic.clone()),
+            };
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    /// Instantiates the mixins in `ty`, which is written in the mixin `item`, for the instance
+    /// `instance` of it with the arguments `args`.
+    fn expand_type(&mut self, ty: &Type, item: ItemId, args: &[Ty], instance: InstanceId) {
+        match &ty.kind {
+            TypeKind::Path(_) => {}
+            TypeKind::Mixin(path, written) => {
+                let mut lowered = Vec::new();
+                for arg in written {
+                    self.expand_type(&arg.ty, item, args, instance);
+                    lowered.push(self.lower(&arg.ty).subst(item, args));
+                }
+                self.instantiate(path, written, lowered, &ty.loc, Some(instance));
+            }
+            TypeKind::Ptr(_, pointee) | TypeKind::Ref(_, pointee) => {
+                self.expand_type(pointee, item, args, instance)
+            }
+        }
+    }
+
+    /// Chooses the `impl!` blocks that apply to the instance `id`, out of those written for its
+    /// mixin.  Exactly one impl of each trait has to apply; which one depends on which bounds the
+    /// arguments satisfy.
+    fn select_impls(&mut self, id: InstanceId) {
+        let instance = self.types.instances.get(id).clone();
+        let ty = instance.ty();
+
+        // The candidates of each trait, in the order their first impl is written, along with the
+        // argument and bound that keep each from applying.
+        type Candidates<'a> = Vec<(ItemId, Option<(Ty, &'a Bound)>)>;
+        let mut candidates: Vec<(Option<Res>, Candidates<'a>)> = Vec::new();
+        for impl_ in self.impls() {
+            if resolve::generic_params(resolve::item(self.program, impl_)).is_empty() {
+                continue;
+            }
+            let Some(trait_) = self.impl_trait(impl_) else {
+                continue;
+            };
+            let Some(args) = self.impl_args(impl_, &ty) else {
+                continue;
+            };
+            let unmet = self.unmet_bound(impl_, &args);
+            match candidates.iter_mut().find(|(found, _)| *found == trait_) {
+                Some((_, impls)) => impls.push((impl_, unmet)),
+                None => candidates.push((trait_, vec![(impl_, unmet)])),
+            }
+        }
+
+        for (trait_, impls) in candidates {
+            let applying: Vec<_> = impls
+                .iter()
+                .filter(|(_, unmet)| unmet.is_none())
+                .map(|(impl_, _)| *impl_)
+                .collect();
+            // Any number of inherent impls can apply, each adding its own functions.
+            let Some(trait_) = trait_ else {
+                self.types.inherent_impls.insert(id, applying);
+                continue;
+            };
+            let trait_name = match &resolve::item(self.program, impls[0].0).kind {
+                ItemKind::Impl(Impl {
+                    trait_: Some(path), ..
+                }) => path.last().name.clone(),
+                _ => continue,
+            };
+            let ty_name = self.display(&ty);
+
+            let diagnostic = match applying.len() {
+                0 => {
+                    let mut diagnostic = Diagnostic::error(
+                        format!(
+                            "no `impl!` block of `{}` applies to `{}`",
+                            trait_name, ty_name
+                        ),
+                        instance.loc.clone(),
+                    )
+                    .with_label("instantiated here");
+                    for (impl_, unmet) in &impls {
+                        let (arg, bound) = unmet.as_ref().expect("no candidate applies");
+                        let arg = self.display(arg);
+                        let trait_ = &bound.path.last().name;
+                        let reason = match bound.negative {
+                            false => format!("`{}` doesn't implement `{}`", arg, trait_),
+                            true => format!("`{}` implements `{}`", arg, trait_),
+                        };
+                        diagnostic = diagnostic.with_secondary(
+                            self.impl_header(*impl_),
+                            format!("doesn't apply, because {}", reason),
+                        );
+                    }
+                    diagnostic
+                }
+                1 => {
+                    self.types.trait_impls.insert((id, trait_), applying[0]);
+                    continue;
+                }
+                _ => {
+                    let mut diagnostic = Diagnostic::error(
+                        format!(
+                            "conflicting `impl!` blocks of `{}` for `{}`",
+                            trait_name, ty_name
+                        ),
+                        instance.loc.clone(),
+                    )
+                    .with_label(format!("{} impls apply here", applying.len()))
+                    .with_help("add a negative bound, such as `T: !Drop`, to keep them apart");
+                    for impl_ in applying {
+                        diagnostic =
+                            diagnostic.with_secondary(self.impl_header(impl_), "this impl applies");
+                    }
+                    diagnostic
+                }
+            };
+            let diagnostic = match instance.parent {
+                Some(parent) => self
+                    .types
+                    .instances
+                    .backtrace(self.program, parent, diagnostic),
+                None => diagnostic,
+            };
+            self.diagnostics.push(diagnostic);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::IntTy;
+    use crate::diagnostic::{Diagnostics, Severity};
+    use crate::lint::LintContext;
+    use crate::source::SourceMap;
+    use crate::target::Target;
+    use crate::typeck::{self, Types};
+    use crate::{loader, parser};
+
+    /// The result of checking a source.
+    struct Checked {
+        /// The source.
+        src: &'static str,
+
+        /// The program.
+        program: Program,
+
+        /// What type checking found out.
+        types: Types,
+
+        /// Every diagnostic reported.
+        diagnostics: Vec<Diagnostic>,
+    }
+
+    impl Checked {
+        /// Returns the source at `loc`.
+        fn text(&self, loc: &Loc) -> &'static str {
+            &self.src[loc.span.clone()]
+        }
+
+        /// Returns the line of `loc`, counting from 1.
+        fn line(&self, loc: &Loc) -> usize {
+            self.src[..loc.span.start].matches('\n').count() + 1
+        }
+
+        /// Returns the id of the item at `index`.
+        fn item(&self, index: usize) -> ItemId {
+            ItemId {
+                file: self.program.root().ast.file,
+                index,
+            }
+        }
+
+        /// Returns the message of every diagnostic, with its line and the source at its primary
+        /// location.
+        fn messages(&self) -> Vec<(&str, usize, &'static str)> {
+            self.diagnostics
+                .iter()
+                .map(|diagnostic| {
+                    let loc = &diagnostic.primary.loc;
+                    (&diagnostic.message[..], self.line(loc), self.text(loc))
+                })
+                .collect()
+        }
+
+        /// Returns the line of every secondary location of the diagnostic at `index`, with its
+        /// label.
+        fn secondary(&self, index: usize) -> Vec<(usize, &str)> {
+            self.diagnostics[index]
+                .secondary
+                .iter()
+                .map(|label| (self.line(&label.loc), &label.message[..]))
+                .collect()
+        }
+    }
+
+    /// Parses, resolves and checks `src`, which starts with a newline so that its first line
+    /// is line 2.
+    fn check_src(src: &'static str) -> Checked {
+        let mut sources = SourceMap::new();
+        let file = sources.add("test.hl", src.to_string());
+        let mut diagnostics = Diagnostics::new();
+        let module = parser::parse(&sources, file, &mut diagnostics).expect("the source parses");
+        let program = loader::load(module, &[], &mut sources, &mut diagnostics);
+        let mut lints = LintContext::new(Vec::new(), false);
+        for module in &program.modules {
+            lints.add_module(&module.ast, &sources, &mut diagnostics);
+        }
+        let resolutions = resolve::resolve(&program, &lints, &mut diagnostics);
+        let types = typeck::check(
+            &program,
+            &resolutions,
+            Target::host(),
+            &sources,
+            &lints,
+            &mut diagnostics,
+        );
+        Checked {
+            src,
+            program,
+            types,
+            diagnostics: diagnostics.iter().cloned().collect(),
+        }
+    }
+
+    #[test]
+    fn instances_are_shared_by_arguments() {
+        let checked = check_src(
+            "
+struct Thing { a: int32 }
+mixin struct Holder!<T: Default> { value: T }
+struct First = Holder!<int32>;
+struct Second = Holder!<int32>;
+struct Third = Holder!<uint8>;
+struct Bad = Holder!<Thing>;
+struct Worse = Holder!<Thing>;
+",
+        );
+        // The bounds are only checked the first time an argument list is used.
+        assert_eq!(
+            checked.messages(),
+            [("`Thing` doesn't implement `Default`", 7, "Thing")]
+        );
+
+        let instances = &checked.types.instances;
+        assert_eq!(instances.len(), 3);
+        let holder = checked.item(1);
+        let int32 = instances
+            .find(holder, &[Ty::Int(IntTy::Int32)])
+            .expect("`Holder!<int32>` is instantiated");
+        assert_eq!(checked.line(&instances.get(int32).loc), 4);
+        assert!(instances.find(holder, &[Ty::Int(IntTy::Uint8)]).is_some());
+        assert!(instances.find(holder, &[Ty::Int(IntTy::Int64)]).is_none());
+    }
+
+    #[test]
+    fn bound_errors_point_at_the_instantiation() {
+        let checked = check_src(
+            "
+struct Thing { a: int32 }
+struct Other { b: int32 }
+mixin struct Holder!<T: Default> { value: T }
+mixin struct Outer!<T: Mem> { holder: Holder!<T> }
+struct Direct = Holder!<Thing>;
+struct Nested = Outer!<Other>;
+",
+        );
+        assert_eq!(
+            checked.messages(),
+            [
+                ("`Thing` doesn't implement `Default`", 6, "Thing"),
+                ("`Other` doesn't implement `Default`", 5, "T"),
+            ]
+        );
+        assert_eq!(checked.secondary(0), [(4, "required by this bound")]);
+        // Errors inside a mixin point back at where it was instantiated.
+        assert_eq!(
+            checked.secondary(1),
+            [
+                (4, "required by this bound"),
+                (7, "in this instantiation of `Outer!<Other>`"),
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_in_instances() {
+        let checked = check_src(
+            "
+struct Thing { a: int32 }
+struct Other { b: int32 }
+mixin struct Holder!<T: Default> { value: T }
+mixin struct Cell!<T: Mem> { value: T }
+impl!<T: Mem> Cell!<T> {
+    fun take(_holder: Holder!<T>) {}
+    fun count(&self) -> int32 {}
+}
+struct A = Cell!<Thing>;
+struct B = Cell!<Other>;
+",
+        );
+        // The warning that checking the functions generically found isn't repeated for every
+        // instance, but the errors in different instances are all reported, even in the same
+        // place.
+        assert_eq!(
+            checked.messages(),
+            [
+                ("function never returns a value", 8, "count"),
+                ("`Thing` doesn't implement `Default`", 7, "T"),
+                ("`Other` doesn't implement `Default`", 7, "T"),
+            ]
+        );
+        assert_eq!(checked.diagnostics[0].severity, Severity::Warning);
+        assert_eq!(
+            checked.secondary(1),
+            [
+                (4, "required by this bound"),
+                (10, "in this instantiation of `Cell!<Thing>`"),
+            ]
+        );
+        assert_eq!(
+            checked.secondary(2),
+            [
+                (4, "required by this bound"),
+                (11, "in this instantiation of `Cell!<Other>`"),
+            ]
+        );
+    }
+
+    #[test]
+    fn impl_blocks_that_dont_apply() {
+        let checked = check_src(
+            "
+struct Thing { a: int32 }
+trait Describe { fun describe(&self) -> uint8; }
+mixin struct Cell!<T: Mem> { value: T }
+impl!<T: Default + Mem> Describe for Cell!<T> { fun describe(&self) -> uint8 { return 1; } }
+impl!<T: Drop + Mem> Describe for Cell!<T> { fun describe(&self) -> uint8 { return 2; } }
+struct Numbers = Cell!<int32>;
+struct Things = Cell!<Thing>;
+",
+        );
+        assert_eq!(
+            checked.messages(),
+            [(
+                "no `impl!` block of `Describe` applies to `Cell!<Thing>`",
+                8,
+                "Cell!<Thing>"
+            )]
+        );
+        assert_eq!(
+            checked.secondary(0),
+            [
+                (
+                    5,
+                    "doesn't apply, because `Thing` doesn't implement `Default`"
+                ),
+                (6, "doesn't apply, because `Thing` doesn't implement `Drop`"),
+            ]
+        );
+
+        let numbers = checked
+            .types
+            .instances
+            .find(checked.item(2), &[Ty::Int(IntTy::Int32)])
+            .expect("`Cell!<int32>` is instantiated");
+        let chosen: Vec<_> = checked
+            .types
+            .trait_impls
+            .iter()
+            .filter(|((instance, _), _)| *instance == numbers)
+            .map(|(_, impl_)| *impl_)
+            .collect();
+        assert_eq!(chosen, [checked.item(3)]);
+    }
+
+    #[test]
+    fn overlapping_impl_blocks() {
+        let checked = check_src(
+            "
+trait Describe { fun describe(&self) -> uint8; }
+mixin struct Cell!<T: Mem> { value: T }
+impl!<T: Default + Mem> Describe for Cell!<T> { fun describe(&self) -> uint8 { return 1; } }
+impl!<T: Mem> Describe for Cell!<T> { fun describe(&self) -> uint8 { return 2; } }
+impl!<T: Drop + Mem> Describe for Cell!<T> { fun describe(&self) -> uint8 { return 3; } }
+struct Numbers = Cell!<int32>;
+",
+        );
+        assert_eq!(
+            checked.messages(),
+            [(
+                "conflicting `impl!` blocks of `Describe` for `Cell!<int32>`",
+                7,
+                "Cell!<int32>"
+            )]
+        );
+        assert_eq!(
+            checked.secondary(0),
+            [(4, "this impl applies"), (5, "this impl applies")]
+        );
+    }
+}
@@ -0,0 +1,478 @@
+//! Parsing of source files, and the translation of parse errors into diagnostics.
+
+use crate::ast::{Block, Expr, ExprKind, Module, Stmt, StmtKind};
+use crate::diagnostic::{Diagnostic, Diagnostics};
+use crate::grammar::ModuleParser;
+use crate::lexer::{LexError, Lexer, Token};
+use crate::source::SourceMap;
+use crate::visit::{self, Visitor};
+use crate::Loc;
+
+/// An error produced by the generated parser.
+pub type ParseError<'input> = lalrpop_util::ParseError<usize, Token<'input>, LexError>;
+
+/// A syntax error that the generated parser recovered from.
+pub type ErrorRecovery<'input> = lalrpop_util::ErrorRecovery<usize, Token<'input>, LexError>;
+
+/// The terminals that can start an expression.
+const EXPR_START: &[&str] = &[
+    "iden", "int", "float", "str", "char", "true", "false", "self", "Self", "(", "-", "!", "~",
+    "*", "&", "&&", "#",
+];
+
+/// The binary operators, along with the other terminals that can continue an expression.
+const OPERATORS: &[&str] = &[
+    "||", "&&", "==", "!=", "<", "<=", ">", ">=", "|", "^", "&", "<<", ">>", "+", "-", "*", "/",
+    "%", "as", ".", "(", "[",
+];
+
+/// The assignment operators.
+const ASSIGN_OPS: &[&str] = &[
+    "=", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "<<=", ">>=",
+];
+
+/// Parses the file with the id `file` from `sources`, reporting syntax errors to `diagnostics`.
+///
+/// The parser recovers from most syntax errors, leaving error nodes in the module, so every
+/// error in the file is reported at once.  `None` is only returned if it couldn't recover.
+pub fn parse(sources: &SourceMap, file: u32, diagnostics: &mut Diagnostics) -> Option<Module> {
+    let src = &sources.get(file).src;
+    let mut errors = Vec::new();
+    let result = ModuleParser::new().parse(file, &mut errors, Lexer::new(file, src));
+
+    for error in errors {
+        diagnostics.push(diagnostic(sources, file, error.error));
+    }
+
+    match result {
+        Ok(module) => Some(module),
+        Err(error) => {
+            diagnostics.push(diagnostic(sources, file, error));
+            None
+        }
+    }
+}
+
+/// Records the error of a failed literal in `errors`, so that parsing can carry on.
+pub(crate) fn record<T>(errors: &mut Vec<ErrorRecovery>, result: Result<T, LexError>) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(error) => {
+            errors.push(ErrorRecovery {
+                error: ParseError::User { error },
+                dropped_tokens: Vec::new(),
+            });
+            None
+        }
+    }
+}
+
+/// Records `token` in `errors` as a token that doesn't belong where it is, after the grammar
+/// accepted it anyway so that parsing can carry on.
+pub(crate) fn misplaced<'input>(
+    errors: &mut Vec<ErrorRecovery<'input>>,
+    (start, token, end): (usize, Token<'input>, usize),
+) {
+    errors.push(ErrorRecovery {
+        error: ParseError::UnrecognizedToken {
+            token: (start, token, end),
+            expected: Vec::new(),
+        },
+        dropped_tokens: Vec::new(),
+    });
+}
+
+/// Translates a parse error in `file` into a diagnostic.
+pub fn diagnostic(sources: &SourceMap, file: u32, error: ParseError) -> Diagnostic {
+    match error {
+        ParseError::User { error } => error.into(),
+        ParseError::InvalidToken { location } => {
+            Diagnostic::error("invalid token", Loc::new(file, location..location))
+        }
+        ParseError::UnrecognizedEOF { location, expected } => {
+            let expected = Expected::new(&expected);
+            Diagnostic::error(
+                format!("expected {}, found end of file", expected.describe()),
+                Loc::new(file, location..location),
+            )
+            .with_label(format!("expected {}", expected.describe()))
+        }
+        ParseError::UnrecognizedToken {
+            token: (_, Token::Invalid(error), _),
+            ..
+        } => error.into(),
+        ParseError::UnrecognizedToken {
+            token: (start, Token::DocComment(_), end),
+            ..
+        } => Diagnostic::error(
+            "doc comments must come before an item, field or variant",
+            Loc::new(file, start..end),
+        )
+        .with_label("this doc comment doesn't document anything")
+        .with_suggestion(
+            "use `//` for a regular comment",
+            Loc::new(file, start..start + 3),
+            "//",
+        ),
+        ParseError::UnrecognizedToken {
+            token: (start, Token::InnerDocComment(_), end),
+            ..
+        } => Diagnostic::error(
+            "`//!` doc comments must come at the start of the file",
+            Loc::new(file, start..end),
+        )
+        .with_label("this doc comment comes after an item")
+        .with_suggestion(
+            "use `///` to document the item after it, or `//` for a regular comment",
+            Loc::new(file, start..start + 3),
+            "///",
+        ),
+        ParseError::UnrecognizedToken {
+            token: (start, token, end),
+            expected,
+        } => {
+            let src = &sources.get(file).src;
+            let mut expected = Expected::new(&expected);
+            if expected.after_expr {
+                expected.narrow(open_delimiter(file, src, start));
+            }
+
+            let found = Loc::new(file, start..end);
+            let comparison = matches!(
+                token,
+                Token::EqEq | Token::Ne | Token::Lt | Token::Le | Token::Gt | Token::Ge
+            );
+
+            // After a complete expression, the missing `;` is the likeliest culprit, so point
+            // right after the expression rather than at the next token.
+            if (expected.after_expr || expected.assign)
+                && expected.names.contains(&";")
+                && !comparison
+            {
+                let after = src[..start].trim_end().len();
+                return Diagnostic::error(
+                    "expected `;` after expression",
+                    Loc::new(file, after..after),
+                )
+                .with_label("expected `;`")
+                .with_secondary(found, format!("found {}", token.describe()))
+                .with_suggestion(
+                    "add a semicolon",
+                    Loc::new(file, after..after),
+                    ";",
+                );
+            }
+
+            let diagnostic = Diagnostic::error(
+                format!(
+                    "expected {}, found {}",
+                    expected.describe(),
+                    token.describe()
+                ),
+                found,
+            )
+            .with_label(format!("expected {}", expected.describe()));
+
+            if comparison {
+                diagnostic.with_help("comparisons can't be chained; combine them with `&&`")
+            } else {
+                diagnostic
+            }
+        }
+        ParseError::ExtraToken {
+            token: (start, token, end),
+        } => Diagnostic::error(
+            format!("unexpected {}", token.describe()),
+            Loc::new(file, start..end),
+        )
+        .with_label("unexpected token"),
+    }
+}
+
+/// The set of terminals the parser expected, collapsed into readable groups.
+#[derive(Debug)]
+struct Expected<'a> {
+    /// The terminals that aren't part of a group.
+    names: Vec<&'a str>,
+
+    /// Whether an expression was expected.
+    expr: bool,
+
+    /// Whether the parser was after a complete expression, so an operator was expected.
+    after_expr: bool,
+
+    /// Whether an assignment operator was expected.
+    assign: bool,
+}
+
+impl<'a> Expected<'a> {
+    /// Collapses the terminals named in `expected`, which LALRPOP quotes.
+    fn new(expected: &'a [String]) -> Self {
+        let mut names: Vec<&str> = expected.iter().map(|name| name.trim_matches('"')).collect();
+        let has_all = |names: &[&str], group: &[&str]| group.iter().all(|t| names.contains(t));
+
+        let expr = has_all(&names, EXPR_START);
+        let after_expr = has_all(&names, OPERATORS);
+        let assign = has_all(&names, ASSIGN_OPS);
+
+        let mut grouped: Vec<&str> = Vec::new();
+        if expr {
+            grouped.extend(EXPR_START);
+        }
+        if after_expr {
+            grouped.extend(OPERATORS);
+        }
+        if assign {
+            grouped.extend(ASSIGN_OPS);
+        }
+        if names.contains(&"iden") {
+            grouped.extend(["self", "Self"]);
+        }
+        // A missing doc comment is never the problem.
+        grouped.extend(["doc", "inner doc"]);
+        names.retain(|name| !grouped.contains(name));
+
+        Self {
+            names,
+            expr,
+            after_expr,
+            assign,
+        }
+    }
+
+    /// Removes the terminals that can't follow an expression inside the `delimiter`.
+    ///
+    /// The generated parser shares states between all expressions, so after one it expects
+    /// anything that may follow any expression.  Inside parentheses or brackets, only a few of
+    /// those make sense.
+    fn narrow(&mut self, delimiter: Option<Token>) {
+        let closers: &[&str] = match delimiter {
+            Some(Token::LParen) => &[",", ")"],
+            Some(Token::LBracket) => &["]"],
+            _ => return,
+        };
+
+        self.names.retain(|name| closers.contains(name));
+        self.assign = false;
+    }
+
+    /// Describes the set, such as "`;` or an operator".
+    fn describe(&self) -> String {
+        let mut items: Vec<String> = self.names.iter().map(|name| terminal_name(name)).collect();
+        if self.expr {
+            items.push("an expression".to_string());
+        }
+        if self.assign {
+            items.push("`=`".to_string());
+        }
+        if self.after_expr {
+            items.push("an operator".to_string());
+        }
+
+        const MAX: usize = 6;
+        if items.len() > MAX {
+            let others = items.len() - (MAX - 1);
+            items.truncate(MAX - 1);
+            items.push(format!("{} other tokens", others));
+        }
+
+        match items.as_slice() {
+            [] => "nothing".to_string(),
+            [item] => item.clone(),
+            [first, second] => format!("{} or {}", first, second),
+            [rest @ .., last] => format!("one of {} or {}", rest.join(", "), last),
+        }
+    }
+}
+
+/// Returns the innermost delimiter that is still open at `offset` in `src`.
+fn open_delimiter(file: u32, src: &str, offset: usize) -> Option<Token<'_>> {
+    let mut open = Vec::new();
+    let mut lexer = Lexer::new(file, src);
+    while let Some(Ok((token, loc))) = lexer.next_token() {
+        if loc.span.start >= offset {
+            break;
+        }
+
+        match token {
+            Token::LParen | Token::LBracket | Token::LBrace | Token::ColonColonBrace => {
+                open.push(token)
+            }
+            Token::RParen | Token::RBracket | Token::RBrace => {
+                open.pop();
+            }
+            // A `;` can only appear directly inside braces, so it ends any unclosed parentheses.
+            Token::Semi => {
+                while open.last().is_some_and(|token| *token != Token::LBrace) {
+                    open.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    open.pop()
+}
+
+/// Returns a readable name for a terminal of the grammar.
+fn terminal_name(terminal: &str) -> String {
+    match terminal {
+        "iden" => "an identifier".to_string(),
+        "int" => "an integer literal".to_string(),
+        "float" => "a float literal".to_string(),
+        "str" => "a string literal".to_string(),
+        "char" => "a character literal".to_string(),
+        _ => format!("`{}`", terminal),
+    }
+}
+
+/// Returns whether `block` has a statement or expression that failed to parse.  Passes after
+/// parsing use this to skip lints that could be wrong about a function whose body is incomplete,
+/// such as a variable being unused when its only use was in the broken statement.
+pub fn has_errors(block: &Block) -> bool {
+    /// Finds error nodes.
+    struct Finder(bool);
+
+    impl Visitor for Finder {
+        fn visit_stmt(&mut self, stmt: &Stmt) {
+            match &stmt.kind {
+                StmtKind::Error => self.0 = true,
+                _ => visit::walk_stmt(self, stmt),
+            }
+        }
+
+        fn visit_expr(&mut self, expr: &Expr) {
+            match &expr.kind {
+                ExprKind::Error => self.0 = true,
+                _ => visit::walk_expr(self, expr),
+            }
+        }
+    }
+
+    let mut finder = Finder(false);
+    finder.visit_block(block);
+    finder.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{BinOp, ItemKind, TypeKind, UnOp};
+
+    /// Parses `src`, which must have no syntax errors.
+    fn parse_src(src: &str) -> Module {
+        let mut sources = SourceMap::new();
+        let file = sources.add("test.hl", src.to_string());
+        let mut diagnostics = Diagnostics::new();
+        let module = parse(&sources, file, &mut diagnostics).expect("the source parses");
+        assert!(diagnostics.iter().next().is_none());
+        module
+    }
+
+    /// Returns the text that `loc` covers in `src`.
+    fn text<'a>(src: &'a str, loc: &Loc) -> &'a str {
+        &src[loc.span.clone()]
+    }
+
+    #[test]
+    fn doc_comments_are_outside_of_locations() {
+        let src = "\
+/// A function.
+fun f() {}
+
+/// A struct.
+#derive(Default)
+publ struct S {
+    /// A field.
+    publ a: int32,
+}
+
+/// An enum.
+enum E {
+    /// A variant.
+    A = 1,
+}
+";
+        let module = parse_src(src);
+        let [f, s, e] = &module.items[..] else {
+            panic!("expected three items");
+        };
+        assert_eq!(text(src, &f.loc), "fun f() {}");
+        assert!(text(src, &s.loc).starts_with("#derive(Default)\npubl struct S {"));
+        assert!(text(src, &e.loc).starts_with("enum E {"));
+        assert!(f.docs.len() == 1 && s.docs.len() == 1 && e.docs.len() == 1);
+
+        let ItemKind::Struct(struct_) = &s.kind else {
+            panic!("expected a struct");
+        };
+        assert_eq!(text(src, &struct_.fields[0].loc), "publ a: int32");
+        assert_eq!(struct_.fields[0].docs.len(), 1);
+        let ItemKind::Enum(enum_) = &e.kind else {
+            panic!("expected an enum");
+        };
+        assert_eq!(text(src, &enum_.variants[0].loc), "A = 1");
+        assert_eq!(enum_.variants[0].docs.len(), 1);
+    }
+
+    #[test]
+    fn double_ampersands_are_two_references() {
+        let src = "fun f(a: &&mut int32) { val b: &&int32 = &&mut c; val d = a && &&b; }";
+        let module = parse_src(src);
+        let ItemKind::Fun(fun) = &module.items[0].kind else {
+            panic!("expected a function");
+        };
+        let TypeKind::Ref(false, inner) = &fun.params[0].ty.kind else {
+            panic!("expected a reference");
+        };
+        assert!(matches!(inner.kind, TypeKind::Ref(true, _)));
+        assert_eq!(text(src, &inner.loc), "&mut int32");
+
+        let stmts = &fun.body.as_ref().unwrap().stmts;
+        let StmtKind::Val(b) = &stmts[0].kind else {
+            panic!("expected a variable");
+        };
+        let Some(TypeKind::Ref(false, inner)) = b.ty.as_ref().map(|ty| &ty.kind) else {
+            panic!("expected a reference");
+        };
+        assert!(matches!(inner.kind, TypeKind::Ref(false, _)));
+        let Some(ExprKind::Unary(UnOp::Ref, inner)) = b.value.as_ref().map(|value| &value.kind)
+        else {
+            panic!("expected a reference");
+        };
+        assert!(matches!(inner.kind, ExprKind::Unary(UnOp::RefMut, _)));
+        assert_eq!(text(src, &inner.loc), "&mut c");
+
+        let StmtKind::Val(d) = &stmts[1].kind else {
+            panic!("expected a variable");
+        };
+        let Some(ExprKind::Binary(BinOp::And, _, rhs)) = d.value.as_ref().map(|value| &value.kind)
+        else {
+            panic!("expected `&&`");
+        };
+        assert!(matches!(rhs.kind, ExprKind::Unary(UnOp::Ref, _)));
+    }
+
+    #[test]
+    fn expression_attributes_cover_casts() {
+        let src = "fun f() { val a = #allow(unsafe_code) x as uint8 as int32 + !#allow(x) y; }";
+        let module = parse_src(src);
+        let ItemKind::Fun(fun) = &module.items[0].kind else {
+            panic!("expected a function");
+        };
+        let StmtKind::Val(val) = &fun.body.as_ref().unwrap().stmts[0].kind else {
+            panic!("expected a variable");
+        };
+        let Some(ExprKind::Binary(BinOp::Add, lhs, rhs)) =
+            val.value.as_ref().map(|value| &value.kind)
+        else {
+            panic!("expected an addition");
+        };
+        assert_eq!(lhs.attrs.len(), 1);
+        assert_eq!(text(src, &lhs.loc), "x as uint8 as int32");
+        let ExprKind::Unary(UnOp::Not, operand) = &rhs.kind else {
+            panic!("expected a `!`");
+        };
+        assert_eq!(operand.attrs.len(), 1);
+        assert_eq!(text(src, &operand.loc), "y");
+    }
+}
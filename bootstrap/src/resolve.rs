@@ -0,0 +1,1319 @@
+//! Name resolution.
+//!
+//! The resolver binds every path in a program to what it names: a local variable, an item of
+//! some module, a generic parameter, `Self`, an enum variant or a builtin.  Only the start of a
+//! path is resolved here.  What comes after a type, as in `T::size_of`, depends on the type's
+//! traits and impls, so it is left to the type checker.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::*;
+use crate::diagnostic::{similar_name, Diagnostic, Diagnostics};
+use crate::lint::{self, LintContext};
+use crate::loader::{LoadedModule, Program};
+use crate::parser;
+use crate::visit::{self, Visitor};
+use crate::Loc;
+
+/// The id of a module-level item: its file and its index in the module's items.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ItemId {
+    /// The file id of the module.
+    pub file: u32,
+
+    /// The index of the item in the module.
+    pub index: usize,
+}
+
+/// The id of a local variable, which indexes [`Resolutions::locals`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LocalId(pub usize);
+
+/// The names that are in scope everywhere without being declared.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Builtin {
+    /// An integer type, such as `int32`.
+    Int(IntTy),
+
+    /// A floating point type, such as `float64`.
+    Float(FloatTy),
+
+    /// `bool`
+    Bool,
+
+    /// `char`
+    Char,
+
+    /// `str`, the type of string slices.
+    Str,
+
+    /// The `Mem` trait of types that can be stored in memory.
+    Mem,
+
+    /// The `Default` trait of types with a default value.
+    Default,
+
+    /// The `Drop` trait of types that clean up after themselves.
+    Drop,
+
+    /// The `panic` function, which aborts the program with a message.
+    Panic,
+}
+
+impl Builtin {
+    /// Every builtin.
+    pub fn all() -> impl Iterator<Item = Builtin> {
+        IntTy::ALL.into_iter().map(Builtin::Int).chain([
+            Builtin::Float(FloatTy::Float32),
+            Builtin::Float(FloatTy::Float64),
+            Builtin::Bool,
+            Builtin::Char,
+            Builtin::Str,
+            Builtin::Mem,
+            Builtin::Default,
+            Builtin::Drop,
+            Builtin::Panic,
+        ])
+    }
+
+    /// Returns the builtin called `name`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all().find(|builtin| builtin.name() == name)
+    }
+
+    /// Returns the name of the builtin.
+    pub fn name(self) -> &'static str {
+        match self {
+            Builtin::Int(ty) => ty.name(),
+            Builtin::Float(ty) => ty.name(),
+            Builtin::Bool => "bool",
+            Builtin::Char => "char",
+            Builtin::Str => "str",
+            Builtin::Mem => "Mem",
+            Builtin::Default => "Default",
+            Builtin::Drop => "Drop",
+            Builtin::Panic => "panic",
+        }
+    }
+
+    /// Returns whether the builtin is a type.
+    pub fn is_type(self) -> bool {
+        matches!(
+            self,
+            Builtin::Int(_) | Builtin::Float(_) | Builtin::Bool | Builtin::Char | Builtin::Str
+        )
+    }
+
+    /// Returns whether the builtin is a trait.
+    pub fn is_trait(self) -> bool {
+        matches!(self, Builtin::Mem | Builtin::Default | Builtin::Drop)
+    }
+}
+
+/// What a name refers to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Res {
+    /// A local variable or parameter.
+    Local(LocalId),
+
+    /// A module-level item.
+    Item(ItemId),
+
+    /// The generic parameter with the given index on an item.
+    Generic(ItemId, usize),
+
+    /// `Self` inside the given struct, union, enum, trait or impl.
+    SelfTy(ItemId),
+
+    /// The variant with the given index of an enum.
+    Variant(ItemId, usize),
+
+    /// A builtin.
+    Builtin(Builtin),
+}
+
+/// What a path refers to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PathRes {
+    /// What the resolved segments refer to.
+    pub res: Res,
+
+    /// The number of segments at the end of the path that are left to the type checker, such as
+    /// `size_of` in `T::size_of`.
+    pub rest: usize,
+}
+
+/// The kinds of local variables.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LocalKind {
+    /// A `val` binding.
+    Val,
+
+    /// A function parameter.
+    Param,
+
+    /// The `self` parameter of a method.
+    SelfParam,
+}
+
+/// A local variable.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Local {
+    /// The name of the variable, where it is declared.
+    pub name: Iden,
+
+    /// How the variable was declared.
+    pub kind: LocalKind,
+
+    /// Whether the variable was declared `mut`.
+    pub mutable: bool,
+}
+
+/// The result of name resolution.
+#[derive(Clone, Debug, Default)]
+pub struct Resolutions {
+    /// What each resolved path refers to, by the location of the path.
+    paths: HashMap<Loc, PathRes>,
+
+    /// The local variables of every function.
+    pub locals: Vec<Local>,
+
+    /// The local declared by each binding, by the location of its name.
+    defs: HashMap<Loc, LocalId>,
+}
+
+impl Resolutions {
+    /// Returns what `path` refers to, or `None` if it couldn't be resolved.
+    pub fn path(&self, path: &Path) -> Option<PathRes> {
+        self.paths.get(&path.loc).copied()
+    }
+
+    /// Returns the local variable with the id `id`.
+    pub fn local(&self, id: LocalId) -> &Local {
+        &self.locals[id.0]
+    }
+
+    /// Returns the local variable declared by the binding `name`.
+    pub fn def(&self, name: &Iden) -> Option<LocalId> {
+        self.defs.get(&name.loc).copied()
+    }
+}
+
+/// Returns the item with the id `id` in `program`.
+pub fn item(program: &Program, id: ItemId) -> &Item {
+    &program
+        .get(id.file)
+        .expect("item ids refer to loaded modules")
+        .ast
+        .items[id.index]
+}
+
+/// Resolves the paths of every module in `program`, reporting unknown and duplicate names to
+/// `diagnostics`, and unused and shadowed variables through `lints`.
+pub fn resolve(
+    program: &Program,
+    lints: &LintContext,
+    diagnostics: &mut Diagnostics,
+) -> Resolutions {
+    let mut resolver = Resolver {
+        program,
+        lints,
+        diagnostics,
+        out: Resolutions::default(),
+        module: program.root(),
+        items: HashMap::new(),
+        unresolved_imports: HashSet::new(),
+        ribs: Vec::new(),
+        uses: Vec::new(),
+        in_methods: None,
+        in_broken_fun: false,
+    };
+
+    for module in &program.modules {
+        resolver.enter_module(module);
+        for (index, item) in module.ast.items.iter().enumerate() {
+            let id = ItemId {
+                file: module.ast.file,
+                index,
+            };
+            resolver.module_item(id, item);
+        }
+    }
+
+    resolver.out
+}
+
+/// What a path is expected to refer to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Expect {
+    /// A value, such as a variable or a function.
+    Value,
+
+    /// A type.
+    Type,
+
+    /// A trait.
+    Trait,
+
+    /// A struct or union, as in a struct literal.
+    Struct,
+}
+
+impl Expect {
+    /// Returns the noun for what is expected, such as "a type".
+    fn noun(self) -> &'static str {
+        match self {
+            Expect::Value => "a value",
+            Expect::Type => "a type",
+            Expect::Trait => "a trait",
+            Expect::Struct => "a struct",
+        }
+    }
+}
+
+/// How a local variable has been used so far.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Uses {
+    /// Whether the variable has been read.
+    read: bool,
+
+    /// Whether the variable has been assigned to after its declaration.
+    assigned: bool,
+}
+
+/// The names declared by one scope.
+#[derive(Debug)]
+struct Rib {
+    /// Whether the scope is a function or a block inside one, rather than an item.
+    local: bool,
+
+    /// The names, in the order they were declared.
+    names: Vec<(Iden, Res)>,
+}
+
+/// The state of [`resolve`].
+struct Resolver<'a> {
+    /// The program being resolved.
+    program: &'a Program,
+
+    /// The lint levels.
+    lints: &'a LintContext,
+
+    /// Where errors are reported.
+    diagnostics: &'a mut Diagnostics,
+
+    /// The resolutions so far.
+    out: Resolutions,
+
+    /// The module being resolved.
+    module: &'a LoadedModule,
+
+    /// The items and imports of the module being resolved, along with where they are declared.
+    items: HashMap<String, (Res, Loc)>,
+
+    /// The names of imports that couldn't be loaded, which have already been reported.
+    unresolved_imports: HashSet<String>,
+
+    /// The scopes inside the module, innermost last.
+    ribs: Vec<Rib>,
+
+    /// How each local variable has been used, indexed like [`Resolutions::locals`].
+    uses: Vec<Uses>,
+
+    /// Whether functions are declared in a trait or impl, which is `"trait"` or `"impl"` there.
+    in_methods: Option<&'static str>,
+
+    /// Whether the body of the function being resolved has syntax errors.  Uses of its variables
+    /// may have been lost with them, so they aren't linted.
+    in_broken_fun: bool,
+}
+
+impl<'a> Resolver<'a> {
+    /// Collects the items and imports of `module`, reporting any declared twice.
+    fn enter_module(&mut self, module: &'a LoadedModule) {
+        self.module = module;
+        self.items.clear();
+        self.unresolved_imports.clear();
+
+        let file = module.ast.file;
+        for (index, item) in module.ast.items.iter().enumerate() {
+            if let Some(name) = item.name() {
+                self.declare_item(name, Res::Item(ItemId { file, index }));
+            }
+        }
+
+        for import in &module.imports {
+            let id = ItemId {
+                file: import.module,
+                index: import.item,
+            };
+            self.declare_item(import.name.binding(), Res::Item(id));
+        }
+
+        for item in &module.ast.items {
+            if let ItemKind::Import(import) = &item.kind {
+                for name in &import.names {
+                    let binding = name.binding();
+                    if !self.items.contains_key(&binding.name) {
+                        self.unresolved_imports.insert(binding.name.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Declares the item or import `name` in the module being resolved.
+    fn declare_item(&mut self, name: &Iden, res: Res) {
+        if let Some((_, previous)) = self.items.get(&name.name) {
+            let previous = previous.clone();
+            self.duplicate("the name", name, &previous);
+            return;
+        }
+        self.items
+            .insert(name.name.clone(), (res, name.loc.clone()));
+    }
+
+    /// Reports `name` as declared twice, after being declared at `previous`.  `what` is what
+    /// it names, such as "field".
+    fn duplicate(&mut self, what: &str, name: &Iden, previous: &Loc) {
+        self.diagnostics.push(
+            Diagnostic::error(
+                format!("{} `{}` is defined more than once", what, name.name),
+                name.loc.clone(),
+            )
+            .with_label(format!("`{}` is redefined here", name.name))
+            .with_secondary(
+                previous.clone(),
+                format!("`{}` is first defined here", name.name),
+            ),
+        );
+    }
+
+    /// Runs `f` inside a new scope.  `local` is set for scopes inside functions.
+    fn with_rib(&mut self, local: bool, f: impl FnOnce(&mut Self)) {
+        self.ribs.push(Rib {
+            local,
+            names: Vec::new(),
+        });
+        f(self);
+        self.ribs.pop();
+    }
+
+    /// Runs `f` inside the scope of the item `id`, which has the generic parameters `params`
+    /// and, if `self_ty` is set, `Self`.
+    fn with_item_rib(
+        &mut self,
+        id: ItemId,
+        params: &[GenericParam],
+        self_ty: bool,
+        f: impl FnOnce(&mut Self),
+    ) {
+        self.with_rib(false, |this| {
+            for (index, param) in params.iter().enumerate() {
+                if let Some(previous) = this.declared_in_rib(&param.name.name) {
+                    this.duplicate("the generic parameter", &param.name, &previous);
+                    continue;
+                }
+                this.declare(param.name.clone(), Res::Generic(id, index));
+            }
+            for bound in params.iter().flat_map(|param| &param.bounds) {
+                this.resolve(&bound.path, Expect::Trait);
+            }
+
+            if self_ty {
+                let loc = this.item(id).loc.clone();
+                this.declare(Iden::new("Self", loc), Res::SelfTy(id));
+            }
+            f(this);
+        });
+    }
+
+    /// Adds `name` to the innermost scope.
+    fn declare(&mut self, name: Iden, res: Res) {
+        self.ribs
+            .last_mut()
+            .expect("declarations are inside a scope")
+            .names
+            .push((name, res));
+    }
+
+    /// Returns where `name` is declared in the innermost scope, if it is.
+    fn declared_in_rib(&self, name: &str) -> Option<Loc> {
+        self.ribs
+            .last()?
+            .names
+            .iter()
+            .find(|(iden, _)| iden.name == name)
+            .map(|(iden, _)| iden.loc.clone())
+    }
+
+    /// Declares a local variable in the innermost scope and returns its id.
+    fn declare_local(&mut self, name: &Iden, kind: LocalKind, mutable: bool) -> LocalId {
+        let id = LocalId(self.out.locals.len());
+        self.out.locals.push(Local {
+            name: name.clone(),
+            kind,
+            mutable,
+        });
+        self.uses.push(Uses::default());
+        self.out.defs.insert(name.loc.clone(), id);
+        self.declare(name.clone(), Res::Local(id));
+        id
+    }
+
+    /// Returns the item with the id `id`.
+    fn item(&self, id: ItemId) -> &'a Item {
+        item(self.program, id)
+    }
+
+    /// Returns what `name` refers to at this point.
+    fn lookup(&self, name: &str) -> Option<Res> {
+        let scoped = self
+            .ribs
+            .iter()
+            .rev()
+            .flat_map(|rib| rib.names.iter().rev())
+            .find(|(iden, _)| iden.name == name)
+            .map(|(_, res)| *res);
+
+        scoped
+            .or_else(|| self.items.get(name).map(|(res, _)| *res))
+            .or_else(|| Builtin::from_name(name).map(Res::Builtin))
+    }
+
+    /// Resolves `path`, which is expected to refer to `expect`.  Returns `None` if it couldn't
+    /// be resolved, which has been reported.
+    fn resolve(&mut self, path: &Path, expect: Expect) -> Option<PathRes> {
+        let first = &path.segments[0];
+        let Some(mut res) = self.lookup(&first.name) else {
+            if !self.unresolved_imports.contains(&first.name) {
+                let diagnostic = self.unknown(first, expect);
+                self.diagnostics.push(diagnostic);
+            }
+            return None;
+        };
+
+        // `Enum::Variant` is resolved here, but anything else after `::` depends on types.
+        let mut rest = path.segments.len() - 1;
+        if let (Some(enum_id), Some(name)) = (self.enum_of(res), path.segments.get(1)) {
+            if let ItemKind::Enum(enum_) = &self.item(enum_id).kind {
+                if let Some(index) = enum_
+                    .variants
+                    .iter()
+                    .position(|variant| variant.name.name == name.name)
+                {
+                    res = Res::Variant(enum_id, index);
+                    rest -= 1;
+                }
+            }
+        }
+
+        let fits = match (expect, rest) {
+            (Expect::Value, 0) => self.is_value(res),
+            (Expect::Value, _) => self.is_type(res) || self.is_trait(res),
+            (Expect::Type, 0) => self.is_type(res),
+            (Expect::Trait, 0) => self.is_trait(res),
+            (Expect::Struct, 0) => self.is_struct(res),
+            (_, _) => false,
+        };
+        if !fits {
+            let diagnostic = self.mismatch(path, res, rest, expect);
+            self.diagnostics.push(diagnostic);
+            return None;
+        }
+
+        let resolved = PathRes { res, rest };
+        self.out.paths.insert(path.loc.clone(), resolved);
+        Some(resolved)
+    }
+
+    /// Resolves the value path `path`, counting it as a read of the variable it names if `read`
+    /// is set, or as an assignment to it otherwise.
+    fn resolve_value(&mut self, path: &Path, read: bool) {
+        if let Some(PathRes {
+            res: Res::Local(id),
+            ..
+        }) = self.resolve(path, Expect::Value)
+        {
+            let uses = &mut self.uses[id.0];
+            match read {
+                true => uses.read = true,
+                false => uses.assigned = true,
+            }
+        }
+    }
+
+    /// Returns the enum that `res` names, if it names one.
+    fn enum_of(&self, res: Res) -> Option<ItemId> {
+        match res {
+            Res::Item(id) | Res::SelfTy(id) => {
+                matches!(self.item(id).kind, ItemKind::Enum(_)).then_some(id)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns whether `res` is a value.
+    fn is_value(&self, res: Res) -> bool {
+        match res {
+            Res::Local(_) | Res::Variant(..) => true,
+            Res::Item(id) => matches!(self.item(id).kind, ItemKind::Fun(_)),
+            Res::Builtin(builtin) => builtin == Builtin::Panic,
+            Res::Generic(..) | Res::SelfTy(_) => false,
+        }
+    }
+
+    /// Returns whether `res` is a type.
+    fn is_type(&self, res: Res) -> bool {
+        match res {
+            Res::Generic(..) | Res::SelfTy(_) => true,
+            Res::Item(id) => match &self.item(id).kind {
+                ItemKind::Struct(_) | ItemKind::Union(_) | ItemKind::Enum(_) => true,
+                ItemKind::Alias(alias) => alias.kind != AliasKind::Trait,
+                _ => false,
+            },
+            Res::Builtin(builtin) => builtin.is_type(),
+            Res::Local(_) | Res::Variant(..) => false,
+        }
+    }
+
+    /// Returns whether `res` is a trait.
+    fn is_trait(&self, res: Res) -> bool {
+        match res {
+            Res::Item(id) => match &self.item(id).kind {
+                ItemKind::Trait(_) => true,
+                ItemKind::Alias(alias) => alias.kind == AliasKind::Trait,
+                _ => false,
+            },
+            Res::Builtin(builtin) => builtin.is_trait(),
+            _ => false,
+        }
+    }
+
+    /// Returns whether `res` can name the type of a struct literal.
+    fn is_struct(&self, res: Res) -> bool {
+        match res {
+            Res::SelfTy(id) => !matches!(self.item(id).kind, ItemKind::Enum(_)),
+            Res::Item(id) => match &self.item(id).kind {
+                ItemKind::Struct(_) | ItemKind::Union(_) => true,
+                ItemKind::Alias(alias) => {
+                    matches!(alias.kind, AliasKind::Struct | AliasKind::Union)
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Describes what `res` refers to, such as "struct `Point`".
+    fn describe(&self, res: Res) -> String {
+        match res {
+            Res::Local(id) => {
+                let local = &self.out.locals[id.0];
+                match local.kind {
+                    LocalKind::Param => format!("parameter `{}`", local.name.name),
+                    _ => format!("local variable `{}`", local.name.name),
+                }
+            }
+            Res::Item(id) => {
+                let item = self.item(id);
+                let name = item.name().map_or("", |name| name.name.as_str());
+                let kind = match &item.kind {
+                    ItemKind::Fun(_) => "function",
+                    ItemKind::Struct(_) => "struct",
+                    ItemKind::Union(_) => "union",
+                    ItemKind::Enum(_) => "enum",
+                    ItemKind::Trait(_) => "trait",
+                    ItemKind::Alias(alias) => match alias.kind {
+                        AliasKind::Struct => "struct",
+                        AliasKind::Union => "union",
+                        AliasKind::Enum => "enum",
+                        AliasKind::Trait => "trait",
+                    },
+                    ItemKind::Import(_) | ItemKind::Impl(_) | ItemKind::Error => "item",
+                };
+                format!("{} `{}`", kind, name)
+            }
+            Res::Generic(id, index) => {
+                let name = generic_params(self.item(id))
+                    .get(index)
+                    .map_or("", |param| param.name.name.as_str());
+                format!("generic parameter `{}`", name)
+            }
+            Res::SelfTy(_) => "`Self`".to_string(),
+            Res::Variant(id, index) => {
+                let item = self.item(id);
+                let ItemKind::Enum(enum_) = &item.kind else {
+                    unreachable!("variants belong to enums");
+                };
+                format!(
+                    "variant `{}::{}`",
+                    enum_.name.name, enum_.variants[index].name.name
+                )
+            }
+            Res::Builtin(builtin) if builtin.is_type() => {
+                format!("builtin type `{}`", builtin.name())
+            }
+            Res::Builtin(builtin) if builtin.is_trait() => {
+                format!("builtin trait `{}`", builtin.name())
+            }
+            Res::Builtin(builtin) => format!("builtin function `{}`", builtin.name()),
+        }
+    }
+
+    /// Returns the error for `path`, whose start resolved to `res` with `rest` segments left,
+    /// when `expect` was expected.
+    fn mismatch(&self, path: &Path, res: Res, rest: usize, expect: Expect) -> Diagnostic {
+        let resolved = &path.segments[path.segments.len() - 1 - rest];
+        if rest > 0 && expect == Expect::Value {
+            return Diagnostic::error(
+                format!("expected a type before `::`, found {}", self.describe(res)),
+                resolved.loc.clone(),
+            )
+            .with_label("not a type")
+            .with_help("only the items of types and traits can be named with `::`");
+        }
+        if rest > 0 {
+            let after = &path.segments[path.segments.len() - rest];
+            return Diagnostic::error(
+                format!(
+                    "expected {}, found a path inside {}",
+                    expect.noun(),
+                    self.describe(res)
+                ),
+                after.loc.clone(),
+            )
+            .with_label(format!(
+                "`{}` can't be named through `{}`",
+                after.name, resolved.name
+            ))
+            .with_note(format!("{} must be named directly", expect.noun()));
+        }
+
+        let diagnostic = Diagnostic::error(
+            format!("expected {}, found {}", expect.noun(), self.describe(res)),
+            path.loc.clone(),
+        )
+        .with_label(format!("not {}", expect.noun()));
+        match (expect, res) {
+            (Expect::Value, _) if self.is_struct(res) => diagnostic.with_help(format!(
+                "to create a value of it, use a struct literal such as `{}::{{ .. }}`",
+                resolved.name
+            )),
+            _ => diagnostic,
+        }
+    }
+
+    /// Returns the error for the unknown name `name`, where `expect` was expected.
+    fn unknown(&self, name: &Iden, expect: Expect) -> Diagnostic {
+        match name.name.as_str() {
+            "self" => {
+                return Diagnostic::error("`self` is only available in methods", name.loc.clone())
+                    .with_label("not inside a method")
+                    .with_help(
+                        "methods take `self`, `&self` or `&mut self` as their first parameter",
+                    )
+            }
+            "Self" => {
+                return Diagnostic::error(
+                    "`Self` is only available in types, traits and impls",
+                    name.loc.clone(),
+                )
+                .with_label("not inside a type, trait or impl")
+            }
+            _ => {}
+        }
+
+        let what = match expect {
+            Expect::Value => "name",
+            Expect::Type => "type",
+            Expect::Trait => "trait",
+            Expect::Struct => "struct",
+        };
+        let diagnostic = Diagnostic::error(
+            format!("unknown {} `{}`", what, name.name),
+            name.loc.clone(),
+        )
+        .with_label("not found in this scope");
+
+        let scoped = self
+            .ribs
+            .iter()
+            .flat_map(|rib| &rib.names)
+            .map(|(iden, _)| iden.name.as_str());
+        let candidates: Vec<&str> = scoped
+            .chain(self.items.keys().map(String::as_str))
+            .chain(Builtin::all().map(|builtin| builtin.name()))
+            .collect();
+        if let Some(similar) = similar_name(&name.name, candidates) {
+            return diagnostic.with_suggestion(
+                format!("a name in scope is similar: `{}`", similar),
+                name.loc.clone(),
+                similar,
+            );
+        }
+
+        // The name may be public in another module of the program that isn't imported here.
+        let defined_in = self.program.modules.iter().find(|module| {
+            module.ast.file != self.module.ast.file
+                && module.ast.items.iter().any(|item| {
+                    matches!(item.vis, Visibility::Public(_))
+                        && item.name().is_some_and(|iden| iden.name == name.name)
+                })
+        });
+        let first = self.module.ast.items.first();
+        match (defined_in, first) {
+            (Some(module), Some(first)) => {
+                let offset = first
+                    .docs
+                    .first()
+                    .map_or(first.loc.span.start, |doc| doc.loc.span.start);
+                diagnostic.with_suggestion(
+                    format!(
+                        "`{}` is defined in module `{}`; import it",
+                        name.name, module.name
+                    ),
+                    Loc::new(name.loc.file, offset..offset),
+                    format!("import {{ {} }} from {};\n", name.name, module.name),
+                )
+            }
+            _ => diagnostic,
+        }
+    }
+
+    /// Resolves the module-level item `item`, whose id is `id`.
+    fn module_item(&mut self, id: ItemId, item: &Item) {
+        match &item.kind {
+            ItemKind::Fun(fun) => self.fun(fun),
+            ItemKind::Struct(struct_) | ItemKind::Union(struct_) => {
+                self.with_item_rib(id, &struct_.params, true, |this| {
+                    let mut names: HashMap<&str, Loc> = HashMap::new();
+                    for field in &struct_.fields {
+                        this.visit_type(&field.ty);
+                        match names.get(field.name.name.as_str()) {
+                            Some(previous) => {
+                                let previous = previous.clone();
+                                this.duplicate("the field", &field.name, &previous);
+                            }
+                            None => {
+                                names.insert(&field.name.name, field.name.loc.clone());
+                            }
+                        }
+                    }
+                });
+            }
+            ItemKind::Enum(enum_) => {
+                self.with_item_rib(id, &enum_.params, true, |this| {
+                    let mut names: HashMap<&str, Loc> = HashMap::new();
+                    for variant in &enum_.variants {
+                        if let Some(value) = &variant.value {
+                            this.visit_expr(value);
+                        }
+                        match names.get(variant.name.name.as_str()) {
+                            Some(previous) => {
+                                let previous = previous.clone();
+                                this.duplicate("the variant", &variant.name, &previous);
+                            }
+                            None => {
+                                names.insert(&variant.name.name, variant.name.loc.clone());
+                            }
+                        }
+                    }
+                });
+            }
+            ItemKind::Trait(trait_) => {
+                self.with_item_rib(id, &trait_.params, true, |this| {
+                    this.methods(&trait_.items, "trait");
+                });
+            }
+            ItemKind::Impl(impl_) => {
+                self.with_item_rib(id, &impl_.params, true, |this| {
+                    if let Some(trait_) = &impl_.trait_ {
+                        this.resolve(trait_, Expect::Trait);
+                    }
+                    this.visit_type(&impl_.target);
+                    this.methods(&impl_.items, "impl");
+                });
+            }
+            ItemKind::Alias(alias) => self.visit_type(&alias.target),
+            ItemKind::Import(_) | ItemKind::Error => {}
+        }
+    }
+
+    /// Resolves the function `fun`, whose locals are reported through the unused variable lint
+    /// once its body has been resolved, unless the body has syntax errors.
+    fn fun(&mut self, fun: &Fun) {
+        let first_local = self.out.locals.len();
+        self.in_broken_fun = fun.body.as_ref().is_some_and(parser::has_errors);
+        self.with_rib(true, |this| {
+            if let Some(receiver) = &fun.receiver {
+                match this.in_methods {
+                    Some(_) => {
+                        this.declare_local(
+                            &Iden::new("self", receiver.loc.clone()),
+                            LocalKind::SelfParam,
+                            false,
+                        );
+                    }
+                    None => this.diagnostics.push(
+                        Diagnostic::error(
+                            "`self` parameters are only allowed in methods",
+                            receiver.loc.clone(),
+                        )
+                        .with_label("not a method")
+                        .with_note("methods are functions declared in a trait or impl"),
+                    ),
+                }
+            }
+
+            for param in &fun.params {
+                this.visit_type(&param.ty);
+                if let Some(previous) = this.declared_in_rib(&param.name.name) {
+                    this.duplicate("the parameter", &param.name, &previous);
+                    continue;
+                }
+                this.declare_local(&param.name, LocalKind::Param, false);
+            }
+            if let Some(ret) = &fun.ret {
+                this.visit_type(ret);
+            }
+            if let Some(body) = &fun.body {
+                this.visit_block(body);
+            }
+        });
+
+        // Functions without a body have nothing that could use their parameters.
+        if fun.body.is_some() && !self.in_broken_fun {
+            for index in first_local..self.out.locals.len() {
+                self.unused(LocalId(index));
+            }
+        }
+    }
+
+    /// Reports the local variable `id` through the unused variable lint if it is never read.
+    fn unused(&mut self, id: LocalId) {
+        let local = &self.out.locals[id.0];
+        let uses = self.uses[id.0];
+        if uses.read || local.kind == LocalKind::SelfParam || local.name.name.starts_with('_') {
+            return;
+        }
+
+        let name = &local.name;
+        let message = match (local.kind, uses.assigned) {
+            (_, true) => format!("variable `{}` is assigned to, but never used", name.name),
+            (LocalKind::Param, false) => format!("unused parameter `{}`", name.name),
+            (_, false) => format!("unused variable `{}`", name.name),
+        };
+        let diagnostic = Diagnostic::warning(message, name.loc.clone())
+            .with_label("never read")
+            .with_suggestion(
+                "if this is intentional, prefix it with an underscore",
+                name.loc.clone(),
+                format!("_{}", name.name),
+            );
+        self.lints
+            .emit(&lint::UNUSED_VARIABLE, diagnostic, self.diagnostics);
+    }
+
+    /// Reports the new local variable `name` through the shadowing lint if a variable of the
+    /// same function already has its name.
+    fn shadowing(&mut self, name: &Iden) {
+        if name.name.starts_with('_') || self.in_broken_fun {
+            return;
+        }
+
+        let previous = self
+            .ribs
+            .iter()
+            .rev()
+            .take_while(|rib| rib.local)
+            .flat_map(|rib| rib.names.iter().rev())
+            .find(|(iden, res)| iden.name == name.name && matches!(res, Res::Local(_)));
+        let Some((previous, _)) = previous else {
+            return;
+        };
+
+        let diagnostic = Diagnostic::warning(
+            format!(
+                "`{}` shadows an earlier variable with the same name",
+                name.name
+            ),
+            name.loc.clone(),
+        )
+        .with_label(format!("this `{}` hides the earlier one", name.name))
+        .with_secondary(
+            previous.loc.clone(),
+            "the earlier variable is declared here",
+        )
+        .with_help("give the variables different names if they aren't meant to be the same");
+        self.lints
+            .emit(&lint::SHADOWING, diagnostic, self.diagnostics);
+    }
+
+    /// Resolves the items declared inside a trait or impl, which `kind` names.
+    fn methods(&mut self, items: &[Item], kind: &'static str) {
+        let outer = self.in_methods.replace(kind);
+        let mut names: HashMap<&str, Loc> = HashMap::new();
+        for item in items {
+            self.visit_item(item);
+
+            let Some(name) = item.name() else { continue };
+            if !matches!(item.kind, ItemKind::Fun(_)) {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        format!("only functions can be declared in a {}", kind),
+                        item.loc.clone(),
+                    )
+                    .with_label("not a function"),
+                );
+            } else if let Some(previous) = names.get(name.name.as_str()) {
+                let previous = previous.clone();
+                self.duplicate("the function", name, &previous);
+            } else {
+                names.insert(&name.name, name.loc.clone());
+            }
+        }
+        self.in_methods = outer;
+    }
+}
+
+/// Returns the generic parameters of `item`.
+pub fn generic_params(item: &Item) -> &[GenericParam] {
+    match &item.kind {
+        ItemKind::Struct(struct_) | ItemKind::Union(struct_) => &struct_.params,
+        ItemKind::Enum(enum_) => &enum_.params,
+        ItemKind::Trait(trait_) => &trait_.params,
+        ItemKind::Impl(impl_) => &impl_.params,
+        _ => &[],
+    }
+}
+
+impl Visitor for Resolver<'_> {
+    // Items inside traits and impls are resolved in the scope of their parent.
+    fn visit_item(&mut self, item: &Item) {
+        match &item.kind {
+            ItemKind::Fun(fun) => self.fun(fun),
+            _ => visit::walk_item(self, item),
+        }
+    }
+
+    fn visit_type(&mut self, ty: &Type) {
+        match &ty.kind {
+            TypeKind::Path(path) | TypeKind::Mixin(path, _) => {
+                self.resolve(path, Expect::Type);
+            }
+            TypeKind::Ptr(..) | TypeKind::Ref(..) => {}
+        }
+        if let TypeKind::Mixin(_, args) = &ty.kind {
+            for bound in args.iter().flat_map(|arg| &arg.bounds) {
+                self.resolve(&bound.path, Expect::Trait);
+            }
+        }
+        visit::walk_type(self, ty);
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        self.with_rib(true, |this| visit::walk_block(this, block));
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            // The variable isn't in scope in its own type or initial value.
+            StmtKind::Val(val) => {
+                if let Some(ty) = &val.ty {
+                    self.visit_type(ty);
+                }
+                if let Some(value) = &val.value {
+                    self.visit_expr(value);
+                }
+                self.shadowing(&val.name);
+                self.declare_local(&val.name, LocalKind::Val, val.mutable);
+            }
+
+            // Assigning to a variable doesn't use its value, but a compound assignment does.
+            StmtKind::Assign(assign) => {
+                match &assign.target.kind {
+                    ExprKind::Path(path) if path.segments.len() == 1 => {
+                        self.resolve_value(path, assign.op.is_some())
+                    }
+                    _ => self.visit_expr(&assign.target),
+                }
+                self.visit_expr(&assign.value);
+            }
+            _ => visit::walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Path(path) => self.resolve_value(path, true),
+            ExprKind::Struct(lit) => {
+                self.resolve(&lit.path, Expect::Struct);
+                visit::walk_expr(self, expr);
+            }
+            _ => visit::walk_expr(self, expr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::LintFlag;
+    use crate::lint::Level;
+    use crate::source::SourceMap;
+    use crate::{loader, parser};
+
+    /// The outcome of resolving a source file.
+    struct Resolved {
+        /// The source that was resolved.
+        src: &'static str,
+
+        /// The file of the source.
+        file: u32,
+
+        /// What the names in it refer to.
+        resolutions: Resolutions,
+
+        /// The diagnostics that were reported.
+        diagnostics: Vec<Diagnostic>,
+    }
+
+    impl Resolved {
+        /// Returns what the path at the last `needle` in the source refers to.
+        fn path(&self, needle: &str) -> Option<PathRes> {
+            self.path_in(needle, needle)
+        }
+
+        /// Returns what the path at the first `needle` in the last `context` in the source
+        /// refers to.
+        fn path_in(&self, context: &str, needle: &str) -> Option<PathRes> {
+            let start = self
+                .src
+                .rfind(context)
+                .expect("the context is in the source")
+                + context.find(needle).expect("the needle is in the context");
+            let loc = Loc::new(self.file, start..start + needle.len());
+            self.resolutions.paths.get(&loc).copied()
+        }
+
+        /// Returns the id of the item at `index` in the source.
+        fn item(&self, index: usize) -> ItemId {
+            ItemId {
+                file: self.file,
+                index,
+            }
+        }
+
+        /// Returns the messages of the diagnostics.
+        fn messages(&self) -> Vec<&str> {
+            self.diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.message.as_str())
+                .collect()
+        }
+    }
+
+    /// Resolves `src`, with the shadowing lint turned on.
+    fn resolve_src(src: &'static str) -> Resolved {
+        let mut sources = SourceMap::new();
+        let file = sources.add("test.hl", src.to_string());
+        let mut diagnostics = Diagnostics::new();
+        let module = parser::parse(&sources, file, &mut diagnostics).expect("the source parses");
+        let program = loader::load(module, &[], &mut sources, &mut diagnostics);
+        let flags = vec![LintFlag {
+            level: Level::Warn,
+            name: lint::SHADOWING.name.to_string(),
+        }];
+        let mut lints = LintContext::new(flags, false);
+        for module in &program.modules {
+            lints.add_module(&module.ast, &sources, &mut diagnostics);
+        }
+        let resolutions = resolve(&program, &lints, &mut diagnostics);
+        Resolved {
+            src,
+            file,
+            resolutions,
+            diagnostics: diagnostics.iter().cloned().collect(),
+        }
+    }
+
+    #[test]
+    fn locals_and_items() {
+        let resolved = resolve_src(
+            "
+struct Point { x: int32 }
+
+fun origin(scale: int32) -> Point {
+    val zero = 0 * scale;
+    return Point::{ x: zero };
+}
+",
+        );
+        assert!(resolved.messages().is_empty());
+        assert_eq!(
+            resolved.path("Point"),
+            Some(PathRes {
+                res: Res::Item(resolved.item(0)),
+                rest: 0
+            })
+        );
+        let Some(PathRes {
+            res: Res::Local(zero),
+            rest: 0,
+        }) = resolved.path("zero")
+        else {
+            panic!("`zero` is a local variable");
+        };
+        assert_eq!(resolved.resolutions.local(zero).name.name, "zero");
+        let Some(PathRes {
+            res: Res::Local(scale),
+            ..
+        }) = resolved.path("scale")
+        else {
+            panic!("`scale` is a parameter");
+        };
+        assert_eq!(resolved.resolutions.local(scale).kind, LocalKind::Param);
+    }
+
+    #[test]
+    fn generic_and_mixin_paths() {
+        let resolved = resolve_src(
+            "
+mixin struct DynArray!<T: Mem> { buf: *mut T, len: uint }
+
+impl!<T: Mem> DynArray!<T> {
+    fun elem_size() -> uint {
+        return T::size_of();
+    }
+}
+
+struct Numbers = DynArray!<int32>;
+",
+        );
+        assert!(resolved.messages().is_empty());
+        assert_eq!(
+            resolved.path("T::size_of"),
+            Some(PathRes {
+                res: Res::Generic(resolved.item(1), 0),
+                rest: 1
+            })
+        );
+        assert_eq!(
+            resolved.path("DynArray"),
+            Some(PathRes {
+                res: Res::Item(resolved.item(0)),
+                rest: 0
+            })
+        );
+        assert_eq!(
+            resolved.path("Mem"),
+            Some(PathRes {
+                res: Res::Builtin(Builtin::Mem),
+                rest: 0
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_names() {
+        let resolved = resolve_src(
+            "
+fun f() -> int32 {
+    val count = 1;
+    val _t: Unknown = missing();
+    return count + cont + y;
+}
+",
+        );
+        assert_eq!(
+            resolved.messages(),
+            [
+                "unknown type `Unknown`",
+                "unknown name `missing`",
+                "unknown name `cont`",
+                "unknown name `y`",
+            ]
+        );
+        let suggestions: Vec<_> = resolved
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                diagnostic
+                    .suggestions
+                    .iter()
+                    .map(|suggestion| suggestion.replacement.as_str())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        assert_eq!(suggestions, [vec![], vec![], vec!["count"], vec![]]);
+    }
+
+    #[test]
+    fn duplicate_names() {
+        let resolved = resolve_src(
+            "
+struct Point { x: int32 }
+struct Point { y: int32 }
+
+fun f(_a: int32, _a: int32) {}
+",
+        );
+        assert_eq!(
+            resolved.messages(),
+            [
+                "the name `Point` is defined more than once",
+                "the parameter `_a` is defined more than once",
+            ]
+        );
+        let first = resolved.src.find("Point").unwrap();
+        assert_eq!(
+            resolved.diagnostics[0].secondary[0].loc,
+            Loc::new(resolved.file, first..first + "Point".len())
+        );
+    }
+
+    #[test]
+    fn shadowing() {
+        let resolved = resolve_src(
+            "
+fun f(a: int32) -> int32 {
+    val b = a;
+    val b = b + 1;
+    val _c = 1;
+    val _c = 2;
+    if true {
+        val a = b;
+        return a;
+    }
+    return b;
+}
+
+fun g() -> int32 {
+    val b = 3;
+    return b;
+}
+",
+        );
+        assert_eq!(
+            resolved.messages(),
+            [
+                "`b` shadows an earlier variable with the same name",
+                "`a` shadows an earlier variable with the same name",
+            ]
+        );
+        // The initial value of a variable still refers to the one it shadows.
+        let Some(PathRes {
+            res: Res::Local(first),
+            ..
+        }) = resolved.path_in("b + 1", "b")
+        else {
+            panic!("`b` is a local variable");
+        };
+        let start = resolved.src.find("val b").unwrap() + "val ".len();
+        assert_eq!(
+            resolved.resolutions.local(first).name.loc,
+            Loc::new(resolved.file, start..start + 1)
+        );
+    }
+}
@@ -0,0 +1,241 @@
+//! Source files, and the map that hands out their [`Loc::file`] ids.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::Loc;
+
+/// A line and column in a source file.  Both start at 1, and columns count characters rather
+/// than bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LineCol {
+    /// The line number.
+    pub line: usize,
+
+    /// The column number.
+    pub col: usize,
+}
+
+/// A loaded source file.
+#[derive(Clone, Debug)]
+pub struct SourceFile {
+    /// The path the file was loaded from.
+    pub path: PathBuf,
+
+    /// The contents of the file.
+    pub src: String,
+
+    /// The byte offset of the start of each line.
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    /// Creates a source file from its path and contents.
+    pub fn new(path: impl Into<PathBuf>, src: String) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        Self {
+            path: path.into(),
+            src,
+            line_starts,
+        }
+    }
+
+    /// Returns the number of lines in the file.
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Returns the line and column of the byte `offset`.  Offsets past the end of the file are
+    /// clamped to it.
+    pub fn line_col(&self, offset: usize) -> LineCol {
+        let offset = offset.min(self.src.len());
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(line) => line - 1,
+        };
+
+        let start = self.line_starts[line];
+        let col = self.src[start..offset].chars().count() + 1;
+        LineCol {
+            line: line + 1,
+            col,
+        }
+    }
+
+    /// Returns the byte offset of the start of the 1-based `line`.
+    pub fn line_start(&self, line: usize) -> usize {
+        self.line_starts[line - 1]
+    }
+
+    /// Returns the text of the 1-based `line`, without its line ending.
+    pub fn line(&self, line: usize) -> &str {
+        let start = self.line_starts[line - 1];
+        let end = self
+            .line_starts
+            .get(line)
+            .copied()
+            .unwrap_or(self.src.len());
+
+        self.src[start..end].trim_end_matches(['\n', '\r'])
+    }
+}
+
+/// The map from file ids to the source files they were handed out for.
+#[derive(Clone, Debug, Default)]
+pub struct SourceMap {
+    /// The files, indexed by their ids.
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    /// Creates an empty source map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file with the given path and contents, returning its id.
+    pub fn add(&mut self, path: impl Into<PathBuf>, src: String) -> u32 {
+        self.files.push(SourceFile::new(path, src));
+        (self.files.len() - 1) as u32
+    }
+
+    /// Reads the file at `path` and adds it, returning its id.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> io::Result<u32> {
+        let path = path.as_ref();
+        let src = fs::read_to_string(path)?;
+        Ok(self.add(path, src))
+    }
+
+    /// Returns the id of the file loaded from `path`, if there is one.
+    pub fn find(&self, path: impl AsRef<Path>) -> Option<u32> {
+        let path = path.as_ref();
+        self.files
+            .iter()
+            .position(|file| file.path == path)
+            .map(|id| id as u32)
+    }
+
+    /// Returns the file with the id `file`.
+    ///
+    /// # Panics
+    /// Panics if the id wasn't handed out by this source map.
+    pub fn get(&self, file: u32) -> &SourceFile {
+        &self.files[file as usize]
+    }
+
+    /// Returns the files in the map, along with their ids.
+    pub fn files(&self) -> impl Iterator<Item = (u32, &SourceFile)> {
+        self.files
+            .iter()
+            .enumerate()
+            .map(|(id, file)| (id as u32, file))
+    }
+
+    /// Returns the lines and columns of the start and end of `loc`.
+    pub fn range(&self, loc: &Loc) -> (LineCol, LineCol) {
+        let file = self.get(loc.file);
+        (file.line_col(loc.span.start), file.line_col(loc.span.end))
+    }
+
+    /// Formats `loc` as `path:line:col`.
+    pub fn describe(&self, loc: &Loc) -> String {
+        let start = self.range(loc).0;
+        format!(
+            "{}:{}:{}",
+            self.get(loc.file).path.display(),
+            start.line,
+            start.col
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the line and column of `offset` in `src`.
+    fn line_col(src: &str, offset: usize) -> (usize, usize) {
+        let LineCol { line, col } = SourceFile::new("test.hl", src.to_string()).line_col(offset);
+        (line, col)
+    }
+
+    #[test]
+    fn lines_and_columns() {
+        let src = "fun main() {\n    return;\n}";
+        assert_eq!(line_col(src, 0), (1, 1));
+        assert_eq!(line_col(src, 4), (1, 5));
+        assert_eq!(line_col(src, 12), (1, 13));
+        assert_eq!(line_col(src, 13), (2, 1));
+        assert_eq!(line_col(src, 17), (2, 5));
+        assert_eq!(line_col(src, 25), (3, 1));
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        let file = SourceFile::new("test.hl", "a\r\nbc\r\n\r\nd".to_string());
+        assert_eq!(file.line_count(), 4);
+        assert_eq!(file.line_col(1), LineCol { line: 1, col: 2 });
+        assert_eq!(file.line_col(3), LineCol { line: 2, col: 1 });
+        assert_eq!(file.line_col(4), LineCol { line: 2, col: 2 });
+        assert_eq!(file.line_col(9), LineCol { line: 4, col: 1 });
+        assert_eq!(file.line(1), "a");
+        assert_eq!(file.line(2), "bc");
+        assert_eq!(file.line(3), "");
+        assert_eq!(file.line(4), "d");
+    }
+
+    #[test]
+    fn columns_count_characters() {
+        // `é` takes two bytes and `🦀` four, but each is one column.
+        let src = "val é = \"🦀\" + x;";
+        assert_eq!(line_col(src, src.find('=').unwrap()), (1, 7));
+        assert_eq!(line_col(src, src.find('x').unwrap()), (1, 15));
+        assert_eq!(line_col("é\né", 3), (2, 1));
+        assert_eq!(line_col("é\né", 5), (2, 2));
+    }
+
+    #[test]
+    fn tabs_are_one_column() {
+        let src = "\tval\tx = 1;\n\t\ty";
+        assert_eq!(line_col(src, 1), (1, 2));
+        assert_eq!(line_col(src, src.find('x').unwrap()), (1, 6));
+        assert_eq!(line_col(src, src.find('y').unwrap()), (2, 3));
+    }
+
+    #[test]
+    fn end_of_file() {
+        assert_eq!(line_col("", 0), (1, 1));
+        assert_eq!(line_col("ab", 2), (1, 3));
+        // A trailing newline starts a line of its own, which is where the end of the file is.
+        assert_eq!(line_col("ab\n", 3), (2, 1));
+        assert_eq!(line_col("ab\r\n", 4), (2, 1));
+        // Offsets past the end are clamped to it.
+        assert_eq!(line_col("ab\n", 10), (2, 1));
+        assert_eq!(line_col("é", 10), (1, 2));
+    }
+
+    #[test]
+    fn spans() {
+        let mut sources = SourceMap::new();
+        sources.add("first.hl", "fun a() {}\n".to_string());
+        let file = sources.add("dir/second.hl", "\r\n\tfun é() {\r\n}".to_string());
+        let src = &sources.get(file).src;
+        let name = src.find('é').unwrap();
+
+        let loc = Loc::new(file, name..src.len());
+        assert_eq!(
+            sources.range(&loc),
+            (LineCol { line: 2, col: 6 }, LineCol { line: 3, col: 2 })
+        );
+        assert_eq!(sources.describe(&loc), "dir/second.hl:2:6");
+
+        let end = Loc::new(file, src.len()..src.len());
+        assert_eq!(sources.range(&end).0, LineCol { line: 3, col: 2 });
+        assert_eq!(sources.find("dir/second.hl"), Some(file));
+        assert_eq!(sources.find("third.hl"), None);
+    }
+}
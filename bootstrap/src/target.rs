@@ -0,0 +1,41 @@
+//! The targets code can be compiled for.
+
+/// A target architecture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Target {
+    /// The name of the target, as given to `--target`.
+    pub name: &'static str,
+
+    /// The width of a pointer in bits, which is also the width of `int` and `uint`.
+    pub ptr_bits: u32,
+}
+
+impl Target {
+    /// Every supported target.
+    pub const ALL: &'static [Target] = &[
+        Target::new("x86_64", 64),
+        Target::new("aarch64", 64),
+        Target::new("riscv64", 64),
+        Target::new("x86", 32),
+        Target::new("arm", 32),
+        Target::new("riscv32", 32),
+        Target::new("wasm32", 32),
+    ];
+
+    /// Creates a target.
+    const fn new(name: &'static str, ptr_bits: u32) -> Self {
+        Self { name, ptr_bits }
+    }
+
+    /// Returns the target called `name`, if it is supported.
+    pub fn find(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|target| target.name == name)
+    }
+
+    /// Returns the target the compiler is running on.  Unsupported hosts get a target with the
+    /// host's name and pointer width.
+    pub fn host() -> Self {
+        Self::find(std::env::consts::ARCH)
+            .unwrap_or_else(|| Self::new(std::env::consts::ARCH, usize::BITS))
+    }
+}
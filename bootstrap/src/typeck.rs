@@ -0,0 +1,3112 @@
+//! Type checking.
+//!
+//! Hail has no implicit conversions, so every call argument, assignment, return value and
+//! operand must have exactly the type expected of it.  When it doesn't, the error suggests the
+//! `as` cast that would convert it.  The types of `val` bindings without a declared type are
+//! inferred from their values, and integer and float literals without a suffix take whatever
+//! type they are used as, defaulting to `int32` and `float64`.
+//!
+//! Methods are looked up in the impls for the type of the value they are called on, or for what
+//! it refers to if that has none, and the value is borrowed when the method takes `&self` or
+//! `&mut self`.  Inherent impls come before trait impls.  A type can implement each trait once.
+//!
+//! The compiler implements `Mem` for every sized type and `Default` for every primitive, and a
+//! struct whose fields all implement `Default` can derive it with `#derive(Default)`.  Values of
+//! types that implement `Drop` should be dropped by hand, which the `missing_drop` lint checks.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::*;
+use crate::attr;
+use crate::cast::{self, Cast};
+use crate::diagnostic::{plural, similar_name, Diagnostic, Diagnostics};
+use crate::flow;
+use crate::lint::{self, LintContext};
+use crate::literal;
+use crate::loader::Program;
+use crate::mixin::{self, Instance, InstanceId, Instances};
+use crate::parser;
+use crate::resolve::{self, Builtin, ItemId, LocalId, LocalKind, PathRes, Res, Resolutions};
+use crate::source::SourceMap;
+use crate::target::Target;
+use crate::types::{LayoutCx, LayoutError, Ty};
+use crate::visibility;
+use crate::Loc;
+
+/// Checks the types of every function body and enum discriminant in `program` for `target`,
+/// reporting mismatches to `diagnostics` and unsafe casts, missing returns and missing drops
+/// through `lints`.
+/// Every mixin instantiation is expanded along the way, and checked against the mixin's bounds.
+pub fn check(
+    program: &Program,
+    resolutions: &Resolutions,
+    target: Target,
+    sources: &SourceMap,
+    lints: &LintContext,
+    diagnostics: &mut Diagnostics,
+) -> Types {
+    let mut checker = Checker {
+        program,
+        resolutions,
+        target,
+        sources,
+        lints,
+        diagnostics,
+        file: 0,
+        expanded: HashMap::new(),
+        sites: HashSet::new(),
+        decls: HashMap::new(),
+        instance: None,
+        locals: HashMap::new(),
+        moved: HashSet::new(),
+        exprs: HashMap::new(),
+        vars: Vec::new(),
+        literals: Vec::new(),
+        ret: Ty::Unit,
+        ret_loc: None,
+        types: Types::default(),
+    };
+
+    // Field types are needed for layouts, which `size_of` may ask for before the struct, and
+    // derived traits are needed wherever the struct is used.
+    for module in &program.modules {
+        checker.file = module.ast.file;
+        for (index, item) in module.ast.items.iter().enumerate() {
+            let id = ItemId {
+                file: module.ast.file,
+                index,
+            };
+            checker.derives(id, item);
+            if let ItemKind::Struct(struct_) | ItemKind::Union(struct_) = &item.kind {
+                let fields = struct_
+                    .fields
+                    .iter()
+                    .map(|field| checker.lower(&field.ty))
+                    .collect();
+                checker.types.fields.insert(id, fields);
+            }
+        }
+    }
+
+    for module in &program.modules {
+        checker.file = module.ast.file;
+        for (index, item) in module.ast.items.iter().enumerate() {
+            let id = ItemId {
+                file: module.ast.file,
+                index,
+            };
+            checker.item(id, item);
+        }
+    }
+
+    checker.coherence();
+    checker.expand_instances();
+    checker.recursive_types();
+    checker.types
+}
+
+/// What type checking found out that later passes need.
+#[derive(Clone, Debug, Default)]
+pub struct Types {
+    /// The bodies of functions that can end without a `return`, by location, along with the
+    /// type whose `Default::default()` they return when they do.
+    pub implicit_returns: HashMap<Loc, Ty>,
+
+    /// The types of the fields of every struct and union, in the order they are declared and in
+    /// terms of the generic parameters of mixins.  Layouts are computed from these.
+    pub fields: HashMap<ItemId, Vec<Ty>>,
+
+    /// Every instance of every mixin, which is what gets compiled in place of the mixins.
+    pub instances: Instances,
+
+    /// The `impl!` block chosen for each trait that each instance implements.
+    pub trait_impls: HashMap<(InstanceId, Res), ItemId>,
+
+    /// The inherent `impl!` blocks that apply to each instance.
+    pub inherent_impls: HashMap<InstanceId, Vec<ItemId>>,
+
+    /// The traits each struct derives with `#derive`, along with where they are named.
+    pub derives: HashMap<(ItemId, Builtin), Loc>,
+}
+
+/// What an inference variable has been unified with.
+#[derive(Clone, Debug, PartialEq)]
+enum Var {
+    /// Nothing yet.
+    Unbound,
+
+    /// Another variable, which stands for both.
+    Link(usize),
+
+    /// A type.
+    Bound(Ty),
+}
+
+/// An integer literal whose range depends on the target or on inference, which is checked
+/// against its type once that is known.
+#[derive(Debug)]
+struct IntLiteral<'a> {
+    /// The literal.
+    lit: &'a IntLit,
+
+    /// Whether the literal is directly negated.
+    negative: bool,
+
+    /// The type of the literal.
+    ty: Ty,
+
+    /// The location of the literal.
+    loc: Loc,
+}
+
+/// A function found by looking up a name on a type.
+#[derive(Clone, Debug)]
+enum Method<'a> {
+    /// A function declared in the impl or trait `owner`, whose generic parameters stand for
+    /// `args`.  `in_trait` is set for the functions of traits and of impls of traits, which are
+    /// as visible as the trait.
+    Declared {
+        item: &'a Item,
+        fun: &'a Fun,
+        owner: ItemId,
+        args: Vec<Ty>,
+        in_trait: bool,
+    },
+
+    /// A function of a builtin trait, which the compiler provides.
+    Builtin(Builtin, &'static str),
+}
+
+impl Method<'_> {
+    /// Returns where the function is declared, unless it is builtin.
+    fn loc(&self) -> Option<Loc> {
+        match self {
+            Method::Declared { fun, .. } => Some(fun.name.loc.clone()),
+            Method::Builtin(..) => None,
+        }
+    }
+}
+
+/// The types a function takes and returns.
+#[derive(Clone, Debug)]
+struct Signature {
+    /// How the function takes `self`, if it is a method.
+    receiver: Option<ReceiverKind>,
+
+    /// The types of the other parameters.
+    params: Vec<Ty>,
+
+    /// The return type.
+    ret: Ty,
+}
+
+/// The state of [`check`].
+pub(crate) struct Checker<'a> {
+    /// The program being checked.
+    pub(crate) program: &'a Program,
+
+    /// What the paths in the program refer to.
+    resolutions: &'a Resolutions,
+
+    /// The target being compiled for, which decides the width of `int` and `uint`.
+    target: Target,
+
+    /// The source files, for the text of suggestions.
+    sources: &'a SourceMap,
+
+    /// The lint levels.
+    lints: &'a LintContext,
+
+    /// Where errors are reported.
+    pub(crate) diagnostics: &'a mut Diagnostics,
+
+    /// The file of the module being checked.
+    pub(crate) file: u32,
+
+    /// The types of aliases and of `Self` in impls, by item.  `None` marks an item whose type
+    /// is being computed, to catch types defined in terms of themselves.
+    expanded: HashMap<ItemId, Option<Ty>>,
+
+    /// The places mixins are instantiated or named outside of other mixins that have been
+    /// checked.
+    sites: HashSet<Loc>,
+
+    /// Where the functions named by paths such as `Self::new` are declared, by the location of
+    /// the path, for errors about their calls.
+    decls: HashMap<Loc, Loc>,
+
+    /// The `impl!` block whose functions are being checked for an instance, along with its
+    /// arguments for that instance and the instance.
+    pub(crate) instance: Option<(ItemId, Vec<Ty>, InstanceId)>,
+
+    /// The types of the local variables of the function being checked.
+    locals: HashMap<LocalId, Ty>,
+
+    /// The local variables of the function being checked that are used by value somewhere,
+    /// which hands them on to whatever they are passed to.
+    moved: HashSet<LocalId>,
+
+    /// The types of the expressions of the function being checked, by location.
+    exprs: HashMap<Loc, Ty>,
+
+    /// The inference variables of the function being checked.
+    vars: Vec<Var>,
+
+    /// The integer literals of the function being checked whose ranges aren't known yet.
+    literals: Vec<IntLiteral<'a>>,
+
+    /// The return type of the function being checked.
+    ret: Ty,
+
+    /// The location of the return type of the function being checked, if it has one.
+    ret_loc: Option<Loc>,
+
+    /// What has been found out for later passes.
+    pub(crate) types: Types,
+}
+
+impl<'a> Checker<'a> {
+    /// Checks the module-level item `item`, whose id is `id`.
+    fn item(&mut self, id: ItemId, item: &'a Item) {
+        match &item.kind {
+            ItemKind::Fun(fun) => self.fun(fun, None),
+            ItemKind::Impl(impl_) => {
+                self.trait_impl(id, impl_);
+                let self_ty = self.self_ty(id);
+                for item in &impl_.items {
+                    if let ItemKind::Fun(fun) = &item.kind {
+                        self.fun(fun, Some(self_ty.clone()));
+                    }
+                }
+            }
+            ItemKind::Trait(trait_) => {
+                for item in &trait_.items {
+                    if let ItemKind::Fun(fun) = &item.kind {
+                        self.fun(fun, Some(Ty::SelfParam(id)));
+                    }
+                }
+            }
+            ItemKind::Enum(enum_) => {
+                for value in enum_
+                    .variants
+                    .iter()
+                    .filter_map(|variant| variant.value.as_ref())
+                {
+                    let ty = self.infer(value);
+                    if !self.shallow(&ty).is_int() && !self.is_ok(&ty) {
+                        let ty = self.display(&ty);
+                        self.diagnostics.push(
+                            Diagnostic::error(
+                                "enum discriminants must be integers",
+                                value.loc.clone(),
+                            )
+                            .with_label(format!("expected an integer, found `{}`", ty)),
+                        );
+                    }
+                }
+                self.finish();
+            }
+            ItemKind::Alias(_) => {
+                self.alias_ty(id);
+            }
+            ItemKind::Struct(struct_) => self.derived_default(id, struct_),
+            ItemKind::Union(_) | ItemKind::Import(_) | ItemKind::Error => {}
+        }
+    }
+
+    /// Records the traits that `item` derives with `#derive`, reporting those that can't be
+    /// derived.  Only structs can derive traits, and only `Default`.
+    fn derives(&mut self, id: ItemId, item: &Item) {
+        for attr in item
+            .attrs
+            .iter()
+            .filter(|attr| attr::attr_name(attr) == "derive")
+        {
+            if !matches!(item.kind, ItemKind::Struct(_)) {
+                self.diagnostics.push(
+                    Diagnostic::error("only structs can derive traits", attr.loc.clone())
+                        .with_label("not a struct"),
+                );
+                continue;
+            }
+
+            for (name, loc) in attr::arg_names(attr, "trait", self.diagnostics) {
+                if name != Builtin::Default.name() {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("`{}` can't be derived", name), loc.clone())
+                            .with_label("only `Default` can be derived")
+                            .with_help(format!("implement it with `impl {} for ..`", name)),
+                    );
+                    continue;
+                }
+                if let Some(previous) = self.types.derives.get(&(id, Builtin::Default)) {
+                    self.diagnostics.push(
+                        Diagnostic::error("`Default` is derived twice", loc.clone())
+                            .with_label("derived again")
+                            .with_secondary(previous.clone(), "first derived here"),
+                    );
+                    continue;
+                }
+                self.types
+                    .derives
+                    .insert((id, Builtin::Default), loc.clone());
+            }
+        }
+    }
+
+    /// Checks that every field of `struct_`, whose id is `id`, implements `Default` if the
+    /// struct derives it.
+    fn derived_default(&mut self, id: ItemId, struct_: &Struct) {
+        let Some(derive) = self.types.derives.get(&(id, Builtin::Default)).cloned() else {
+            return;
+        };
+        let fields = self.types.fields.get(&id).cloned().unwrap_or_default();
+        for (field, ty) in struct_.fields.iter().zip(&fields) {
+            if self.implements(ty, Res::Builtin(Builtin::Default)) {
+                continue;
+            }
+            self.diagnostics.push(
+                Diagnostic::error(
+                    format!("field `{}` doesn't implement `Default`", field.name.name),
+                    field.ty.loc.clone(),
+                )
+                .with_label(format!(
+                    "`{}` doesn't implement `Default`",
+                    self.display(ty)
+                ))
+                .with_secondary(derive.clone(), "required because `Default` is derived here"),
+            );
+        }
+    }
+
+    /// Checks the function `fun`.  `self_ty` is the type of `Self` in methods.
+    pub(crate) fn fun(&mut self, fun: &'a Fun, self_ty: Option<Ty>) {
+        let Some(body) = &fun.body else { return };
+
+        if let (Some(receiver), Some(self_ty)) = (&fun.receiver, self_ty) {
+            let ty = receiver_ty(receiver.kind, self_ty);
+            if let Some(id) = self
+                .resolutions
+                .def(&Iden::new("self", receiver.loc.clone()))
+            {
+                self.locals.insert(id, ty);
+            }
+        }
+        for param in &fun.params {
+            let ty = self.lower(&param.ty);
+            if let Some(id) = self.resolutions.def(&param.name) {
+                self.locals.insert(id, ty);
+            }
+        }
+        self.ret = fun.ret.as_ref().map_or(Ty::Unit, |ret| self.lower(ret));
+        self.ret_loc = fun.ret.as_ref().map(|ret| ret.loc.clone());
+
+        self.block(body);
+        let exprs = &self.exprs;
+        let diverges = |expr: &Expr| matches!(exprs.get(&expr.loc), Some(Ty::Never));
+        if self.ret != Ty::Unit && !self.ret.has_error() && flow::falls_through(body, &diverges) {
+            self.missing_return(fun, body);
+        }
+        // The moves that would have dropped a variable may have been lost with a syntax error.
+        if !parser::has_errors(body) {
+            self.missing_drops(body);
+        }
+        self.finish();
+    }
+
+    /// Records that `expr` is used by value, if it is a local variable.
+    fn moves(&mut self, expr: &Expr) {
+        if let ExprKind::Path(path) = &expr.kind {
+            if let Some(PathRes {
+                res: Res::Local(id),
+                rest: 0,
+            }) = self.resolutions.path(path)
+            {
+                self.moved.insert(id);
+            }
+        }
+    }
+
+    /// Reports the parameters and variables of the function whose `body` was just checked that
+    /// implement `Drop` but are never dropped, or moved somewhere that could drop them.
+    fn missing_drops(&mut self, body: &Block) {
+        let mut locals: Vec<_> = self
+            .locals
+            .keys()
+            .copied()
+            .filter(|id| !self.moved.contains(id))
+            .collect();
+        locals.sort_by_key(|id| id.0);
+
+        let end = Loc::new(body.loc.file, body.loc.span.end - 1..body.loc.span.end);
+        for id in locals {
+            let local = self.resolutions.local(id);
+            // `self` belongs to whoever called the method.
+            if local.name.name == "self" {
+                continue;
+            }
+            let ty = self.resolved(&self.locals[&id]);
+            if ty.has_error() || !self.implements(&ty, Res::Builtin(Builtin::Drop)) {
+                continue;
+            }
+
+            let name = &local.name;
+            let diagnostic = Diagnostic::warning(
+                format!("`{}` is never dropped", name.name),
+                name.loc.clone(),
+            )
+            .with_label(format!("`{}` implements `Drop`", self.display(&ty)))
+            .with_secondary(
+                end.clone(),
+                format!("`{}` goes out of scope here", name.name),
+            )
+            .with_help(format!(
+                "call `{}.drop()` once it is no longer needed",
+                name.name
+            ));
+            self.lints
+                .emit(&lint::MISSING_DROP, diagnostic, self.diagnostics);
+        }
+    }
+
+    /// Reports that the function `fun` can reach the end of its `body` without returning a
+    /// value, and records that it returns the default value of its return type there.
+    fn missing_return(&mut self, fun: &Fun, body: &Block) {
+        let ret = self.resolved(&self.ret);
+        let ret_name = self.display(&ret);
+        let end = Loc::new(body.loc.file, body.loc.span.end - 1..body.loc.span.end);
+        if !self.implements(&ret, Res::Builtin(Builtin::Default)) {
+            self.diagnostics.push(
+                Diagnostic::error(
+                    format!(
+                        "function `{}` can end without returning a value",
+                        fun.name.name
+                    ),
+                    fun.name.loc.clone(),
+                )
+                .with_label(format!("returns `{}`", ret_name))
+                .with_secondary(end, "the function can end here")
+                .with_note(format!(
+                    "`{}` doesn't implement `Default`, so there is no value to return implicitly",
+                    ret_name
+                ))
+                .with_help("return a value at the end, or call `panic` if it can't be reached"),
+            );
+            return;
+        }
+
+        let message = match flow::returns_value(body) {
+            true => "function doesn't return a value on every path",
+            false => "function never returns a value",
+        };
+        let diagnostic = Diagnostic::warning(message, fun.name.loc.clone())
+            .with_label(format!("returns `{}`", ret_name))
+            .with_secondary(
+                end,
+                format!("`{}::default()` is returned when it ends here", ret_name),
+            );
+        self.lints
+            .emit(&lint::MISSING_RETURN, diagnostic, self.diagnostics);
+        // Later passes substitute the arguments of instances themselves.
+        if self.instance.is_none() {
+            self.types.implicit_returns.insert(body.loc.clone(), ret);
+        }
+    }
+
+    /// Returns whether `ty` implements the trait `trait_`.  Every type whose size is known at
+    /// compile time implements `Mem`, every primitive implements `Default`, and structs
+    /// implement the traits they derive.
+    fn implements(&mut self, ty: &Ty, trait_: Res) -> bool {
+        match ty {
+            Ty::Error => true,
+            Ty::Param(id, index) => {
+                let params = resolve::generic_params(resolve::item(self.program, *id));
+                params.get(*index).is_some_and(|param| {
+                    param
+                        .bounds
+                        .iter()
+                        .any(|bound| !bound.negative && self.trait_res(&bound.path) == Some(trait_))
+                })
+            }
+            _ if trait_ == Res::Builtin(Builtin::Mem) => {
+                // Recursive types have been reported already.
+                let cx = LayoutCx::new(self.program, &self.types.fields, self.target);
+                cx.layout(ty) != Err(LayoutError::Unsized)
+            }
+            Ty::Int(_) | Ty::Float(_) | Ty::Bool | Ty::Char
+                if trait_ == Res::Builtin(Builtin::Default) =>
+            {
+                true
+            }
+            // The fields of structs that derive traits are checked where they are declared.
+            Ty::Adt(id, _) if self.derived(*id, trait_) => true,
+            _ => self.impls().into_iter().any(|impl_| {
+                self.impl_trait(impl_) == Some(Some(trait_))
+                    && self
+                        .impl_args(impl_, ty)
+                        .is_some_and(|args| self.unmet_bound(impl_, &args).is_none())
+            }),
+        }
+    }
+
+    /// Returns whether the struct `id` derives the trait `trait_`.
+    fn derived(&self, id: ItemId, trait_: Res) -> bool {
+        match trait_ {
+            Res::Builtin(builtin) => self.types.derives.contains_key(&(id, builtin)),
+            _ => false,
+        }
+    }
+
+    /// Returns every impl in the program.
+    pub(crate) fn impls(&self) -> Vec<ItemId> {
+        let mut impls = Vec::new();
+        for module in &self.program.modules {
+            for (index, item) in module.ast.items.iter().enumerate() {
+                if let ItemKind::Impl(_) = item.kind {
+                    impls.push(ItemId {
+                        file: module.ast.file,
+                        index,
+                    });
+                }
+            }
+        }
+        impls
+    }
+
+    /// Returns the trait the impl `impl_` implements, which is `None` for inherent impls, or
+    /// nothing if the trait couldn't be resolved.
+    pub(crate) fn impl_trait(&self, impl_: ItemId) -> Option<Option<Res>> {
+        match &resolve::item(self.program, impl_).kind {
+            ItemKind::Impl(Impl {
+                trait_: Some(path), ..
+            }) => self.trait_res(path).map(Some),
+            _ => Some(None),
+        }
+    }
+
+    /// Returns the arguments for the parameters of the impl `impl_` that make the type it is
+    /// for `ty`, if there are any.  Bounds aren't checked.
+    pub(crate) fn impl_args(&mut self, impl_: ItemId, ty: &Ty) -> Option<Vec<Ty>> {
+        let params = resolve::generic_params(resolve::item(self.program, impl_));
+        let mut args = vec![None; params.len()];
+        let target = self.self_ty(impl_);
+        if !match_ty(&target, ty, impl_, &mut args) {
+            return None;
+        }
+        // Parameters that don't appear in the type can't be inferred.
+        Some(
+            args.into_iter()
+                .map(|arg| arg.unwrap_or(Ty::Error))
+                .collect(),
+        )
+    }
+
+    /// Returns the first argument in `args` for the parameters of the impl `impl_` that doesn't
+    /// satisfy one of its bounds, along with the bound.
+    pub(crate) fn unmet_bound(&mut self, impl_: ItemId, args: &[Ty]) -> Option<(Ty, &'a Bound)> {
+        let params = resolve::generic_params(resolve::item(self.program, impl_));
+        for (index, param) in params.iter().enumerate() {
+            if args[index].has_error() {
+                continue;
+            }
+            for bound in &param.bounds {
+                let Some(trait_) = self.trait_res(&bound.path) else {
+                    continue;
+                };
+                if self.implements(&args[index], trait_) == bound.negative {
+                    return Some((args[index].clone(), bound));
+                }
+            }
+        }
+        None
+    }
+
+    /// Checks that the impl `id` of a trait has the functions the trait declares, with the same
+    /// signatures, and no others.
+    fn trait_impl(&mut self, id: ItemId, impl_: &'a Impl) {
+        let Some(path) = &impl_.trait_ else { return };
+        let Some(trait_) = self.trait_res(path) else {
+            return;
+        };
+        let trait_name = &path.last().name;
+        let self_ty = self.self_ty(id);
+
+        // The name, declaration, signature and whether it has a body, of each function.
+        let (decls, trait_loc): (Vec<_>, _) = match trait_ {
+            Res::Builtin(Builtin::Mem) => {
+                self.diagnostics.push(
+                    Diagnostic::error("`Mem` can't be implemented by hand", path.loc.clone())
+                        .with_label("implemented by the compiler")
+                        .with_note(
+                            "every type whose size is known at compile time implements `Mem`",
+                        ),
+                );
+                return;
+            }
+            Res::Builtin(builtin) => {
+                if let Ty::Adt(item, _) = self_ty {
+                    if let Some(derive) = self.types.derives.get(&(item, builtin)) {
+                        self.diagnostics.push(
+                            Diagnostic::error(
+                                format!(
+                                    "conflicting impls of `{}` for `{}`",
+                                    trait_name,
+                                    self.display(&self_ty)
+                                ),
+                                self.impl_header(id),
+                            )
+                            .with_label("conflicting impl")
+                            .with_secondary(derive.clone(), "the trait is also derived here")
+                            .with_note("a type can only implement a trait once"),
+                        );
+                        return;
+                    }
+                }
+                (
+                    builtin_funs(builtin)
+                        .iter()
+                        .map(|(name, _)| {
+                            let method = Method::Builtin(builtin, name);
+                            (*name, None, self.signature(&method, &self_ty), false)
+                        })
+                        .collect(),
+                    None,
+                )
+            }
+            Res::Item(trait_id) => {
+                let item = resolve::item(self.program, trait_id);
+                let ItemKind::Trait(trait_ast) = &item.kind else {
+                    return;
+                };
+                let decls = trait_ast
+                    .items
+                    .iter()
+                    .filter_map(|item| Some((item, item_fun(item)?)))
+                    .map(|(item, fun)| {
+                        let method = Method::Declared {
+                            item,
+                            fun,
+                            owner: trait_id,
+                            args: Vec::new(),
+                            in_trait: true,
+                        };
+                        let signature = self.signature(&method, &self_ty);
+                        let loc = Some(fun.name.loc.clone());
+                        (fun.name.name.as_str(), loc, signature, fun.body.is_some())
+                    })
+                    .collect();
+                (decls, Some(trait_ast.name.loc.clone()))
+            }
+            _ => return,
+        };
+
+        let funs: Vec<&'a Fun> = impl_.items.iter().filter_map(item_fun).collect();
+        for fun in &funs {
+            match decls.iter().find(|(name, ..)| *name == fun.name.name) {
+                Some((_, decl, signature, _)) => {
+                    self.compare_fun(fun, signature, decl.clone(), trait_name)
+                }
+                None => {
+                    let mut diagnostic = Diagnostic::error(
+                        format!(
+                            "`{}` isn't a function of trait `{}`",
+                            fun.name.name, trait_name
+                        ),
+                        fun.name.loc.clone(),
+                    )
+                    .with_label(format!("not declared in `{}`", trait_name));
+                    if let Some(loc) = &trait_loc {
+                        diagnostic =
+                            diagnostic.with_secondary(loc.clone(), "the trait is declared here");
+                    }
+                    let candidates = decls.iter().map(|(name, ..)| *name);
+                    let diagnostic = match similar_name(&fun.name.name, candidates) {
+                        Some(similar) => diagnostic.with_suggestion(
+                            format!(
+                                "the trait has a function with a similar name: `{}`",
+                                similar
+                            ),
+                            fun.name.loc.clone(),
+                            similar,
+                        ),
+                        None => diagnostic,
+                    };
+                    self.diagnostics.push(diagnostic);
+                }
+            }
+        }
+
+        // Functions with a body in the trait don't have to be implemented.
+        let missing: Vec<_> = decls
+            .iter()
+            .filter(|(name, _, _, provided)| {
+                !provided && !funs.iter().any(|fun| fun.name.name == *name)
+            })
+            .collect();
+        if missing.is_empty() {
+            return;
+        }
+        let names = missing
+            .iter()
+            .map(|(name, ..)| format!("`{}`", name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut diagnostic = Diagnostic::error(
+            format!("missing {} in impl of `{}`", names, trait_name),
+            self.impl_header(id),
+        )
+        .with_label(format!("missing {}", names));
+        for (name, decl, signature, _) in missing {
+            diagnostic = match decl {
+                Some(decl) => {
+                    diagnostic.with_secondary(decl.clone(), format!("`{}` is declared here", name))
+                }
+                None => diagnostic.with_note(format!(
+                    "`{}` is declared as `{}`",
+                    name,
+                    self.describe_fun(name, signature)
+                )),
+            };
+        }
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Formats the signature of the function `name` as it is declared, such as
+    /// `fun drop(self)`.
+    fn describe_fun(&self, name: &str, signature: &Signature) -> String {
+        let receiver = signature.receiver.map(|kind| match kind {
+            ReceiverKind::Value => "self".to_string(),
+            ReceiverKind::Ref => "&self".to_string(),
+            ReceiverKind::RefMut => "&mut self".to_string(),
+        });
+        let params = signature.params.iter().map(|param| self.display(param));
+        let params: Vec<_> = receiver.into_iter().chain(params).collect();
+        match signature.ret {
+            Ty::Unit => format!("fun {}({})", name, params.join(", ")),
+            ref ret => format!(
+                "fun {}({}) -> {}",
+                name,
+                params.join(", "),
+                self.display(ret)
+            ),
+        }
+    }
+
+    /// Reports where `fun`, in an impl of the trait `trait_name`, differs from `expected`, the
+    /// signature of its declaration in the trait at `decl`.
+    fn compare_fun(
+        &mut self,
+        fun: &Fun,
+        expected: &Signature,
+        decl: Option<Loc>,
+        trait_name: &str,
+    ) {
+        let mismatch = |message: String, loc: Loc, label: String| {
+            let diagnostic = Diagnostic::error(message, loc).with_label(label);
+            match &decl {
+                Some(decl) => diagnostic.with_secondary(decl.clone(), "declared here in the trait"),
+                None => diagnostic,
+            }
+        };
+
+        let receiver = fun.receiver.as_ref().map(|receiver| receiver.kind);
+        if receiver != expected.receiver {
+            let loc = fun
+                .receiver
+                .as_ref()
+                .map_or(&fun.name.loc, |receiver| &receiver.loc);
+            self.diagnostics.push(mismatch(
+                format!(
+                    "`{}` takes {} but its declaration in trait `{}` takes {}",
+                    fun.name.name,
+                    receiver_name(receiver),
+                    trait_name,
+                    receiver_name(expected.receiver)
+                ),
+                loc.clone(),
+                format!("expected {}", receiver_name(expected.receiver)),
+            ));
+            return;
+        }
+
+        if fun.params.len() != expected.params.len() {
+            self.diagnostics.push(mismatch(
+                format!(
+                    "`{}` takes {} but its declaration in trait `{}` takes {}",
+                    fun.name.name,
+                    plural(fun.params.len(), "parameter"),
+                    trait_name,
+                    expected.params.len()
+                ),
+                fun.name.loc.clone(),
+                format!("expected {}", plural(expected.params.len(), "parameter")),
+            ));
+            return;
+        }
+
+        for (param, expected) in fun.params.iter().zip(&expected.params) {
+            let found = self.lower(&param.ty);
+            if found != *expected && !found.has_error() && !expected.has_error() {
+                self.diagnostics.push(mismatch(
+                    format!(
+                        "parameter `{}` of `{}` doesn't match its declaration in trait `{}`",
+                        param.name.name, fun.name.name, trait_name
+                    ),
+                    param.ty.loc.clone(),
+                    format!(
+                        "expected `{}`, found `{}`",
+                        self.display(expected),
+                        self.display(&found)
+                    ),
+                ));
+                return;
+            }
+        }
+
+        let found = fun.ret.as_ref().map_or(Ty::Unit, |ret| self.lower(ret));
+        let expected = &expected.ret;
+        if found != *expected && !found.has_error() && !expected.has_error() {
+            let loc = fun.ret.as_ref().map_or(&fun.name.loc, |ret| &ret.loc);
+            self.diagnostics.push(mismatch(
+                format!(
+                    "the return type of `{}` doesn't match its declaration in trait `{}`",
+                    fun.name.name, trait_name
+                ),
+                loc.clone(),
+                format!(
+                    "expected `{}`, found `{}`",
+                    self.display(expected),
+                    self.display(&found)
+                ),
+            ));
+        }
+    }
+
+    /// Reports impls of the same trait for the same type, and functions that inherent impls for
+    /// the same type both define.  Two `impl!` blocks only conflict for the instances both apply
+    /// to, so they are left to [`Checker::select_impls`].
+    fn coherence(&mut self) {
+        let impls = self.impls();
+        for (index, &first) in impls.iter().enumerate() {
+            for &second in &impls[index + 1..] {
+                let (Some(trait_), Some(second_trait)) =
+                    (self.impl_trait(first), self.impl_trait(second))
+                else {
+                    continue;
+                };
+                if trait_ != second_trait {
+                    continue;
+                }
+
+                let generic =
+                    |impl_| !resolve::generic_params(resolve::item(self.program, impl_)).is_empty();
+                let (pattern, concrete) = match (generic(first), generic(second)) {
+                    (true, true) => continue,
+                    (true, false) => (first, second),
+                    (false, _) => (second, first),
+                };
+                let ty = self.self_ty(concrete);
+                if ty.has_error() {
+                    continue;
+                }
+                let overlaps = match self.impl_args(pattern, &ty) {
+                    Some(args) => self.unmet_bound(pattern, &args).is_none(),
+                    None => false,
+                };
+                if !overlaps {
+                    continue;
+                }
+
+                match trait_ {
+                    Some(_) => self.conflicting_impls(first, second, &ty),
+                    None => self.duplicate_funs(first, second, &ty),
+                }
+            }
+        }
+    }
+
+    /// Reports that `second` implements the same trait as `first` for `ty`.
+    fn conflicting_impls(&mut self, first: ItemId, second: ItemId, ty: &Ty) {
+        let trait_name = match &resolve::item(self.program, second).kind {
+            ItemKind::Impl(Impl {
+                trait_: Some(path), ..
+            }) => path.last().name.clone(),
+            _ => return,
+        };
+        self.diagnostics.push(
+            Diagnostic::error(
+                format!(
+                    "conflicting impls of `{}` for `{}`",
+                    trait_name,
+                    self.display(ty)
+                ),
+                self.impl_header(second),
+            )
+            .with_label("conflicting impl")
+            .with_secondary(self.impl_header(first), "the first impl is here")
+            .with_note("a type can only implement a trait once"),
+        );
+    }
+
+    /// Reports the functions that the inherent impls `first` and `second`, which both apply to
+    /// `ty`, both define.
+    fn duplicate_funs(&mut self, first: ItemId, second: ItemId, ty: &Ty) {
+        let funs = |impl_| match &resolve::item(self.program, impl_).kind {
+            ItemKind::Impl(impl_) => impl_.items.iter().filter_map(item_fun).collect(),
+            _ => Vec::new(),
+        };
+        let (first_funs, second_funs) = (funs(first), funs(second));
+        for fun in second_funs {
+            let Some(previous) = first_funs
+                .iter()
+                .find(|previous| previous.name.name == fun.name.name)
+            else {
+                continue;
+            };
+            self.diagnostics.push(
+                Diagnostic::error(
+                    format!(
+                        "duplicate definitions of `{}` for `{}`",
+                        fun.name.name,
+                        self.display(ty)
+                    ),
+                    fun.name.loc.clone(),
+                )
+                .with_label("defined again here")
+                .with_secondary(previous.name.loc.clone(), "first defined here"),
+            );
+        }
+    }
+
+    /// Returns the header of the impl `impl_`, from `impl` to the type it is for.
+    pub(crate) fn impl_header(&self, impl_: ItemId) -> Loc {
+        let item = resolve::item(self.program, impl_);
+        let ItemKind::Impl(Impl { target, .. }) = &item.kind else {
+            return item.loc.clone();
+        };
+        Loc::new(item.loc.file, item.loc.span.start..target.loc.span.end)
+    }
+
+    /// Returns what the trait named by `path` resolves to.
+    fn trait_res(&self, path: &Path) -> Option<Res> {
+        match self.resolutions.path(path)? {
+            PathRes { res, rest: 0 } => Some(res),
+            _ => None,
+        }
+    }
+
+    /// Reports the structs and unions that contain themselves without a pointer or reference in
+    /// between, which would make them infinitely big.
+    fn recursive_types(&mut self) {
+        let cx = LayoutCx::new(self.program, &self.types.fields, self.target);
+        let mut ids: Vec<_> = self.types.fields.keys().copied().collect();
+        ids.sort_by_key(|id| (id.file, id.index));
+        for id in ids {
+            let item = resolve::item(self.program, id);
+            let params = resolve::generic_params(item);
+            let args = (0..params.len()).map(|index| Ty::Param(id, index));
+            if cx.layout(&Ty::Adt(id, args.collect())) != Err(LayoutError::Recursive(id)) {
+                continue;
+            }
+
+            let name = item.name().expect("structs and unions have names");
+            self.diagnostics.push(
+                Diagnostic::error(
+                    format!("recursive type `{}` has infinite size", name.name),
+                    name.loc.clone(),
+                )
+                .with_label(format!("`{}` contains itself", name.name))
+                .with_help("store it behind a pointer or reference to break the cycle"),
+            );
+        }
+    }
+
+    /// Gives the literals that are still untyped their default types, and checks that every
+    /// literal fits in its type.  This ends the checking of a function or enum.
+    fn finish(&mut self) {
+        for literal in std::mem::take(&mut self.literals) {
+            // Literals that don't fit on any target have already been reported by the parser.
+            let (lit, negative, loc) = (literal.lit, literal.negative, &literal.loc);
+            if literal::check_int(lit, lit.suffix, negative, literal::MAX_PTR_BITS, loc).is_err() {
+                continue;
+            }
+
+            if let Ty::Int(ty) = self.default(&literal.ty) {
+                let ptr_bits = self.target.ptr_bits;
+                if let Err(error) = literal::check_int(lit, Some(ty), negative, ptr_bits, loc) {
+                    self.diagnostics.push(error.into());
+                }
+            }
+        }
+
+        self.locals.clear();
+        self.moved.clear();
+        self.exprs.clear();
+        self.decls.clear();
+        self.vars.clear();
+    }
+
+    /// Returns the type of `Self` in the impl `id`, which is the type the impl is for.
+    fn self_ty(&mut self, id: ItemId) -> Ty {
+        let ItemKind::Impl(impl_) = &resolve::item(self.program, id).kind else {
+            return Ty::Error;
+        };
+        self.expand(id, &impl_.target, |name| {
+            Diagnostic::error(
+                "the type of `Self` is defined in terms of itself",
+                name.clone(),
+            )
+            .with_label("`Self` can't be used in the type an impl is for")
+        })
+    }
+
+    /// Returns the type the alias `id` stands for.
+    fn alias_ty(&mut self, id: ItemId) -> Ty {
+        let ItemKind::Alias(alias) = &resolve::item(self.program, id).kind else {
+            return Ty::Error;
+        };
+        self.expand(id, &alias.target, |_| {
+            Diagnostic::error(
+                format!(
+                    "the type `{}` is defined in terms of itself",
+                    alias.name.name
+                ),
+                alias.name.loc.clone(),
+            )
+            .with_label(format!("`{}` refers back to itself", alias.name.name))
+            .with_note("an alias must name a type that doesn't depend on the alias")
+        })
+    }
+
+    /// Returns `ty`, the type that the item `id` stands for, lowering it the first time.  If
+    /// the lowering comes back to `id`, the error from `cycle` is reported.
+    fn expand(&mut self, id: ItemId, ty: &Type, cycle: impl FnOnce(&Loc) -> Diagnostic) -> Ty {
+        match self.expanded.get(&id) {
+            Some(Some(ty)) => return ty.clone(),
+            Some(None) => {
+                self.diagnostics.push(cycle(&ty.loc));
+                self.expanded.insert(id, Some(Ty::Error));
+                return Ty::Error;
+            }
+            None => {}
+        }
+
+        self.expanded.insert(id, None);
+        let lowered = self.lower(ty);
+        let lowered = match self.expanded.get(&id) {
+            // The cycle has been reported.
+            Some(Some(Ty::Error)) => Ty::Error,
+            _ => lowered,
+        };
+        self.expanded.insert(id, Some(lowered.clone()));
+        lowered
+    }
+
+    /// Returns the type written as `ty`.
+    pub(crate) fn lower(&mut self, ty: &Type) -> Ty {
+        match &ty.kind {
+            TypeKind::Path(path) => self.lower_path(path),
+            TypeKind::Mixin(path, args) => {
+                let lowered = args.iter().map(|arg| self.lower(&arg.ty)).collect();
+                let parent = self.instance.as_ref().map(|(_, _, instance)| *instance);
+                self.instantiate(path, args, lowered, &ty.loc, parent)
+            }
+            TypeKind::Ptr(mutable, ty) => Ty::Ptr(*mutable, Box::new(self.lower(ty))),
+            TypeKind::Ref(mutable, ty) => Ty::Ref(*mutable, Box::new(self.lower(ty))),
+        }
+    }
+
+    /// Returns the type `path` names.
+    fn lower_path(&mut self, path: &Path) -> Ty {
+        let Some(PathRes { res, rest: 0 }) = self.resolutions.path(path) else {
+            return Ty::Error;
+        };
+
+        if let Res::Item(id) = res {
+            if mixin::is_mixin(self.program, id) && self.sites.insert(path.loc.clone()) {
+                let name = &path.last().name;
+                let params = resolve::generic_params(resolve::item(self.program, id));
+                let placeholders = vec!["_"; params.len()].join(", ");
+                let end = Loc::new(path.loc.file, path.loc.span.end..path.loc.span.end);
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        format!("mixin `{}` is used without arguments", name),
+                        path.loc.clone(),
+                    )
+                    .with_label(format!("expected `{}!<{}>`", name, placeholders))
+                    .with_suggestion(
+                        "instantiate it with the types to use",
+                        end,
+                        format!("!<{}>", placeholders),
+                    ),
+                );
+            }
+        }
+        self.lower_res(res)
+    }
+
+    /// Returns the type `res` refers to.  Mixins get error arguments, since they are only
+    /// types once instantiated.  The parameters of an `impl!` block being checked for an
+    /// instance are replaced by their arguments.
+    fn lower_res(&mut self, res: Res) -> Ty {
+        let ty = self.lower_generic_res(res);
+        match &self.instance {
+            Some((impl_, args, _)) => ty.subst(*impl_, args),
+            None => ty,
+        }
+    }
+
+    /// Returns the type `res` refers to, in terms of the generic parameters it is declared with.
+    fn lower_generic_res(&mut self, res: Res) -> Ty {
+        match res {
+            Res::Builtin(Builtin::Int(ty)) => Ty::Int(ty),
+            Res::Builtin(Builtin::Float(ty)) => Ty::Float(ty),
+            Res::Builtin(Builtin::Bool) => Ty::Bool,
+            Res::Builtin(Builtin::Char) => Ty::Char,
+            Res::Builtin(Builtin::Str) => Ty::Str,
+            Res::Generic(id, index) => Ty::Param(id, index),
+            Res::SelfTy(id) => match &resolve::item(self.program, id).kind {
+                ItemKind::Trait(_) => Ty::SelfParam(id),
+                ItemKind::Impl(_) => self.self_ty(id),
+                item => {
+                    let params = match item {
+                        ItemKind::Struct(struct_) | ItemKind::Union(struct_) => &struct_.params,
+                        ItemKind::Enum(enum_) => &enum_.params,
+                        _ => return Ty::Error,
+                    };
+                    let args = (0..params.len()).map(|index| Ty::Param(id, index));
+                    Ty::Adt(id, args.collect())
+                }
+            },
+            Res::Item(id) => match &resolve::item(self.program, id).kind {
+                ItemKind::Alias(_) => self.alias_ty(id),
+                ItemKind::Struct(_) | ItemKind::Union(_) | ItemKind::Enum(_) => {
+                    let params = resolve::generic_params(resolve::item(self.program, id));
+                    Ty::Adt(id, vec![Ty::Error; params.len()])
+                }
+                _ => Ty::Error,
+            },
+            _ => Ty::Error,
+        }
+    }
+
+    /// Returns the instance of the mixin `path` for `args`, which are written as `written` at
+    /// `loc`.  `parent` is the instance being expanded, if the instantiation is inside a mixin.
+    ///
+    /// Types are lowered again wherever they are used, so instantiations written outside of
+    /// mixins are only checked the first time.
+    pub(crate) fn instantiate(
+        &mut self,
+        path: &Path,
+        written: &[MixinArg],
+        mut args: Vec<Ty>,
+        loc: &Loc,
+        parent: Option<InstanceId>,
+    ) -> Ty {
+        let Some(PathRes { res, rest: 0 }) = self.resolutions.path(path) else {
+            return Ty::Error;
+        };
+        let report = match parent {
+            Some(_) => false,
+            None => self.sites.insert(loc.clone()),
+        };
+
+        let id = match res {
+            Res::Item(id) if mixin::is_mixin(self.program, id) => id,
+            _ => {
+                if report {
+                    let name = &path.last().name;
+                    let args_loc = Loc::new(loc.file, path.loc.span.end..loc.span.end);
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("`{}` isn't a mixin", name), path.loc.clone())
+                            .with_label("only mixins can be instantiated with `!<..>`")
+                            .with_suggestion("remove the arguments", args_loc, ""),
+                    );
+                }
+                return self.lower_res(res);
+            }
+        };
+
+        let item = resolve::item(self.program, id);
+        let params = resolve::generic_params(item);
+        if args.len() != params.len() {
+            if report {
+                let name = item.name().expect("mixins have names");
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        format!(
+                            "mixin `{}` takes {} but {} supplied",
+                            name.name,
+                            plural(params.len(), "argument"),
+                            match args.len() {
+                                1 => "1 was".to_string(),
+                                count => format!("{} were", count),
+                            }
+                        ),
+                        loc.clone(),
+                    )
+                    .with_label(format!("expected {}", plural(params.len(), "argument")))
+                    .with_secondary(name.loc.clone(), "the mixin is declared here"),
+                );
+            }
+            args.resize(params.len(), Ty::Error);
+            return Ty::Adt(id, args);
+        }
+
+        // Arguments that refer to generic parameters are checked once the mixin or impl they
+        // are in is instantiated.
+        if !args.iter().all(mixin::is_concrete) {
+            return Ty::Adt(id, args);
+        }
+
+        if self.types.instances.find(id, &args).is_none() {
+            for (index, param) in params.iter().enumerate() {
+                let arg_loc = written.get(index).map_or(loc, |arg| &arg.ty.loc);
+                let restated = written.get(index).map_or(&[][..], |arg| &arg.bounds[..]);
+                for bound in param.bounds.iter().chain(restated) {
+                    self.check_bound(&args[index], bound, arg_loc, parent);
+                }
+            }
+        }
+
+        self.types.instances.insert(Instance {
+            item: id,
+            args: args.clone(),
+            loc: loc.clone(),
+            parent,
+        });
+        Ty::Adt(id, args)
+    }
+
+    /// Reports `arg`, written at `loc`, if it doesn't satisfy `bound`.  `parent` is the instance
+    /// being expanded, if the argument is inside a mixin.
+    fn check_bound(&mut self, arg: &Ty, bound: &Bound, loc: &Loc, parent: Option<InstanceId>) {
+        let Some(trait_) = self.trait_res(&bound.path) else {
+            return;
+        };
+        if self.implements(arg, trait_) != bound.negative {
+            return;
+        }
+
+        let (arg, trait_name) = (self.display(arg), &bound.path.last().name);
+        let diagnostic = match bound.negative {
+            false => Diagnostic::error(
+                format!("`{}` doesn't implement `{}`", arg, trait_name),
+                loc.clone(),
+            )
+            .with_label(format!(
+                "`{}` is required to implement `{}`",
+                arg, trait_name
+            )),
+            true => Diagnostic::error(
+                format!("`{}` implements `{}`", arg, trait_name),
+                loc.clone(),
+            )
+            .with_label(format!(
+                "`{}` is required not to implement `{}`",
+                arg, trait_name
+            )),
+        }
+        .with_secondary(bound.loc.clone(), "required by this bound");
+        let diagnostic = match parent {
+            Some(parent) => self
+                .types
+                .instances
+                .backtrace(self.program, parent, diagnostic),
+            None => diagnostic,
+        };
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Returns the type of the local `id`.
+    fn local_ty(&self, id: LocalId) -> Ty {
+        self.locals.get(&id).cloned().unwrap_or(Ty::Error)
+    }
+
+    /// Returns the signature of the function `fun`, declared at module level.
+    fn fun_ty(&mut self, fun: &Fun) -> Ty {
+        let params = fun
+            .params
+            .iter()
+            .map(|param| self.lower(&param.ty))
+            .collect();
+        let ret = fun.ret.as_ref().map_or(Ty::Unit, |ret| self.lower(ret));
+        Ty::Fun(params, Box::new(ret))
+    }
+
+    /// Returns a new inference variable for a literal, which is an integer one if `int` is set
+    /// and a float one otherwise.
+    fn new_var(&mut self, int: bool) -> Ty {
+        let var = self.vars.len();
+        self.vars.push(Var::Unbound);
+        match int {
+            true => Ty::IntVar(var),
+            false => Ty::FloatVar(var),
+        }
+    }
+
+    /// Returns the representative of the inference variable `var`.
+    fn root(&self, mut var: usize) -> usize {
+        while let Var::Link(next) = self.vars[var] {
+            var = next;
+        }
+        var
+    }
+
+    /// Returns `ty`, or what it has been inferred to be if it is an inference variable.
+    fn shallow(&self, ty: &Ty) -> Ty {
+        match ty {
+            Ty::IntVar(var) | Ty::FloatVar(var) => {
+                let root = self.root(*var);
+                match &self.vars[root] {
+                    Var::Bound(ty) => ty.clone(),
+                    _ if matches!(ty, Ty::IntVar(_)) => Ty::IntVar(root),
+                    _ => Ty::FloatVar(root),
+                }
+            }
+            _ => ty.clone(),
+        }
+    }
+
+    /// Returns `ty` with every inference variable in it replaced by what it has been inferred to
+    /// be so far.
+    fn resolved(&self, ty: &Ty) -> Ty {
+        match self.shallow(ty) {
+            Ty::Ptr(mutable, ty) => Ty::Ptr(mutable, Box::new(self.resolved(&ty))),
+            Ty::Ref(mutable, ty) => Ty::Ref(mutable, Box::new(self.resolved(&ty))),
+            Ty::Adt(id, args) => Ty::Adt(id, args.iter().map(|ty| self.resolved(ty)).collect()),
+            Ty::Fun(params, ret) => Ty::Fun(
+                params.iter().map(|ty| self.resolved(ty)).collect(),
+                Box::new(self.resolved(&ret)),
+            ),
+            ty => ty,
+        }
+    }
+
+    /// Returns what the literal type `ty` has been inferred to be, giving it its default type
+    /// if it hasn't been.
+    fn default(&mut self, ty: &Ty) -> Ty {
+        match self.shallow(ty) {
+            Ty::IntVar(var) => {
+                self.vars[var] = Var::Bound(Ty::Int(IntTy::Int32));
+                Ty::Int(IntTy::Int32)
+            }
+            Ty::FloatVar(var) => {
+                self.vars[var] = Var::Bound(Ty::Float(FloatTy::Float64));
+                Ty::Float(FloatTy::Float64)
+            }
+            ty => ty,
+        }
+    }
+
+    /// Makes `a` and `b` the same type, inferring the types of literals in them.  Returns
+    /// whether they can be the same.
+    fn unify(&mut self, a: &Ty, b: &Ty) -> bool {
+        match (self.shallow(a), self.shallow(b)) {
+            (Ty::Error, _) | (_, Ty::Error) | (_, Ty::Never) => true,
+            (Ty::IntVar(a), Ty::IntVar(b)) | (Ty::FloatVar(a), Ty::FloatVar(b)) => {
+                if a != b {
+                    self.vars[a] = Var::Link(b);
+                }
+                true
+            }
+            (Ty::IntVar(var), ty @ Ty::Int(_))
+            | (ty @ Ty::Int(_), Ty::IntVar(var))
+            | (Ty::FloatVar(var), ty @ Ty::Float(_))
+            | (ty @ Ty::Float(_), Ty::FloatVar(var)) => {
+                self.vars[var] = Var::Bound(ty);
+                true
+            }
+            (Ty::Ptr(a_mut, a), Ty::Ptr(b_mut, b)) | (Ty::Ref(a_mut, a), Ty::Ref(b_mut, b)) => {
+                a_mut == b_mut && self.unify(&a, &b)
+            }
+            (Ty::Adt(a_id, a_args), Ty::Adt(b_id, b_args)) => {
+                a_id == b_id
+                    && a_args.len() == b_args.len()
+                    && a_args.iter().zip(&b_args).all(|(a, b)| self.unify(a, b))
+            }
+            (Ty::Fun(a_params, a_ret), Ty::Fun(b_params, b_ret)) => {
+                a_params.len() == b_params.len()
+                    && a_params
+                        .iter()
+                        .zip(&b_params)
+                        .all(|(a, b)| self.unify(a, b))
+                    && self.unify(&a_ret, &b_ret)
+            }
+            (a, b) => a == b,
+        }
+    }
+
+    /// Returns whether `ty` is one that errors shouldn't be reported for, because it comes from
+    /// an error or from code that never finishes.
+    fn is_ok(&self, ty: &Ty) -> bool {
+        matches!(self.shallow(ty), Ty::Error | Ty::Never)
+    }
+
+    /// Formats `ty` as it is written in Hail.
+    pub(crate) fn display(&self, ty: &Ty) -> String {
+        self.resolved(ty).display(self.program).to_string()
+    }
+
+    /// Returns the source text of `expr`.
+    fn source(&self, expr: &Expr) -> &'a str {
+        &self.sources.get(expr.loc.file).src[expr.loc.span.clone()]
+    }
+
+    /// Returns whether a value of type `from` can be converted to `to` with `as`.  `literal` is
+    /// the value and whether it is negated, if it is an integer literal.
+    fn classify_cast(&self, from: &Ty, to: &Ty, literal: Option<(u128, bool)>) -> Cast {
+        let (from, to) = (self.resolved(from), self.resolved(to));
+        let ptr_bits = self.target.ptr_bits;
+        cast::classify(self.program, &from, &to, literal, ptr_bits, |ty| {
+            self.display(ty)
+        })
+    }
+
+    /// Checks the cast `expr` of `value`, of type `from`, to `to`, reporting invalid casts and
+    /// those that may lose information or break memory safety.
+    fn cast(&mut self, expr: &Expr, value: &Expr, from: &Ty, to: &Ty) {
+        let literal = match &value.kind {
+            ExprKind::Lit(Lit::Int(lit)) => Some((lit.value, false)),
+            ExprKind::Unary(UnOp::Neg, operand) => match &operand.kind {
+                ExprKind::Lit(Lit::Int(lit)) => Some((lit.value, true)),
+                _ => None,
+            },
+            _ => None,
+        };
+        match self.classify_cast(from, to, literal) {
+            Cast::Ok => {}
+            Cast::Unsafe(message, label) => {
+                let diagnostic = Diagnostic::warning(message, expr.loc.clone()).with_label(label);
+                self.lints
+                    .emit(&lint::UNSAFE_CODE, diagnostic, self.diagnostics);
+            }
+            Cast::Invalid => {
+                let (from, to) = (self.display(from), self.display(to));
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        format!("cannot cast `{}` to `{}`", from, to),
+                        expr.loc.clone(),
+                    )
+                    .with_label("invalid cast")
+                    .with_note(
+                        "`as` converts between numbers, between numbers and pointers, between \
+                         pointers and references, and from enums to integers",
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Returns the error for `expr`, of type `found`, where a value of type `expected` was
+    /// expected.  If a cast would convert it, the error suggests one.
+    fn mismatch(&self, expr: &Expr, expected: &Ty, found: &Ty) -> Diagnostic {
+        let expected_name = self.display(expected);
+        let diagnostic =
+            Diagnostic::error("mismatched types", expr.loc.clone()).with_label(format!(
+                "expected `{}`, found `{}`",
+                expected_name,
+                self.display(found)
+            ));
+        // Types that are still being inferred can't be written in the suggestion.
+        let written = self.resolved(expected);
+        if self.classify_cast(found, expected, None) == Cast::Invalid
+            || written.has_error()
+            || written.has_vars()
+            || !expr.attrs.is_empty()
+        {
+            return diagnostic;
+        }
+
+        let src = self.source(expr);
+        let replacement = match expr.kind {
+            ExprKind::Binary(..) | ExprKind::Cast(..) => format!("({}) as {}", src, expected_name),
+            _ => format!("{} as {}", src, expected_name),
+        };
+        diagnostic
+            .with_note("Hail never converts between types implicitly")
+            .with_suggestion("convert with `as`", expr.loc.clone(), replacement)
+    }
+
+    /// Checks that `expr` has the type `expected`, reporting an error if it doesn't.
+    fn expect(&mut self, expr: &'a Expr, expected: &Ty) {
+        self.expect_because(expr, expected, None);
+    }
+
+    /// Checks that `expr` has the type `expected`, reporting an error if it doesn't.  The error
+    /// points out `because`, the location and reason of the expectation, if there is one.
+    fn expect_because(&mut self, expr: &'a Expr, expected: &Ty, because: Option<(Loc, &str)>) {
+        let found = self.infer(expr);
+        if self.unify(expected, &found) {
+            return;
+        }
+
+        let mut diagnostic = self.mismatch(expr, expected, &found);
+        if let Some((loc, reason)) = because {
+            diagnostic = diagnostic.with_secondary(loc, reason);
+        }
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Checks the statements of `block`.
+    fn block(&mut self, block: &'a Block) {
+        for stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+    }
+
+    /// Checks `stmt`.
+    fn stmt(&mut self, stmt: &'a Stmt) {
+        match &stmt.kind {
+            StmtKind::Val(val) => {
+                let ty = match (&val.ty, &val.value) {
+                    (Some(ty), value) => {
+                        let ty = self.lower(ty);
+                        if let Some(value) = value {
+                            self.expect(value, &ty);
+                        }
+                        ty
+                    }
+                    (None, Some(value)) => self.infer(value),
+                    (None, None) => {
+                        self.diagnostics.push(
+                            Diagnostic::error(
+                                format!("the type of `{}` can't be inferred", val.name.name),
+                                val.name.loc.clone(),
+                            )
+                            .with_label("it has no type or value")
+                            .with_suggestion(
+                                "give it a type",
+                                Loc::new(
+                                    val.name.loc.file,
+                                    val.name.loc.span.end..val.name.loc.span.end,
+                                ),
+                                ": int32",
+                            ),
+                        );
+                        Ty::Error
+                    }
+                };
+                if let Some(value) = &val.value {
+                    self.moves(value);
+                }
+                if let Some(id) = self.resolutions.def(&val.name) {
+                    self.locals.insert(id, ty);
+                }
+            }
+            StmtKind::Assign(assign) => {
+                let mut target = self.infer(&assign.target);
+                self.place(&assign.target, "assign to");
+
+                // The suggested fix dereferences an indexed pointer, so check the value against
+                // the element.
+                if let (ExprKind::Index(..), Some(element)) =
+                    (&assign.target.kind, self.shallow(&target).pointee())
+                {
+                    target = element.clone();
+                }
+                match assign.op {
+                    Some(op) => {
+                        self.binary(op, &assign.target, &target, &assign.value);
+                    }
+                    None => {
+                        self.expect(&assign.value, &target);
+                        self.moves(&assign.value);
+                    }
+                }
+            }
+            StmtKind::Expr(expr) => {
+                self.infer(expr);
+            }
+            StmtKind::Return(value) => self.ret(stmt, value.as_ref()),
+            StmtKind::If(if_) => self.if_(if_),
+            StmtKind::While(while_) => {
+                self.cond(&while_.cond);
+                self.block(&while_.body);
+            }
+            StmtKind::Block(block) => self.block(block),
+            StmtKind::Break | StmtKind::Continue | StmtKind::Error => {}
+        }
+    }
+
+    /// Checks the `return` statement `stmt`, which returns `value`.
+    fn ret(&mut self, stmt: &Stmt, value: Option<&'a Expr>) {
+        let ret = self.ret.clone();
+        match value {
+            Some(value) if ret == Ty::Unit => {
+                self.infer(value);
+                self.diagnostics.push(
+                    Diagnostic::error("this function doesn't return a value", value.loc.clone())
+                        .with_label("a value is returned here")
+                        .with_help(
+                            "declare the type it returns after its parameters, as in `-> int32`",
+                        ),
+                );
+            }
+            Some(value) => {
+                let because = self
+                    .ret_loc
+                    .clone()
+                    .map(|loc| (loc, "expected because of this return type"));
+                self.expect_because(value, &ret, because);
+                self.moves(value);
+            }
+            None if ret != Ty::Unit && !ret.has_error() => {
+                let ret_name = self.display(&ret);
+                let mut diagnostic = Diagnostic::error(
+                    format!(
+                        "`return` without a value in a function that returns `{}`",
+                        ret_name
+                    ),
+                    stmt.loc.clone(),
+                )
+                .with_label(format!("expected a value of type `{}`", ret_name));
+                if let Some(loc) = &self.ret_loc {
+                    diagnostic =
+                        diagnostic.with_secondary(loc.clone(), "the function returns this type");
+                }
+                self.diagnostics.push(diagnostic);
+            }
+            None => {}
+        }
+    }
+
+    /// Checks the `if` statement `if_`.
+    fn if_(&mut self, if_: &'a If) {
+        self.cond(&if_.cond);
+        self.block(&if_.then);
+        match &if_.else_ {
+            Some(Else::Block(block)) => self.block(block),
+            Some(Else::If(if_)) => self.if_(if_),
+            None => {}
+        }
+    }
+
+    /// Checks that the condition `cond` is a `bool`.
+    fn cond(&mut self, cond: &'a Expr) {
+        let ty = self.infer(cond);
+        if self.unify(&Ty::Bool, &ty) {
+            return;
+        }
+
+        let mut diagnostic = Diagnostic::error("conditions must be `bool`", cond.loc.clone())
+            .with_label(format!("expected `bool`, found `{}`", self.display(&ty)));
+        if self.shallow(&ty).is_int() && cond.attrs.is_empty() {
+            let src = self.source(cond);
+            let replacement = match cond.kind {
+                ExprKind::Binary(..) => format!("({}) != 0", src),
+                _ => format!("{} != 0", src),
+            };
+            diagnostic = diagnostic
+                .with_note("integers aren't true or false in Hail")
+                .with_suggestion("compare it with zero", cond.loc.clone(), replacement);
+        }
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Returns the type of `expr`.
+    fn infer(&mut self, expr: &'a Expr) -> Ty {
+        let ty = self.infer_kind(expr);
+        self.exprs.insert(expr.loc.clone(), ty.clone());
+        ty
+    }
+
+    /// Returns the type of `expr`, by its kind.
+    fn infer_kind(&mut self, expr: &'a Expr) -> Ty {
+        match &expr.kind {
+            ExprKind::Lit(lit) => self.lit(lit, false, &expr.loc),
+            ExprKind::Path(path) => self.path(path),
+            ExprKind::Call(callee, args) => self.call(callee, args),
+            ExprKind::Method(recv, name, args) => self.method(recv, name, args),
+            ExprKind::Field(base, name) => {
+                let base = self.infer(base);
+                self.field(&base, name)
+            }
+            ExprKind::Index(base, index) => {
+                let base_ty = self.infer(base);
+                self.expect(index, &Ty::Int(IntTy::Uint));
+                match self.shallow(&base_ty) {
+                    // Indexing offsets the pointer, leaving its type as it was.
+                    ty @ (Ty::Ptr(..) | Ty::Ref(..) | Ty::Error) => ty,
+                    ty => {
+                        self.diagnostics.push(
+                            Diagnostic::error(
+                                format!("`{}` can't be indexed", self.display(&ty)),
+                                base.loc.clone(),
+                            )
+                            .with_label("not a pointer or reference")
+                            .with_note("only pointers and references can be indexed"),
+                        );
+                        Ty::Error
+                    }
+                }
+            }
+            ExprKind::Unary(op, operand) => self.unary(expr, *op, operand),
+            ExprKind::Binary(op, lhs, rhs) => {
+                let lhs_ty = self.infer(lhs);
+                self.binary(*op, lhs, &lhs_ty, rhs)
+            }
+            ExprKind::Cast(value, ty) => {
+                let from = self.infer(value);
+                let to = self.lower(ty);
+                self.cast(expr, value, &from, &to);
+                to
+            }
+            ExprKind::Struct(lit) => self.struct_lit(lit),
+            ExprKind::Error => Ty::Error,
+        }
+    }
+
+    /// Returns the type of the literal `lit` at `loc`, which is directly negated if `negative`
+    /// is set.
+    fn lit(&mut self, lit: &'a Lit, negative: bool, loc: &Loc) -> Ty {
+        match lit {
+            Lit::Bool(_) => Ty::Bool,
+            Lit::Int(lit) => {
+                let ty = match lit.suffix {
+                    // The width of `int` and `uint` depends on the target, which the parser
+                    // doesn't know, so their literals are checked again.
+                    Some(ty @ (IntTy::Int | IntTy::Uint)) => Ty::Int(ty),
+                    Some(ty) => return Ty::Int(ty),
+                    None => self.new_var(true),
+                };
+                self.literals.push(IntLiteral {
+                    lit,
+                    negative,
+                    ty: ty.clone(),
+                    loc: loc.clone(),
+                });
+                ty
+            }
+            Lit::Float(FloatLit {
+                suffix: Some(ty), ..
+            }) => Ty::Float(*ty),
+            Lit::Float(_) => self.new_var(false),
+            Lit::Str(_) => Ty::Ref(false, Box::new(Ty::Str)),
+            Lit::ByteStr(_) => Ty::Ptr(false, Box::new(Ty::Int(IntTy::Uint8))),
+            Lit::Char(_) => Ty::Char,
+            Lit::Byte(_) => Ty::Int(IntTy::Uint8),
+        }
+    }
+
+    /// Returns the type of the value `path` names.
+    fn path(&mut self, path: &Path) -> Ty {
+        let Some(PathRes { res, rest }) = self.resolutions.path(path) else {
+            return Ty::Error;
+        };
+        if rest > 0 {
+            // What comes after a type, as in `T::size_of`, is looked up in its impls.
+            return self.associated(path, res, rest);
+        }
+
+        match res {
+            Res::Local(id) => self.local_ty(id),
+            Res::Item(id) => match &resolve::item(self.program, id).kind {
+                ItemKind::Fun(fun) => self.fun_ty(fun),
+                _ => Ty::Error,
+            },
+            Res::Variant(id, _) => {
+                let params = resolve::generic_params(resolve::item(self.program, id));
+                Ty::Adt(id, vec![Ty::Error; params.len()])
+            }
+            Res::Builtin(Builtin::Panic) => {
+                Ty::Fun(vec![Ty::Ref(false, Box::new(Ty::Str))], Box::new(Ty::Never))
+            }
+            Res::Generic(..) | Res::SelfTy(_) | Res::Builtin(_) => Ty::Error,
+        }
+    }
+
+    /// Returns the type of the value `path` names, whose last `rest` segments come after the
+    /// type `res` refers to, as in `Self::new`.  Methods named this way take `self` as their
+    /// first argument.
+    fn associated(&mut self, path: &Path, res: Res, rest: usize) -> Ty {
+        let ty = self.lower_res(res);
+        if rest != 1 || ty == Ty::Error {
+            return Ty::Error;
+        }
+
+        let name = path.last();
+        let methods = self.lookup(&ty, &name.name);
+        if methods.is_empty() {
+            let ty_name = self.display(&ty);
+            let diagnostic = match name.name.as_str() {
+                "size_of" | "align_of" => {
+                    let ty_end = path.segments[path.segments.len() - 2].loc.span.end;
+                    let ty_loc = Loc::new(path.loc.file, path.loc.span.start..ty_end);
+                    let diagnostic =
+                        Diagnostic::error(format!("`{}` doesn't implement `Mem`", ty_name), ty_loc)
+                            .with_label(format!("`{}` has no size known at compile time", ty_name));
+                    match ty {
+                        Ty::Param(..) => {
+                            diagnostic.with_help(format!("add a `Mem` bound: `{}: Mem`", ty_name))
+                        }
+                        _ => diagnostic,
+                    }
+                }
+                _ => Diagnostic::error(
+                    format!("no function named `{}` found for `{}`", name.name, ty_name),
+                    name.loc.clone(),
+                )
+                .with_label(format!("not found in `{}`", ty_name)),
+            };
+            self.diagnostics.push(diagnostic);
+            return Ty::Error;
+        }
+
+        let Some(method) = self.pick(methods, &ty, name) else {
+            return Ty::Error;
+        };
+        self.check_visible(&method, name);
+        if let Some(loc) = method.loc() {
+            self.decls.insert(path.loc.clone(), loc);
+        }
+        let signature = self.signature(&method, &ty);
+        let receiver = signature.receiver.map(|kind| receiver_ty(kind, ty));
+        let params = receiver.into_iter().chain(signature.params).collect();
+        Ty::Fun(params, Box::new(signature.ret))
+    }
+
+    /// Returns the type of the call of the method `name` on `recv` with `args`.  The method is
+    /// looked up on the type of `recv`, then on what it refers to if it is a reference, and
+    /// `recv` is borrowed if the method takes `&self` or `&mut self`.
+    fn method(&mut self, recv: &'a Expr, name: &Iden, args: &'a [Expr]) -> Ty {
+        let recv_ty = self.infer(recv);
+        let mut ty = self.resolved(&recv_ty);
+        // Whether the reference `recv` was looked through is `mut`.
+        let mut behind = None;
+        let methods = loop {
+            if ty == Ty::Error {
+                return self.infer_args(args);
+            }
+            let methods = self.lookup(&ty, &name.name);
+            if !methods.is_empty() {
+                break methods;
+            }
+            match ty {
+                Ty::Ref(mutable, pointee) => {
+                    behind = Some(mutable);
+                    ty = *pointee;
+                }
+                _ => {
+                    let mut diagnostic = Diagnostic::error(
+                        format!(
+                            "no method named `{}` found for `{}`",
+                            name.name,
+                            self.display(&recv_ty)
+                        ),
+                        name.loc.clone(),
+                    )
+                    .with_label("method not found");
+                    if let Ty::Ptr(..) = ty {
+                        diagnostic = diagnostic.with_help(
+                            "dereference the pointer with `*` to call methods on what it points to",
+                        );
+                    }
+                    self.diagnostics.push(diagnostic);
+                    return self.infer_args(args);
+                }
+            }
+        };
+
+        let Some(method) = self.pick(methods, &ty, name) else {
+            return self.infer_args(args);
+        };
+        self.check_visible(&method, name);
+        let signature = self.signature(&method, &ty);
+        match signature.receiver {
+            None => {
+                let mut diagnostic = Diagnostic::error(
+                    format!("`{}` is an associated function, not a method", name.name),
+                    name.loc.clone(),
+                )
+                .with_label("not a method")
+                .with_help(format!(
+                    "call it as `{}::{}(..)`",
+                    self.display(&ty),
+                    name.name
+                ));
+                if let Some(loc) = method.loc() {
+                    diagnostic = diagnostic.with_secondary(loc, "declared here without `self`");
+                }
+                self.diagnostics.push(diagnostic);
+            }
+            Some(ReceiverKind::RefMut) => match behind {
+                None => self.place(recv, "mutably borrow"),
+                Some(false) => self.behind(recv, &Ty::Ref(false, Box::new(ty)), "mutably borrow"),
+                Some(true) => {}
+            },
+            Some(ReceiverKind::Value) => self.moves(recv),
+            Some(ReceiverKind::Ref) => {}
+        }
+
+        self.check_args(&name.loc, method.loc(), &signature.params, args);
+        signature.ret
+    }
+
+    /// Infers the types of `args`, the arguments of a call that can't be checked, and returns
+    /// the error type for the call.
+    fn infer_args(&mut self, args: &'a [Expr]) -> Ty {
+        for arg in args {
+            self.infer(arg);
+        }
+        Ty::Error
+    }
+
+    /// Returns the functions named `name` that can be called on `ty`.  Those of inherent impls
+    /// hide those of traits.
+    fn lookup(&mut self, ty: &Ty, name: &str) -> Vec<Method<'a>> {
+        let mut inherent = Vec::new();
+        let mut traits = Vec::new();
+
+        // Generic parameters and `Self` in traits implement the traits they are bounded by.
+        let bounds = match ty {
+            Ty::Error => return Vec::new(),
+            Ty::Param(owner, index) => {
+                let params = resolve::generic_params(resolve::item(self.program, *owner));
+                params.get(*index).map_or(Vec::new(), |param| {
+                    param
+                        .bounds
+                        .iter()
+                        .filter(|bound| !bound.negative)
+                        .filter_map(|bound| self.trait_res(&bound.path))
+                        .collect()
+                })
+            }
+            Ty::SelfParam(trait_) => vec![Res::Item(*trait_)],
+            // The compiler implements `Mem` for sized types and `Default` for primitives.
+            Ty::Int(_) | Ty::Float(_) | Ty::Bool | Ty::Char => {
+                vec![Res::Builtin(Builtin::Mem), Res::Builtin(Builtin::Default)]
+            }
+            _ if self.implements(ty, Res::Builtin(Builtin::Mem)) => {
+                vec![Res::Builtin(Builtin::Mem)]
+            }
+            _ => Vec::new(),
+        };
+        let derived = match ty {
+            Ty::Adt(id, _) => Builtin::all()
+                .filter(|builtin| self.derived(*id, Res::Builtin(*builtin)))
+                .map(Res::Builtin)
+                .collect(),
+            _ => Vec::new(),
+        };
+        for trait_ in bounds.into_iter().chain(derived) {
+            traits.extend(self.trait_fun(trait_, name, false));
+        }
+
+        for impl_ in self.impls() {
+            let Some(trait_) = self.impl_trait(impl_) else {
+                continue;
+            };
+            let Some(args) = self.impl_args(impl_, ty) else {
+                continue;
+            };
+            if self.unmet_bound(impl_, &args).is_some() {
+                continue;
+            }
+            let ItemKind::Impl(impl_ast) = &resolve::item(self.program, impl_).kind else {
+                continue;
+            };
+
+            let found = impl_ast.items.iter().find_map(|item| match &item.kind {
+                ItemKind::Fun(fun) if fun.name.name == name => Some((item, fun)),
+                _ => None,
+            });
+            match (found, trait_) {
+                (Some((item, fun)), trait_) => {
+                    let method = Method::Declared {
+                        item,
+                        fun,
+                        owner: impl_,
+                        args,
+                        in_trait: trait_.is_some(),
+                    };
+                    match trait_ {
+                        Some(_) => traits.push(method),
+                        None => inherent.push(method),
+                    }
+                }
+                // Impls that don't define a function use the trait's body for it.
+                (None, Some(trait_)) => traits.extend(self.trait_fun(trait_, name, true)),
+                (None, None) => {}
+            }
+        }
+
+        match inherent.is_empty() {
+            true => traits,
+            false => inherent,
+        }
+    }
+
+    /// Returns the function `name` of the trait `trait_`, if it has one.  If `provided` is set,
+    /// it must have a body.
+    fn trait_fun(&self, trait_: Res, name: &str, provided: bool) -> Option<Method<'a>> {
+        match trait_ {
+            Res::Builtin(builtin) if !provided => builtin_funs(builtin)
+                .iter()
+                .find(|(fun, _)| *fun == name)
+                .map(|(fun, _)| Method::Builtin(builtin, fun)),
+            Res::Item(id) => {
+                let ItemKind::Trait(trait_) = &resolve::item(self.program, id).kind else {
+                    return None;
+                };
+                trait_.items.iter().find_map(|item| match &item.kind {
+                    ItemKind::Fun(fun)
+                        if fun.name.name == name && (!provided || fun.body.is_some()) =>
+                    {
+                        Some(Method::Declared {
+                            item,
+                            fun,
+                            owner: id,
+                            args: Vec::new(),
+                            in_trait: true,
+                        })
+                    }
+                    _ => None,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the one function in `methods`, which were found by looking up `name` on `ty`,
+    /// reporting if there are several.
+    fn pick(&mut self, mut methods: Vec<Method<'a>>, ty: &Ty, name: &Iden) -> Option<Method<'a>> {
+        // Several functions from inherent impls, or from impls of the same trait, have already
+        // been reported as duplicates.
+        let mut traits: Vec<_> = methods
+            .iter()
+            .map(|method| self.method_trait(method))
+            .collect();
+        traits.dedup();
+        if traits.len() == 1 {
+            methods.truncate(1);
+            return methods.pop();
+        }
+
+        let mut diagnostic = Diagnostic::error(
+            format!(
+                "multiple functions named `{}` apply to `{}`",
+                name.name,
+                self.display(ty)
+            ),
+            name.loc.clone(),
+        )
+        .with_label("ambiguous")
+        .with_note("they come from different traits it implements");
+        for loc in methods.iter().filter_map(Method::loc) {
+            diagnostic = diagnostic.with_secondary(loc, "one of them is declared here");
+        }
+        self.diagnostics.push(diagnostic);
+        None
+    }
+
+    /// Returns the trait `method` belongs to, or `None` if it is from an inherent impl.
+    fn method_trait(&self, method: &Method<'a>) -> Option<Res> {
+        match method {
+            Method::Declared { owner, .. } => match &resolve::item(self.program, *owner).kind {
+                ItemKind::Trait(_) => Some(Res::Item(*owner)),
+                _ => self.impl_trait(*owner).flatten(),
+            },
+            Method::Builtin(trait_, _) => Some(Res::Builtin(*trait_)),
+        }
+    }
+
+    /// Reports the use of `method` at `name` if it is private to another module.
+    fn check_visible(&mut self, method: &Method<'a>, name: &Iden) {
+        let Method::Declared {
+            item,
+            fun,
+            owner,
+            in_trait: false,
+            ..
+        } = method
+        else {
+            return;
+        };
+        if !visibility::is_visible(&item.vis, owner.file, self.file) {
+            self.diagnostics.push(visibility::private(
+                &format!("function `{}`", fun.name.name),
+                name.loc.clone(),
+                fun.name.loc.clone(),
+                visibility::item_publ_loc(item, self.sources),
+            ));
+        }
+    }
+
+    /// Returns the signature of `method` when it is called on `self_ty`.
+    fn signature(&mut self, method: &Method<'a>, self_ty: &Ty) -> Signature {
+        match method {
+            Method::Declared {
+                fun, owner, args, ..
+            } => {
+                let mut lower = |ty: &Type| {
+                    self.lower(ty)
+                        .subst(*owner, args)
+                        .subst_self(*owner, self_ty)
+                };
+                let params = fun.params.iter().map(|param| lower(&param.ty)).collect();
+                let ret = fun.ret.as_ref().map_or(Ty::Unit, lower);
+                Signature {
+                    receiver: fun.receiver.as_ref().map(|receiver| receiver.kind),
+                    params,
+                    ret,
+                }
+            }
+            Method::Builtin(trait_, name) => {
+                let receiver = builtin_funs(*trait_)
+                    .iter()
+                    .find(|(fun, _)| fun == name)
+                    .and_then(|(_, receiver)| *receiver);
+                let ret = match trait_ {
+                    Builtin::Mem => Ty::Int(IntTy::Uint),
+                    Builtin::Default => self_ty.clone(),
+                    _ => Ty::Unit,
+                };
+                Signature {
+                    receiver,
+                    params: Vec::new(),
+                    ret,
+                }
+            }
+        }
+    }
+
+    /// Returns where the function `callee` names is declared, if it names one.
+    fn callee_loc(&self, callee: &Expr) -> Option<Loc> {
+        let ExprKind::Path(path) = &callee.kind else {
+            return None;
+        };
+        if let Some(loc) = self.decls.get(&path.loc) {
+            return Some(loc.clone());
+        }
+        match self.resolutions.path(path)? {
+            PathRes {
+                res: Res::Item(id),
+                rest: 0,
+            } => resolve::item(self.program, id)
+                .name()
+                .map(|name| name.loc.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns the type of the call of `callee` with `args`.
+    fn call(&mut self, callee: &'a Expr, args: &'a [Expr]) -> Ty {
+        let callee_ty = self.infer(callee);
+        let (params, ret) = match self.shallow(&callee_ty) {
+            Ty::Fun(params, ret) => (params, *ret),
+            Ty::Error => {
+                for arg in args {
+                    self.infer(arg);
+                }
+                return Ty::Error;
+            }
+            ty => {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        format!("expected a function, found `{}`", self.display(&ty)),
+                        callee.loc.clone(),
+                    )
+                    .with_label("not a function"),
+                );
+                for arg in args {
+                    self.infer(arg);
+                }
+                return Ty::Error;
+            }
+        };
+
+        let decl = self.callee_loc(callee);
+        self.check_args(&callee.loc, decl, &params, args);
+        ret
+    }
+
+    /// Checks `args` against `params`, the parameters of the function called at `loc` and
+    /// declared at `decl`.
+    fn check_args(&mut self, loc: &Loc, decl: Option<Loc>, params: &[Ty], args: &'a [Expr]) {
+        if params.len() != args.len() {
+            let mut diagnostic = Diagnostic::error(
+                format!(
+                    "this function takes {} but {} supplied",
+                    plural(params.len(), "argument"),
+                    match args.len() {
+                        1 => "1 was".to_string(),
+                        count => format!("{} were", count),
+                    }
+                ),
+                loc.clone(),
+            )
+            .with_label(format!("expected {}", plural(params.len(), "argument")));
+            if let Some(decl) = decl {
+                diagnostic = diagnostic.with_secondary(decl, "the function is declared here");
+            }
+            self.diagnostics.push(diagnostic);
+        }
+
+        for (index, arg) in args.iter().enumerate() {
+            match params.get(index) {
+                Some(param) => self.expect(arg, param),
+                None => {
+                    self.infer(arg);
+                }
+            }
+            self.moves(arg);
+        }
+    }
+
+    /// Returns the type of the field `name` of a value of type `base`, looking through
+    /// references and pointers.
+    fn field(&mut self, base: &Ty, name: &Iden) -> Ty {
+        let mut ty = self.shallow(base);
+        while let Ty::Ref(_, pointee) | Ty::Ptr(_, pointee) = ty {
+            ty = self.shallow(&pointee);
+        }
+
+        let (id, args) = match &ty {
+            Ty::Adt(id, args) => (*id, args.clone()),
+            Ty::Error => return Ty::Error,
+            _ => {
+                let ty_name = self.display(&ty);
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        format!("no field `{}` on type `{}`", name.name, ty_name),
+                        name.loc.clone(),
+                    )
+                    .with_label("unknown field")
+                    .with_note(format!("`{}` has no fields", ty_name)),
+                );
+                return Ty::Error;
+            }
+        };
+
+        let item = resolve::item(self.program, id);
+        let fields = match &item.kind {
+            ItemKind::Struct(struct_) | ItemKind::Union(struct_) => &struct_.fields[..],
+            _ => &[],
+        };
+        let Some(field) = fields.iter().find(|field| field.name.name == name.name) else {
+            let diagnostic = self.unknown_field(&ty, fields, name);
+            self.diagnostics.push(diagnostic);
+            return Ty::Error;
+        };
+
+        self.field_ty(id, &args, item, field, name)
+    }
+
+    /// Returns the type of `field`, of the struct or union `item` whose id is `id`, for the
+    /// arguments `args`.  The field is used at `name`, where it must be visible.
+    fn field_ty(&mut self, id: ItemId, args: &[Ty], item: &Item, field: &Field, name: &Iden) -> Ty {
+        if !visibility::is_visible(&field.vis, id.file, self.file) {
+            let struct_name = item.name().map_or("", |name| name.name.as_str());
+            self.diagnostics.push(visibility::private(
+                &format!("field `{}` of `{}`", field.name.name, struct_name),
+                name.loc.clone(),
+                field.name.loc.clone(),
+                visibility::field_publ_loc(field),
+            ));
+        }
+        self.lower(&field.ty).subst(id, args)
+    }
+
+    /// Returns the error for `name`, which isn't one of `fields` of the type `ty`.
+    fn unknown_field(&self, ty: &Ty, fields: &[Field], name: &Iden) -> Diagnostic {
+        let diagnostic = Diagnostic::error(
+            format!("no field `{}` on type `{}`", name.name, self.display(ty)),
+            name.loc.clone(),
+        )
+        .with_label("unknown field");
+        let candidates: Vec<&str> = fields
+            .iter()
+            .map(|field| field.name.name.as_str())
+            .collect();
+        match similar_name(&name.name, candidates) {
+            Some(similar) => diagnostic.with_suggestion(
+                format!("a field with a similar name exists: `{}`", similar),
+                name.loc.clone(),
+                similar,
+            ),
+            None => diagnostic,
+        }
+    }
+
+    /// Returns the type of the struct literal `lit`.  Every field of a struct must be initialized
+    /// once, and exactly one field of a union.
+    fn struct_lit(&mut self, lit: &'a StructLit) -> Ty {
+        let ty = self.lower_path(&lit.path);
+        let (id, args) = match &ty {
+            Ty::Adt(id, args) => (*id, args.clone()),
+            _ => {
+                // Paths that aren't structs have been reported already, except for `Self` in
+                // traits, which can be any type.
+                if !ty.has_error() {
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            format!("expected a struct, found `{}`", self.display(&ty)),
+                            lit.path.loc.clone(),
+                        )
+                        .with_label("not a struct"),
+                    );
+                }
+                for field in &lit.fields {
+                    self.infer(&field.value);
+                }
+                return Ty::Error;
+            }
+        };
+
+        let item = resolve::item(self.program, id);
+        let (fields, union) = match &item.kind {
+            ItemKind::Struct(struct_) => (&struct_.fields[..], false),
+            ItemKind::Union(struct_) => (&struct_.fields[..], true),
+            _ => (&[][..], false),
+        };
+        let mut seen: Vec<&FieldInit> = Vec::new();
+        for init in &lit.fields {
+            if let Some(first) = seen.iter().find(|first| first.name.name == init.name.name) {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        format!("field `{}` is initialized twice", init.name.name),
+                        init.name.loc.clone(),
+                    )
+                    .with_label("initialized again")
+                    .with_secondary(first.name.loc.clone(), "first initialized here"),
+                );
+                self.infer(&init.value);
+                continue;
+            }
+
+            let Some(field) = fields
+                .iter()
+                .find(|field| field.name.name == init.name.name)
+            else {
+                let diagnostic = self.unknown_field(&ty, fields, &init.name);
+                self.diagnostics.push(diagnostic);
+                self.infer(&init.value);
+                continue;
+            };
+            if let (true, Some(first)) = (union, seen.first()) {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        "union literals initialize exactly one field",
+                        init.name.loc.clone(),
+                    )
+                    .with_label(format!("`{}` is initialized too", init.name.name))
+                    .with_secondary(
+                        first.name.loc.clone(),
+                        format!("`{}` is initialized here", first.name.name),
+                    ),
+                );
+            }
+            seen.push(init);
+
+            let field_ty = self.field_ty(id, &args, item, field, &init.name);
+            let found = self.infer(&init.value);
+            if !self.unify(&field_ty, &found) {
+                let mut diagnostic = self.mismatch(&init.value, &field_ty, &found);
+                // The value of a shorthand field is its name, so a conversion has to be written
+                // out as the value.
+                if init.shorthand {
+                    for suggestion in &mut diagnostic.suggestions {
+                        suggestion.replacement =
+                            format!("{}: {}", init.name.name, suggestion.replacement);
+                    }
+                }
+                self.diagnostics.push(diagnostic);
+            }
+            self.moves(&init.value);
+        }
+
+        // Fields with names that aren't known have been reported, so only count the ones that are.
+        let known = lit
+            .fields
+            .iter()
+            .filter(|init| fields.iter().any(|field| field.name.name == init.name.name))
+            .count();
+        if union && known == 0 && !fields.is_empty() {
+            self.diagnostics.push(
+                Diagnostic::error(
+                    "union literals initialize exactly one field",
+                    lit.path.loc.clone(),
+                )
+                .with_label("no field is initialized"),
+            );
+        }
+        if union || known < lit.fields.len() {
+            return ty;
+        }
+
+        let missing: Vec<&Field> = fields
+            .iter()
+            .filter(|field| {
+                !lit.fields
+                    .iter()
+                    .any(|init| init.name.name == field.name.name)
+            })
+            .collect();
+        if !missing.is_empty() {
+            let names: Vec<String> = missing
+                .iter()
+                .map(|field| format!("`{}`", field.name.name))
+                .collect();
+            let names = match names.split_last() {
+                Some((last, rest)) if !rest.is_empty() => {
+                    format!("{} and {}", rest.join(", "), last)
+                }
+                _ => names.join(""),
+            };
+            let mut diagnostic = Diagnostic::error(
+                format!(
+                    "missing {} {} in initializer of `{}`",
+                    match missing.len() {
+                        1 => "field",
+                        _ => "fields",
+                    },
+                    names,
+                    self.display(&ty)
+                ),
+                lit.path.loc.clone(),
+            )
+            .with_label(format!("missing {}", names));
+            for field in missing {
+                diagnostic = diagnostic.with_secondary(
+                    field.name.loc.clone(),
+                    format!("`{}` is declared here", field.name.name),
+                );
+            }
+            self.diagnostics.push(diagnostic);
+        }
+        ty
+    }
+
+    /// Returns the type of `expr`, the operator `op` applied to `operand`.
+    fn unary(&mut self, expr: &Expr, op: UnOp, operand: &'a Expr) -> Ty {
+        if let (UnOp::Neg, ExprKind::Lit(lit)) = (op, &operand.kind) {
+            let ty = self.lit(lit, true, &operand.loc);
+            self.exprs.insert(operand.loc.clone(), ty.clone());
+            return self.negate(expr, ty);
+        }
+
+        let ty = self.infer(operand);
+        match op {
+            UnOp::Neg => self.negate(expr, ty),
+            UnOp::Not => {
+                if !self.unify(&Ty::Bool, &ty) {
+                    let mut diagnostic = Diagnostic::error(
+                        format!("`!` can't be applied to `{}`", self.display(&ty)),
+                        expr.loc.clone(),
+                    )
+                    .with_label("`!` needs a `bool`");
+                    if self.shallow(&ty).is_int() {
+                        let loc =
+                            Loc::new(expr.loc.file, expr.loc.span.start..expr.loc.span.start + 1);
+                        diagnostic = diagnostic.with_suggestion(
+                            "to flip the bits of an integer, use `~`",
+                            loc,
+                            "~",
+                        );
+                    }
+                    self.diagnostics.push(diagnostic);
+                }
+                Ty::Bool
+            }
+            UnOp::BitNot => {
+                if !self.shallow(&ty).is_int() && !self.is_ok(&ty) {
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            format!("`~` can't be applied to `{}`", self.display(&ty)),
+                            expr.loc.clone(),
+                        )
+                        .with_label("`~` needs an integer"),
+                    );
+                    return Ty::Error;
+                }
+                ty
+            }
+            UnOp::Deref => match self.shallow(&ty) {
+                Ty::Ptr(_, pointee) | Ty::Ref(_, pointee) => *pointee,
+                Ty::Error => Ty::Error,
+                ty => {
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            format!("`{}` can't be dereferenced", self.display(&ty)),
+                            expr.loc.clone(),
+                        )
+                        .with_label("not a pointer or reference"),
+                    );
+                    Ty::Error
+                }
+            },
+            UnOp::Ref => Ty::Ref(false, Box::new(ty)),
+            UnOp::RefMut => {
+                self.place(operand, "mutably borrow");
+                Ty::Ref(true, Box::new(ty))
+            }
+        }
+    }
+
+    /// Returns the type of `expr`, which negates a value of type `ty`.
+    fn negate(&mut self, expr: &Expr, ty: Ty) -> Ty {
+        match self.shallow(&ty) {
+            Ty::Int(int) if int.signed() => ty,
+            Ty::IntVar(_) | Ty::Float(_) | Ty::FloatVar(_) | Ty::Error | Ty::Never => ty,
+            Ty::Int(_) => {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        format!("`{}` can't be negated", self.display(&ty)),
+                        expr.loc.clone(),
+                    )
+                    .with_label("unsigned integers can't be negative"),
+                );
+                Ty::Error
+            }
+            _ => {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        format!("`-` can't be applied to `{}`", self.display(&ty)),
+                        expr.loc.clone(),
+                    )
+                    .with_label("`-` needs a signed integer or a float"),
+                );
+                Ty::Error
+            }
+        }
+    }
+
+    /// Returns the type of the binary operation `op` on `lhs`, whose type is `lhs_ty`, and
+    /// `rhs`.
+    fn binary(&mut self, op: BinOp, lhs: &Expr, lhs_ty: &Ty, rhs: &'a Expr) -> Ty {
+        let lhs_ty = self.shallow(lhs_ty);
+        match op {
+            BinOp::And | BinOp::Or => {
+                if !self.unify(&Ty::Bool, &lhs_ty) {
+                    self.bad_operand(op, lhs, &lhs_ty, "a `bool`");
+                }
+                self.expect(rhs, &Ty::Bool);
+                return Ty::Bool;
+            }
+            BinOp::Shl | BinOp::Shr => {
+                let rhs_ty = self.infer(rhs);
+                if !self.shallow(&rhs_ty).is_int() && !self.is_ok(&rhs_ty) {
+                    self.bad_operand(op, rhs, &rhs_ty, "an integer");
+                }
+                if !lhs_ty.is_int() && !self.is_ok(&lhs_ty) {
+                    self.bad_operand(op, lhs, &lhs_ty, "an integer");
+                    return Ty::Error;
+                }
+                return lhs_ty;
+            }
+            _ => {}
+        }
+
+        let needed = match op {
+            BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Rem => {
+                (!lhs_ty.is_numeric()).then_some("a number")
+            }
+            BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor => {
+                (!lhs_ty.is_int() && lhs_ty != Ty::Bool).then_some("an integer or a `bool`")
+            }
+            BinOp::Eq | BinOp::Ne => {
+                let comparable = match &lhs_ty {
+                    Ty::Adt(id, _) => {
+                        matches!(resolve::item(self.program, *id).kind, ItemKind::Enum(_))
+                    }
+                    Ty::Fun(..) | Ty::Str | Ty::Unit | Ty::Param(..) | Ty::SelfParam(_) => false,
+                    _ => true,
+                };
+                (!comparable).then_some("a primitive, pointer or enum value")
+            }
+            BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => {
+                let ordered = lhs_ty.is_numeric() || matches!(lhs_ty, Ty::Char | Ty::Ptr(..));
+                (!ordered).then_some("a number, `char` or pointer")
+            }
+            BinOp::And | BinOp::Or | BinOp::Shl | BinOp::Shr => unreachable!("checked above"),
+        };
+        let comparison = matches!(
+            op,
+            BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge
+        );
+
+        // The right operand isn't checked against a type the operator can't be applied to.
+        match needed {
+            Some(needed) if !self.is_ok(&lhs_ty) => {
+                self.bad_operand(op, lhs, &lhs_ty, needed);
+                self.infer(rhs);
+                match comparison {
+                    true => Ty::Bool,
+                    false => Ty::Error,
+                }
+            }
+            _ => {
+                self.expect(rhs, &lhs_ty);
+                match comparison {
+                    true => Ty::Bool,
+                    false => self.shallow(&lhs_ty),
+                }
+            }
+        }
+    }
+
+    /// Reports that `op` can't be applied to `operand`, of type `ty`, which should have been
+    /// `needed`, such as "a number".
+    fn bad_operand(&mut self, op: BinOp, operand: &Expr, ty: &Ty, needed: &str) {
+        let mut diagnostic = Diagnostic::error(
+            format!(
+                "`{}` can't be applied to `{}`",
+                op.symbol(),
+                self.display(ty)
+            ),
+            operand.loc.clone(),
+        )
+        .with_label(format!("`{}` needs {}", op.symbol(), needed));
+        if matches!(self.shallow(ty), Ty::Ptr(..))
+            && matches!(
+                op,
+                BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Rem
+            )
+        {
+            diagnostic = diagnostic
+                .with_help("to do arithmetic on the address, cast the pointer to `uint` with `as`");
+        }
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Checks that `expr` is a place that can be changed, which is what it is used for by
+    /// `action`, such as "assign to".
+    fn place(&mut self, expr: &Expr, action: &str) {
+        match &expr.kind {
+            ExprKind::Path(path) => {
+                let Some(PathRes { res, rest: 0 }) = self.resolutions.path(path) else {
+                    return;
+                };
+                let Res::Local(id) = res else {
+                    self.not_a_place(expr, action);
+                    return;
+                };
+
+                let local = self.resolutions.local(id);
+                if local.mutable {
+                    return;
+                }
+                let name = &local.name;
+                let mut diagnostic = Diagnostic::error(
+                    format!("cannot {} `{}`, which isn't mutable", action, name.name),
+                    expr.loc.clone(),
+                )
+                .with_label(format!("cannot {} it", action));
+                diagnostic = match local.kind {
+                    LocalKind::Val => diagnostic
+                        .with_secondary(name.loc.clone(), "declared here without `mut`")
+                        .with_suggestion(
+                            "make it mutable",
+                            Loc::new(name.loc.file, name.loc.span.start..name.loc.span.start),
+                            "mut ",
+                        ),
+                    LocalKind::Param => diagnostic.with_help(format!(
+                        "parameters can't be changed; copy it into a variable with `val mut {0} = {0};`",
+                        name.name
+                    )),
+                    LocalKind::SelfParam => diagnostic
+                        .with_help("take `&mut self` to change the value a method is called on"),
+                };
+                self.diagnostics.push(diagnostic);
+            }
+            ExprKind::Field(base, _) => {
+                let base_ty = self.exprs.get(&base.loc).cloned().unwrap_or(Ty::Error);
+                match self.shallow(&base_ty) {
+                    Ty::Ref(false, _) | Ty::Ptr(false, _) => self.behind(expr, &base_ty, action),
+                    Ty::Ref(..) | Ty::Ptr(..) => {}
+                    _ => self.place(base, action),
+                }
+            }
+            ExprKind::Unary(UnOp::Deref, operand) => {
+                let ty = self.exprs.get(&operand.loc).cloned().unwrap_or(Ty::Error);
+                if let Ty::Ref(false, _) | Ty::Ptr(false, _) = self.shallow(&ty) {
+                    self.behind(expr, &ty, action);
+                }
+            }
+            ExprKind::Index(..) => {
+                let loc = Loc::new(expr.loc.file, expr.loc.span.start..expr.loc.span.start);
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        format!("cannot {} `{}`", action, self.source(expr)),
+                        expr.loc.clone(),
+                    )
+                    .with_label("this is a pointer to the element, not the element itself")
+                    .with_suggestion(
+                        "dereference it to get to the element",
+                        loc,
+                        "*",
+                    ),
+                );
+            }
+            ExprKind::Error => {}
+            _ => self.not_a_place(expr, action),
+        }
+    }
+
+    /// Reports that `expr` can't be changed by `action` because it is behind `ty`, a reference or
+    /// pointer that isn't `mut`.
+    fn behind(&mut self, expr: &Expr, ty: &Ty, action: &str) {
+        let kind = match self.shallow(ty) {
+            Ty::Ptr(..) => "`*` pointer",
+            _ => "`&` reference",
+        };
+        self.diagnostics.push(
+            Diagnostic::error(
+                format!(
+                    "cannot {} `{}`, which is behind a {}",
+                    action,
+                    self.source(expr),
+                    kind
+                ),
+                expr.loc.clone(),
+            )
+            .with_label(format!("`{}` isn't `mut`", self.display(ty))),
+        );
+    }
+
+    /// Reports that `expr` isn't a place, so `action` can't be done to it.
+    fn not_a_place(&mut self, expr: &Expr, action: &str) {
+        self.diagnostics.push(
+            Diagnostic::error(
+                format!("cannot {} this expression", action),
+                expr.loc.clone(),
+            )
+            .with_label("not a variable, field or dereferenced pointer"),
+        );
+    }
+}
+
+/// Returns the type of `self` in a method that takes it as `kind`, in an impl for `self_ty`.
+fn receiver_ty(kind: ReceiverKind, self_ty: Ty) -> Ty {
+    match kind {
+        ReceiverKind::Value => self_ty,
+        ReceiverKind::Ref => Ty::Ref(false, Box::new(self_ty)),
+        ReceiverKind::RefMut => Ty::Ref(true, Box::new(self_ty)),
+    }
+}
+
+/// Describes how a function takes `self`, for errors.
+fn receiver_name(receiver: Option<ReceiverKind>) -> &'static str {
+    match receiver {
+        None => "no `self`",
+        Some(ReceiverKind::Value) => "`self`",
+        Some(ReceiverKind::Ref) => "`&self`",
+        Some(ReceiverKind::RefMut) => "`&mut self`",
+    }
+}
+
+/// Returns the functions of the builtin trait `trait_`, along with how each takes `self`.
+fn builtin_funs(trait_: Builtin) -> &'static [(&'static str, Option<ReceiverKind>)] {
+    match trait_ {
+        Builtin::Mem => &[("size_of", None), ("align_of", None)],
+        Builtin::Default => &[("default", None)],
+        Builtin::Drop => &[("drop", Some(ReceiverKind::Value))],
+        _ => &[],
+    }
+}
+
+/// Returns the function `item` is, if it is one.
+pub(crate) fn item_fun(item: &Item) -> Option<&Fun> {
+    match &item.kind {
+        ItemKind::Fun(fun) => Some(fun),
+        _ => None,
+    }
+}
+
+/// Returns whether `ty` is `pattern`, where the parameters of the impl `impl_` in `pattern` stand
+/// for any type.  The types they stand for are recorded in `args`.
+fn match_ty(pattern: &Ty, ty: &Ty, impl_: ItemId, args: &mut [Option<Ty>]) -> bool {
+    match (pattern, ty) {
+        (_, Ty::Error) | (Ty::Error, _) => true,
+        (Ty::Param(id, index), ty) if *id == impl_ => match &args[*index] {
+            Some(arg) => arg == ty,
+            None => {
+                args[*index] = Some(ty.clone());
+                true
+            }
+        },
+        (Ty::Ptr(mutable, pattern), Ty::Ptr(ty_mutable, ty))
+        | (Ty::Ref(mutable, pattern), Ty::Ref(ty_mutable, ty)) => {
+            mutable == ty_mutable && match_ty(pattern, ty, impl_, args)
+        }
+        (Ty::Adt(id, patterns), Ty::Adt(ty_id, tys)) => {
+            id == ty_id
+                && patterns.len() == tys.len()
+                && patterns
+                    .iter()
+                    .zip(tys)
+                    .all(|(pattern, ty)| match_ty(pattern, ty, impl_, args))
+        }
+        (pattern, ty) => pattern == ty,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::Severity;
+    use crate::loader;
+
+    /// Checks `src` and returns the severity and message of every diagnostic reported.
+    fn check_src(src: &str) -> Vec<(Severity, String)> {
+        diagnose(src)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.message))
+            .collect()
+    }
+
+    /// Checks `src` and returns every diagnostic reported.
+    fn diagnose(src: &str) -> Vec<Diagnostic> {
+        let mut sources = SourceMap::new();
+        let file = sources.add("test.hl", src.to_string());
+        let mut diagnostics = Diagnostics::new();
+        let module = parser::parse(&sources, file, &mut diagnostics).expect("the source parses");
+        let program = loader::load(module, &[], &mut sources, &mut diagnostics);
+        let mut lints = LintContext::new(Vec::new(), false);
+        for module in &program.modules {
+            lints.add_module(&module.ast, &sources, &mut diagnostics);
+        }
+        let resolutions = resolve::resolve(&program, &lints, &mut diagnostics);
+        check(
+            &program,
+            &resolutions,
+            Target::host(),
+            &sources,
+            &lints,
+            &mut diagnostics,
+        );
+        diagnostics.iter().cloned().collect()
+    }
+
+    /// Returns the error for `src` with the label `label`, which must be the only one.
+    fn error(src: &str, label: &str) -> Diagnostic {
+        let diagnostics = diagnose(src);
+        let [diagnostic] = &diagnostics[..] else {
+            panic!("expected one diagnostic, found {:#?}", diagnostics);
+        };
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(diagnostic.primary.message, label);
+        diagnostic.clone()
+    }
+
+    /// Returns the replacement suggested by `diagnostic`, which must have one suggestion.
+    fn replacement(diagnostic: &Diagnostic) -> &str {
+        let [suggestion] = &diagnostic.suggestions[..] else {
+            panic!(
+                "expected one suggestion, found {:#?}",
+                diagnostic.suggestions
+            );
+        };
+        &suggestion.replacement
+    }
+
+    #[test]
+    fn mismatches_suggest_as() {
+        let src = "fun f(a: int32) { val _x: int64 = a; }";
+        let diagnostic = error(src, "expected `int64`, found `int32`");
+        assert_eq!(diagnostic.message, "mismatched types");
+        assert_eq!(&src[diagnostic.primary.loc.span.clone()], "a");
+        assert_eq!(replacement(&diagnostic), "a as int64");
+
+        let src = "fun f(a: int32) { val _x: int64 = a + a; }";
+        let diagnostic = error(src, "expected `int64`, found `int32`");
+        assert_eq!(replacement(&diagnostic), "(a + a) as int64");
+
+        let src = "fun f(p: *uint8) -> uint { return p; }";
+        let diagnostic = error(src, "expected `uint`, found `*uint8`");
+        assert_eq!(replacement(&diagnostic), "p as uint");
+        assert_eq!(
+            diagnostic.secondary[0].message,
+            "expected because of this return type"
+        );
+    }
+
+    #[test]
+    fn mismatches_without_a_valid_cast_suggest_nothing() {
+        let src = "fun f(b: bool) { val _x: int32 = b; }";
+        let diagnostic = error(src, "expected `int32`, found `bool`");
+        assert!(diagnostic.suggestions.is_empty());
+
+        let src = "struct S { a: int32 }\nfun f(s: S) { val _x: int32 = s; }";
+        let diagnostic = error(src, "expected `int32`, found `S`");
+        assert!(diagnostic.suggestions.is_empty());
+
+        // A suggestion would lose the attribute.
+        let src = "fun f(a: int32) { val _x: int64 = #allow(unsafe_code) a; }";
+        let diagnostic = error(src, "expected `int64`, found `int32`");
+        assert!(diagnostic.suggestions.is_empty());
+    }
+
+    #[test]
+    fn literal_inference() {
+        let src = "
+fun f() -> uint8 {
+    val a = 255;
+    val _b: float64 = 2.5;
+    val _c: int8 = -128;
+    return a;
+}
+";
+        assert_eq!(check_src(src), []);
+
+        let src = "fun f() { val a = 256; val _b: uint8 = a; }";
+        error(src, "doesn't fit in `uint8`");
+        let src = "fun f() { val _a: int8 = -129; }";
+        error(src, "doesn't fit in `int8`");
+        let src = "fun f() { val _a: float32 = 1; }";
+        let diagnostic = error(src, "expected `float32`, found `{integer}`");
+        assert_eq!(replacement(&diagnostic), "1 as float32");
+    }
+
+    /// A struct with methods taking `&mut self` and `&self`.
+    const COUNTER: &str = "
+struct Counter { n: int32 }
+
+impl Counter {
+    fun bump(&mut self) {
+        self.n += 1;
+    }
+
+    fun get(&self) -> int32 {
+        return self.n;
+    }
+}
+";
+
+    #[test]
+    fn auto_ref() {
+        let ok = "
+fun f(r: &Counter, m: &mut Counter) -> int32 {
+    val mut c = Counter::{ n: 0 };
+    c.bump();
+    m.bump();
+    return r.get() + m.get() + c.get();
+}
+";
+        assert_eq!(check_src(&format!("{}{}", COUNTER, ok)), []);
+
+        let src = format!(
+            "{}fun f() {{ val c = Counter::{{ n: 0 }}; c.bump(); }}",
+            COUNTER
+        );
+        let diagnostic = error(&src, "cannot mutably borrow it");
+        assert_eq!(
+            diagnostic.message,
+            "cannot mutably borrow `c`, which isn't mutable"
+        );
+        assert_eq!(replacement(&diagnostic), "mut ");
+
+        let src = format!("{}fun f(r: &Counter) {{ r.bump(); }}", COUNTER);
+        let diagnostic = error(&src, "`&Counter` isn't `mut`");
+        assert_eq!(
+            diagnostic.message,
+            "cannot mutably borrow `r`, which is behind a `&` reference"
+        );
+    }
+
+    #[test]
+    fn assignment_mutability() {
+        let ok = "
+fun f(m: &mut Counter, p: *mut int32) {
+    val mut a = 1;
+    a += 1;
+    m.n = a;
+    *p = a;
+}
+";
+        assert_eq!(check_src(&format!("{}{}", COUNTER, ok)), []);
+
+        let src = "fun f() { val _a = 1; _a = 2; }";
+        let diagnostic = error(src, "cannot assign to it");
+        assert_eq!(
+            diagnostic.message,
+            "cannot assign to `_a`, which isn't mutable"
+        );
+        assert_eq!(replacement(&diagnostic), "mut ");
+
+        let src = "fun f(a: int32) { a += 2; }";
+        let diagnostic = error(src, "cannot assign to it");
+        assert_eq!(
+            diagnostic.help,
+            ["parameters can't be changed; copy it into a variable with `val mut a = a;`"]
+        );
+
+        let src = format!("{}fun f(r: &Counter) {{ r.n = 4; }}", COUNTER);
+        let diagnostic = error(&src, "`&Counter` isn't `mut`");
+        assert_eq!(
+            diagnostic.message,
+            "cannot assign to `r.n`, which is behind a `&` reference"
+        );
+
+        let src = "fun f(p: *int32) { *p = 4; }";
+        let diagnostic = error(src, "`*int32` isn't `mut`");
+        assert_eq!(
+            diagnostic.message,
+            "cannot assign to `*p`, which is behind a `*` pointer"
+        );
+    }
+
+    #[test]
+    fn missing_return_without_default_is_an_error() {
+        let src = "
+struct Pair { a: int32, b: int32 }
+
+fun pair(c: bool) -> Pair {
+    if c {
+        return Pair::{ a: 1, b: 2 };
+    }
+}
+";
+        assert_eq!(
+            check_src(src),
+            [(
+                Severity::Error,
+                "function `pair` can end without returning a value".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn missing_return_with_default_is_a_lint() {
+        let src = "
+#derive(Default)
+struct Pair { a: int32, b: int32 }
+
+fun pair(c: bool) -> Pair {
+    if c {
+        return Pair::{ a: 1, b: 2 };
+    }
+}
+
+fun zero() -> int32 {
+}
+";
+        assert_eq!(
+            check_src(src),
+            [
+                (
+                    Severity::Warning,
+                    "function doesn't return a value on every path".to_string()
+                ),
+                (
+                    Severity::Warning,
+                    "function never returns a value".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn diverging_functions_need_no_return() {
+        let src = "
+struct Pair { a: int32, b: int32 }
+
+fun pair() -> Pair {
+    while true {
+    }
+}
+";
+        assert_eq!(check_src(src), []);
+    }
+}
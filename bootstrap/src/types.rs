@@ -0,0 +1,618 @@
+//! The types of values, and how they are laid out in memory.
+//!
+//! Every type but `str` and generic parameters has a size and alignment known at compile time,
+//! which is what the builtin `Mem` trait exposes through `size_of` and `align_of`.  The sizes of
+//! `int`, `uint`, pointers and references depend on the target.  Struct fields are laid out in
+//! the order they are declared, each at the next offset its alignment allows, and the size of a
+//! struct is rounded up to its alignment so that arrays of it stay aligned.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::ast::{Enum, Expr, ExprKind, FloatTy, IntTy, ItemKind, Lit, UnOp};
+use crate::loader::Program;
+use crate::resolve::{self, ItemId};
+use crate::target::Target;
+
+/// A type.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Ty {
+    /// An integer type.
+    Int(IntTy),
+
+    /// A floating point type.
+    Float(FloatTy),
+
+    /// `bool`
+    Bool,
+
+    /// `char`
+    Char,
+
+    /// `str`
+    Str,
+
+    /// The type of functions that don't return a value, written `()`.
+    Unit,
+
+    /// The type of expressions that never finish, such as calls to `panic`.
+    Never,
+
+    /// A pointer.  The flag is set for `mut` pointers.
+    Ptr(bool, Box<Ty>),
+
+    /// A reference.  The flag is set for `mut` references.
+    Ref(bool, Box<Ty>),
+
+    /// A struct, union or enum, along with the arguments of its generic parameters.
+    Adt(ItemId, Vec<Ty>),
+
+    /// The generic parameter with the given index on an item.
+    Param(ItemId, usize),
+
+    /// `Self` inside the given trait, which stands for whatever type implements it.
+    SelfParam(ItemId),
+
+    /// A function, with its parameter types and return type.
+    Fun(Vec<Ty>, Box<Ty>),
+
+    /// An integer literal whose type hasn't been inferred yet.
+    IntVar(usize),
+
+    /// A floating point literal whose type hasn't been inferred yet.
+    FloatVar(usize),
+
+    /// The type of something that had an error, which has already been reported.  It is
+    /// compatible with every type, so that one error doesn't cause others.
+    Error,
+}
+
+impl Ty {
+    /// Returns whether the type is an integer type, or an integer literal's type.
+    pub fn is_int(&self) -> bool {
+        matches!(self, Ty::Int(_) | Ty::IntVar(_))
+    }
+
+    /// Returns whether the type is a floating point type, or a float literal's type.
+    pub fn is_float(&self) -> bool {
+        matches!(self, Ty::Float(_) | Ty::FloatVar(_))
+    }
+
+    /// Returns whether the type is an integer or floating point type.
+    pub fn is_numeric(&self) -> bool {
+        self.is_int() || self.is_float()
+    }
+
+    /// Returns the type a pointer or reference points to.
+    pub fn pointee(&self) -> Option<&Ty> {
+        match self {
+            Ty::Ptr(_, ty) | Ty::Ref(_, ty) => Some(ty),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the type is or contains [`Ty::Error`].
+    pub fn has_error(&self) -> bool {
+        match self {
+            Ty::Error => true,
+            Ty::Ptr(_, ty) | Ty::Ref(_, ty) => ty.has_error(),
+            Ty::Adt(_, args) => args.iter().any(Ty::has_error),
+            Ty::Fun(params, ret) => params.iter().any(Ty::has_error) || ret.has_error(),
+            _ => false,
+        }
+    }
+
+    /// Returns whether the type is or contains a literal's type that hasn't been inferred.
+    pub fn has_vars(&self) -> bool {
+        match self {
+            Ty::IntVar(_) | Ty::FloatVar(_) => true,
+            Ty::Ptr(_, ty) | Ty::Ref(_, ty) => ty.has_vars(),
+            Ty::Adt(_, args) => args.iter().any(Ty::has_vars),
+            Ty::Fun(params, ret) => params.iter().any(Ty::has_vars) || ret.has_vars(),
+            _ => false,
+        }
+    }
+
+    /// Replaces the generic parameters of `item` in the type with `args`.
+    pub fn subst(&self, item: ItemId, args: &[Ty]) -> Ty {
+        match self {
+            Ty::Param(owner, index) if *owner == item => {
+                args.get(*index).cloned().unwrap_or(Ty::Error)
+            }
+            Ty::Ptr(mutable, ty) => Ty::Ptr(*mutable, Box::new(ty.subst(item, args))),
+            Ty::Ref(mutable, ty) => Ty::Ref(*mutable, Box::new(ty.subst(item, args))),
+            Ty::Adt(id, tys) => Ty::Adt(*id, tys.iter().map(|ty| ty.subst(item, args)).collect()),
+            Ty::Fun(params, ret) => Ty::Fun(
+                params.iter().map(|ty| ty.subst(item, args)).collect(),
+                Box::new(ret.subst(item, args)),
+            ),
+            _ => self.clone(),
+        }
+    }
+
+    /// Replaces `Self` in the trait `trait_` with `ty`, the type implementing it.
+    pub fn subst_self(&self, trait_: ItemId, ty: &Ty) -> Ty {
+        match self {
+            Ty::SelfParam(owner) if *owner == trait_ => ty.clone(),
+            Ty::Ptr(mutable, pointee) => {
+                Ty::Ptr(*mutable, Box::new(pointee.subst_self(trait_, ty)))
+            }
+            Ty::Ref(mutable, pointee) => {
+                Ty::Ref(*mutable, Box::new(pointee.subst_self(trait_, ty)))
+            }
+            Ty::Adt(id, args) => Ty::Adt(
+                *id,
+                args.iter().map(|arg| arg.subst_self(trait_, ty)).collect(),
+            ),
+            Ty::Fun(params, ret) => Ty::Fun(
+                params
+                    .iter()
+                    .map(|param| param.subst_self(trait_, ty))
+                    .collect(),
+                Box::new(ret.subst_self(trait_, ty)),
+            ),
+            _ => self.clone(),
+        }
+    }
+
+    /// Returns a value that formats the type as it is written in Hail, such as `&mut uint8`.
+    pub fn display<'a>(&'a self, program: &'a Program) -> DisplayTy<'a> {
+        DisplayTy { ty: self, program }
+    }
+}
+
+/// A type along with the program it belongs to, which formats it as it is written in Hail.
+#[derive(Debug)]
+pub struct DisplayTy<'a> {
+    /// The type.
+    ty: &'a Ty,
+
+    /// The program the type's items are in.
+    program: &'a Program,
+}
+
+impl DisplayTy<'_> {
+    /// Returns the same type, formatted the same way.
+    fn of<'a>(&'a self, ty: &'a Ty) -> DisplayTy<'a> {
+        DisplayTy {
+            ty,
+            program: self.program,
+        }
+    }
+
+    /// Returns the name of the item `id`.
+    fn name(&self, id: ItemId) -> &str {
+        resolve::item(self.program, id)
+            .name()
+            .map_or("_", |name| name.name.as_str())
+    }
+}
+
+impl fmt::Display for DisplayTy<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.ty {
+            Ty::Int(ty) => f.write_str(ty.name()),
+            Ty::Float(ty) => f.write_str(ty.name()),
+            Ty::Bool => f.write_str("bool"),
+            Ty::Char => f.write_str("char"),
+            Ty::Str => f.write_str("str"),
+            Ty::Unit => f.write_str("()"),
+            Ty::Never => f.write_str("!"),
+            Ty::Ptr(true, ty) => write!(f, "*mut {}", self.of(ty)),
+            Ty::Ptr(false, ty) => write!(f, "*{}", self.of(ty)),
+            Ty::Ref(true, ty) => write!(f, "&mut {}", self.of(ty)),
+            Ty::Ref(false, ty) => write!(f, "&{}", self.of(ty)),
+            Ty::Adt(id, args) => {
+                f.write_str(self.name(*id))?;
+                if args.is_empty() {
+                    return Ok(());
+                }
+                f.write_str("!<")?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", self.of(arg))?;
+                }
+                f.write_str(">")
+            }
+            Ty::Param(id, index) => {
+                let item = resolve::item(self.program, *id);
+                match resolve::generic_params(item).get(*index) {
+                    Some(param) => f.write_str(&param.name.name),
+                    None => f.write_str("_"),
+                }
+            }
+            Ty::SelfParam(_) => f.write_str("Self"),
+            Ty::Fun(params, ret) => {
+                f.write_str("fun(")?;
+                for (i, param) in params.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", self.of(param))?;
+                }
+                f.write_str(")")?;
+                match **ret {
+                    Ty::Unit => Ok(()),
+                    _ => write!(f, " -> {}", self.of(ret)),
+                }
+            }
+            Ty::IntVar(_) => f.write_str("{integer}"),
+            Ty::FloatVar(_) => f.write_str("{float}"),
+            Ty::Error => f.write_str("{unknown}"),
+        }
+    }
+}
+
+/// The size and alignment of a type, in bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Layout {
+    /// The size of the type, which is a multiple of its alignment.
+    pub size: u64,
+
+    /// The alignment of the type, which is a power of two.
+    pub align: u64,
+
+    /// The offset of each field of a struct or union, in the order they are declared.  This is
+    /// empty for other types.
+    pub offsets: Vec<u64>,
+}
+
+impl Layout {
+    /// Returns the layout of a type without fields that is `size` bytes big and aligned to
+    /// `align` bytes.
+    pub fn scalar(size: u64, align: u64) -> Self {
+        Self {
+            size,
+            align,
+            offsets: Vec::new(),
+        }
+    }
+}
+
+/// Why a type has no layout.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LayoutError {
+    /// The size of the type isn't known at compile time, as for `str`, or isn't known until a
+    /// mixin is instantiated, as for generic parameters.
+    Unsized,
+
+    /// The struct or union contains itself, so it would be infinitely big.
+    Recursive(ItemId),
+}
+
+/// Computes the layouts of types for a target.
+#[derive(Debug)]
+pub struct LayoutCx<'a> {
+    /// The program the types are in.
+    program: &'a Program,
+
+    /// The types of the fields of every struct and union, in terms of their generic parameters.
+    fields: &'a HashMap<ItemId, Vec<Ty>>,
+
+    /// The target, which decides the sizes of pointers, `int` and `uint`.
+    target: Target,
+}
+
+impl<'a> LayoutCx<'a> {
+    /// Creates a context for `target`, where the struct and union `id` has the field types
+    /// `fields[id]`.
+    pub fn new(program: &'a Program, fields: &'a HashMap<ItemId, Vec<Ty>>, target: Target) -> Self {
+        Self {
+            program,
+            fields,
+            target,
+        }
+    }
+
+    /// Returns the layout of `ty`.
+    pub fn layout(&self, ty: &Ty) -> Result<Layout, LayoutError> {
+        self.layout_in(ty, &mut Vec::new())
+    }
+
+    /// Returns the layout of `ty` inside the structs and unions `outer`.
+    fn layout_in(&self, ty: &Ty, outer: &mut Vec<ItemId>) -> Result<Layout, LayoutError> {
+        let ptr = u64::from(self.target.ptr_bits / 8);
+        Ok(match ty {
+            Ty::Int(int) => {
+                let size = u64::from(int.bits(self.target.ptr_bits) / 8);
+                Layout::scalar(size, size)
+            }
+            Ty::Float(FloatTy::Float32) => Layout::scalar(4, 4),
+            Ty::Float(FloatTy::Float64) => Layout::scalar(8, 8),
+            Ty::Bool => Layout::scalar(1, 1),
+            Ty::Char => Layout::scalar(4, 4),
+            Ty::Unit | Ty::Never => Layout::scalar(0, 1),
+
+            // A pointer to a `str` carries its length along with its address.
+            Ty::Ptr(_, pointee) | Ty::Ref(_, pointee) if **pointee == Ty::Str => {
+                Layout::scalar(ptr * 2, ptr)
+            }
+            Ty::Ptr(..) | Ty::Ref(..) | Ty::Fun(..) => Layout::scalar(ptr, ptr),
+
+            Ty::Adt(id, args) => match &resolve::item(self.program, *id).kind {
+                ItemKind::Enum(enum_) => {
+                    let size = u64::from(discriminant_ty(enum_).bits(self.target.ptr_bits) / 8);
+                    Layout::scalar(size, size)
+                }
+                ItemKind::Struct(_) => self.aggregate(*id, args, false, outer)?,
+                ItemKind::Union(_) => self.aggregate(*id, args, true, outer)?,
+                _ => return Err(LayoutError::Unsized),
+            },
+
+            // Literals that haven't been inferred get their default types.
+            Ty::IntVar(_) => Layout::scalar(4, 4),
+            Ty::FloatVar(_) => Layout::scalar(8, 8),
+
+            Ty::Str | Ty::Param(..) | Ty::SelfParam(_) | Ty::Error => {
+                return Err(LayoutError::Unsized)
+            }
+        })
+    }
+
+    /// Returns the layout of the struct or union `id` with the generic arguments `args`.  The
+    /// fields of a union all start at offset 0.
+    fn aggregate(
+        &self,
+        id: ItemId,
+        args: &[Ty],
+        union: bool,
+        outer: &mut Vec<ItemId>,
+    ) -> Result<Layout, LayoutError> {
+        if outer.contains(&id) {
+            return Err(LayoutError::Recursive(id));
+        }
+
+        outer.push(id);
+        let mut size = 0;
+        let mut align = 1;
+        let mut offsets = Vec::new();
+        for field in self.fields.get(&id).into_iter().flatten() {
+            let layout = self.layout_in(&field.subst(id, args), outer)?;
+            align = align.max(layout.align);
+            if union {
+                offsets.push(0);
+                size = size.max(layout.size);
+            } else {
+                let offset = align_to(size, layout.align);
+                offsets.push(offset);
+                size = offset + layout.size;
+            }
+        }
+        outer.pop();
+
+        Ok(Layout {
+            size: align_to(size, align),
+            align,
+            offsets,
+        })
+    }
+}
+
+/// Returns `offset` rounded up to a multiple of `align`.
+fn align_to(offset: u64, align: u64) -> u64 {
+    offset.div_ceil(align) * align
+}
+
+/// Returns the value of the enum discriminant `value`, if it is an integer literal.
+pub fn discriminant_value(value: &Expr) -> Option<i128> {
+    match &value.kind {
+        ExprKind::Lit(Lit::Int(lit)) => i128::try_from(lit.value).ok(),
+        ExprKind::Unary(UnOp::Neg, operand) => match &operand.kind {
+            ExprKind::Lit(Lit::Int(lit)) => i128::try_from(lit.value).ok().map(|value| -value),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns the discriminant of each variant of `enum_`.  Variants without an explicit
+/// discriminant take the one after the previous variant's, starting at 0.
+pub fn discriminants(enum_: &Enum) -> Vec<i128> {
+    let mut next = 0;
+    enum_
+        .variants
+        .iter()
+        .map(|variant| {
+            let value = variant
+                .value
+                .as_ref()
+                .and_then(discriminant_value)
+                .unwrap_or(next);
+            next = value + 1;
+            value
+        })
+        .collect()
+}
+
+/// Returns the smallest integer type that holds every discriminant of `enum_`, preferring
+/// unsigned types.
+pub fn discriminant_ty(enum_: &Enum) -> IntTy {
+    let values = discriminants(enum_);
+    let min = values.iter().copied().min().unwrap_or(0);
+    let max = values.iter().copied().max().unwrap_or(0);
+    let candidates = match min < 0 {
+        true => [IntTy::Int8, IntTy::Int16, IntTy::Int32, IntTy::Int64],
+        false => [IntTy::Uint8, IntTy::Uint16, IntTy::Uint32, IntTy::Uint64],
+    };
+    candidates
+        .into_iter()
+        .find(|ty| {
+            let bits = ty.bits(64);
+            match ty.signed() {
+                true => min >= -(1 << (bits - 1)) && max < 1 << (bits - 1),
+                false => max < 1 << bits,
+            }
+        })
+        .unwrap_or(IntTy::Int64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::Diagnostics;
+    use crate::source::SourceMap;
+    use crate::{loader, parser};
+
+    const SRC: &str = "
+struct Padded { a: uint8, b: int32, c: uint16 }
+union Either { a: uint8, b: int64 }
+enum Small { A, B }
+enum Wide { A, B = 256 }
+enum Signed { A = -1, B = 200 }
+struct Pointers { p: *uint8, n: uint, s: *str }
+mixin struct Pair!<T: Mem> { a: T, b: uint8 }
+struct Node { next: Node }
+struct Empty {}
+";
+
+    /// Parses [`SRC`] into a program, along with the field types of its structs and unions.
+    fn program() -> (Program, HashMap<ItemId, Vec<Ty>>) {
+        let mut sources = SourceMap::new();
+        let file = sources.add("test.hl", SRC.to_string());
+        let mut diagnostics = Diagnostics::new();
+        let module = parser::parse(&sources, file, &mut diagnostics).expect("the source parses");
+        let program = loader::load(module, &[], &mut sources, &mut diagnostics);
+
+        let id = |index| ItemId { file, index };
+        let ptr = |ty| Ty::Ptr(false, Box::new(ty));
+        let fields = HashMap::from([
+            (
+                id(0),
+                vec![
+                    Ty::Int(IntTy::Uint8),
+                    Ty::Int(IntTy::Int32),
+                    Ty::Int(IntTy::Uint16),
+                ],
+            ),
+            (id(1), vec![Ty::Int(IntTy::Uint8), Ty::Int(IntTy::Int64)]),
+            (
+                id(5),
+                vec![
+                    ptr(Ty::Int(IntTy::Uint8)),
+                    Ty::Int(IntTy::Uint),
+                    ptr(Ty::Str),
+                ],
+            ),
+            (id(6), vec![Ty::Param(id(6), 0), Ty::Int(IntTy::Uint8)]),
+            (id(7), vec![Ty::Adt(id(7), Vec::new())]),
+            (id(8), Vec::new()),
+        ]);
+        (program, fields)
+    }
+
+    /// Returns the layout of the item at `index` in [`SRC`] with the generic arguments `args`.
+    fn layout(target: &str, index: usize, args: Vec<Ty>) -> Result<Layout, LayoutError> {
+        let (program, fields) = program();
+        let target = Target::find(target).expect("the target is supported");
+        let id = ItemId {
+            file: program.root().ast.file,
+            index,
+        };
+        LayoutCx::new(&program, &fields, target).layout(&Ty::Adt(id, args))
+    }
+
+    /// Returns the layout of a struct or union.
+    fn aggregate(size: u64, align: u64, offsets: &[u64]) -> Layout {
+        Layout {
+            size,
+            align,
+            offsets: offsets.to_vec(),
+        }
+    }
+
+    #[test]
+    fn primitives() {
+        let (program, fields) = program();
+        for (target, ptr) in [("x86_64", 8), ("aarch64", 8), ("x86", 4), ("wasm32", 4)] {
+            let cx = LayoutCx::new(&program, &fields, Target::find(target).unwrap());
+            let size = |ty: Ty| cx.layout(&ty).unwrap().size;
+            assert_eq!(size(Ty::Int(IntTy::Int8)), 1);
+            assert_eq!(size(Ty::Int(IntTy::Uint16)), 2);
+            assert_eq!(size(Ty::Int(IntTy::Int64)), 8);
+            assert_eq!(size(Ty::Int(IntTy::Int)), ptr);
+            assert_eq!(size(Ty::Int(IntTy::Uint)), ptr);
+            assert_eq!(size(Ty::Float(FloatTy::Float32)), 4);
+            assert_eq!(size(Ty::Char), 4);
+            assert_eq!(size(Ty::Bool), 1);
+            assert_eq!(size(Ty::Unit), 0);
+            assert_eq!(
+                cx.layout(&Ty::Ref(true, Box::new(Ty::Bool))),
+                Ok(Layout::scalar(ptr, ptr))
+            );
+            assert_eq!(
+                cx.layout(&Ty::Ref(false, Box::new(Ty::Str))),
+                Ok(Layout::scalar(ptr * 2, ptr))
+            );
+            assert_eq!(cx.layout(&Ty::Str), Err(LayoutError::Unsized));
+        }
+    }
+
+    #[test]
+    fn structs_are_padded() {
+        for target in ["x86_64", "x86"] {
+            assert_eq!(
+                layout(target, 0, Vec::new()),
+                Ok(aggregate(12, 4, &[0, 4, 8]))
+            );
+        }
+        assert_eq!(layout("x86_64", 8, Vec::new()), Ok(aggregate(0, 1, &[])));
+    }
+
+    #[test]
+    fn pointer_sized_fields() {
+        assert_eq!(
+            layout("x86_64", 5, Vec::new()),
+            Ok(aggregate(32, 8, &[0, 8, 16]))
+        );
+        assert_eq!(
+            layout("x86", 5, Vec::new()),
+            Ok(aggregate(16, 4, &[0, 4, 8]))
+        );
+    }
+
+    #[test]
+    fn unions_overlap() {
+        assert_eq!(
+            layout("x86_64", 1, Vec::new()),
+            Ok(aggregate(8, 8, &[0, 0]))
+        );
+    }
+
+    #[test]
+    fn enum_discriminants() {
+        assert_eq!(layout("x86_64", 2, Vec::new()), Ok(Layout::scalar(1, 1)));
+        assert_eq!(layout("x86_64", 3, Vec::new()), Ok(Layout::scalar(2, 2)));
+        assert_eq!(layout("x86", 4, Vec::new()), Ok(Layout::scalar(2, 2)));
+    }
+
+    #[test]
+    fn mixin_instances() {
+        assert_eq!(
+            layout("x86_64", 6, vec![Ty::Int(IntTy::Int64)]),
+            Ok(aggregate(16, 8, &[0, 8]))
+        );
+        assert_eq!(
+            layout("x86", 6, vec![Ty::Int(IntTy::Uint)]),
+            Ok(aggregate(8, 4, &[0, 4]))
+        );
+        assert_eq!(
+            layout("x86_64", 6, vec![Ty::Int(IntTy::Uint8)]),
+            Ok(aggregate(2, 1, &[0, 1]))
+        );
+    }
+
+    #[test]
+    fn recursive_structs() {
+        let (program, _) = program();
+        let node = ItemId {
+            file: program.root().ast.file,
+            index: 7,
+        };
+        assert_eq!(
+            layout("x86_64", 7, Vec::new()),
+            Err(LayoutError::Recursive(node))
+        );
+    }
+}
@@ -0,0 +1,234 @@
+//! Visibility.
+//!
+//! Items, struct fields and functions in impls are private to their module unless they are
+//! declared `publ`.  Using a private one from another module is an error, which suggests adding
+//! `publ` to the declaration.  The functions of a trait, and of impls of traits, are as visible as
+//! the trait, so `publ` isn't allowed on them.
+
+use crate::ast::{Field, Item, ItemKind, Visibility};
+use crate::diagnostic::{Diagnostic, Diagnostics};
+use crate::loader::Program;
+use crate::source::SourceMap;
+use crate::Loc;
+
+/// Returns whether something declared with `vis` in the file `decl` can be used from the file
+/// `from`.
+pub fn is_visible(vis: &Visibility, decl: u32, from: u32) -> bool {
+    matches!(vis, Visibility::Public(_)) || decl == from
+}
+
+/// Returns the error for a use at `loc` of `what`, such as "function `grow`", which is private.
+/// `name` is where it is declared, and `publ` is where `publ ` would make it public.
+pub fn private(what: &str, loc: Loc, name: Loc, publ: Loc) -> Diagnostic {
+    Diagnostic::error(format!("{} is private", what), loc)
+        .with_label("private, so only its own module can use it")
+        .with_secondary(name, "declared here without `publ`")
+        .with_suggestion("make it public", publ, "publ ")
+}
+
+/// Returns where `publ ` would go to make `item` public: after its attributes.
+pub fn item_publ_loc(item: &Item, sources: &SourceMap) -> Loc {
+    let offset = match item.attrs.last() {
+        Some(attr) => {
+            let src = &sources.get(item.loc.file).src;
+            let rest = &src[attr.loc.span.end..];
+            attr.loc.span.end + (rest.len() - rest.trim_start().len())
+        }
+        None => item.loc.span.start,
+    };
+    Loc::new(item.loc.file, offset..offset)
+}
+
+/// Returns where `publ ` would go to make `field` public.
+pub fn field_publ_loc(field: &Field) -> Loc {
+    Loc::new(field.loc.file, field.loc.span.start..field.loc.span.start)
+}
+
+/// Returns what `item` is, along with its name, such as "function `grow`".
+pub fn describe_item(item: &Item) -> String {
+    let kind = match &item.kind {
+        ItemKind::Fun(_) => "function",
+        ItemKind::Struct(_) => "struct",
+        ItemKind::Union(_) => "union",
+        ItemKind::Enum(_) => "enum",
+        ItemKind::Trait(_) => "trait",
+        ItemKind::Alias(_) => "type",
+        ItemKind::Import(_) => "import",
+        ItemKind::Impl(_) => "impl",
+        ItemKind::Error => "item",
+    };
+    match item.name() {
+        Some(name) => format!("{} `{}`", kind, name.name),
+        None => kind.to_string(),
+    }
+}
+
+/// Reports imports of private items, and `publ` where it has no meaning.
+pub fn check(program: &Program, sources: &SourceMap, diagnostics: &mut Diagnostics) {
+    for module in &program.modules {
+        let file = module.ast.file;
+        for import in &module.imports {
+            let item = program.item(import);
+            if is_visible(&item.vis, import.module, file) {
+                continue;
+            }
+
+            let name = item.name().expect("imported items have names");
+            diagnostics.push(private(
+                &describe_item(item),
+                import.name.name.loc.clone(),
+                name.loc.clone(),
+                item_publ_loc(item, sources),
+            ));
+        }
+
+        for item in &module.ast.items {
+            check_publ(item, sources, diagnostics);
+        }
+    }
+}
+
+/// Reports `publ` on `item`, or on the functions inside it, where it has no meaning.
+fn check_publ(item: &Item, sources: &SourceMap, diagnostics: &mut Diagnostics) {
+    match &item.kind {
+        ItemKind::Impl(impl_) => {
+            if let Visibility::Public(loc) = &item.vis {
+                diagnostics.push(
+                    unneeded_publ(loc, sources, "impls can't be `publ`")
+                        .with_help("mark the functions inside the impl `publ` instead"),
+                );
+            }
+
+            // Functions in impls of traits are as visible as the trait.
+            if impl_.trait_.is_some() {
+                for item in &impl_.items {
+                    if let Visibility::Public(loc) = &item.vis {
+                        diagnostics.push(
+                            unneeded_publ(loc, sources, "functions in trait impls can't be `publ`")
+                                .with_note("they are as visible as their trait"),
+                        );
+                    }
+                }
+            }
+        }
+        ItemKind::Trait(trait_) => {
+            for item in &trait_.items {
+                if let Visibility::Public(loc) = &item.vis {
+                    diagnostics.push(
+                        unneeded_publ(loc, sources, "functions in traits can't be `publ`")
+                            .with_note("they are as visible as their trait"),
+                    );
+                }
+            }
+        }
+        ItemKind::Import(_) => {
+            if let Visibility::Public(loc) = &item.vis {
+                diagnostics.push(
+                    unneeded_publ(loc, sources, "imports can't be `publ`")
+                        .with_note("imported names can only be used by the module importing them"),
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns the error `message` for the `publ` keyword at `loc`, with a suggestion to remove it.
+fn unneeded_publ(loc: &Loc, sources: &SourceMap, message: &str) -> Diagnostic {
+    // Remove the whitespace after the keyword along with it.
+    let rest = &sources.get(loc.file).src[loc.span.end..];
+    let space = rest.len() - rest.trim_start().len();
+    let removed = Loc::new(loc.file, loc.span.start..loc.span.end + space);
+    Diagnostic::error(message, loc.clone())
+        .with_label("`publ` isn't allowed here")
+        .with_suggestion("remove `publ`", removed, "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::Suggestion;
+    use crate::{loader, parser};
+
+    /// Parses `src` into a program, which must have no syntax errors.
+    fn program(src: &str) -> (Program, SourceMap) {
+        let mut sources = SourceMap::new();
+        let file = sources.add("test.hl", src.to_string());
+        let mut diagnostics = Diagnostics::new();
+        let module = parser::parse(&sources, file, &mut diagnostics).expect("the source parses");
+        let program = loader::load(module, &[], &mut sources, &mut diagnostics);
+        assert!(diagnostics.iter().next().is_none());
+        (program, sources)
+    }
+
+    /// Applies `suggestion` to `src`.
+    fn apply(src: &str, suggestion: &Suggestion) -> String {
+        let span = suggestion.loc.span.clone();
+        format!(
+            "{}{}{}",
+            &src[..span.start],
+            suggestion.replacement,
+            &src[span.end..]
+        )
+    }
+
+    #[test]
+    fn publ_goes_after_docs_and_attributes() {
+        let src = "\
+/// A function.
+fun f() {}
+
+/// A struct.
+#derive(Default)
+struct S {
+    /// A field.
+    a: int32,
+}
+";
+        let (program, sources) = program(src);
+        let items = &program.root().ast.items;
+        let offset = |loc: Loc| {
+            assert!(loc.span.is_empty());
+            loc.span.start
+        };
+        assert_eq!(
+            offset(item_publ_loc(&items[0], &sources)),
+            src.find("fun f").unwrap()
+        );
+        assert_eq!(
+            offset(item_publ_loc(&items[1], &sources)),
+            src.find("struct S").unwrap()
+        );
+        let ItemKind::Struct(struct_) = &items[1].kind else {
+            panic!("expected a struct");
+        };
+        assert_eq!(
+            offset(field_publ_loc(&struct_.fields[0])),
+            src.find("a: int32").unwrap()
+        );
+    }
+
+    #[test]
+    fn removing_unneeded_publ() {
+        let src = "\
+trait T {
+    publ/*c*/fun a();
+    publ   fun b();
+}
+";
+        let (program, sources) = program(src);
+        let mut diagnostics = Diagnostics::new();
+        check(&program, &sources, &mut diagnostics);
+        let fixed: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| apply(src, &diagnostic.suggestions[0]))
+            .collect();
+        assert_eq!(
+            fixed,
+            [
+                "trait T {\n    /*c*/fun a();\n    publ   fun b();\n}\n",
+                "trait T {\n    publ/*c*/fun a();\n    fun b();\n}\n",
+            ]
+        );
+    }
+}
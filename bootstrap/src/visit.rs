@@ -0,0 +1,208 @@
+//! Traversal of ASTs.
+//!
+//! A pass implements [`Visitor`], overriding the methods for the nodes it cares about.  The
+//! default methods call the matching `walk_*` function, which visits the node's children, so an
+//! overriding method calls it too when it wants to keep descending.
+
+use crate::ast::*;
+
+/// A visitor of ASTs.  Every method visits the children of its node by default.
+pub trait Visitor {
+    /// Visits a module.
+    fn visit_module(&mut self, module: &Module) {
+        walk_module(self, module);
+    }
+
+    /// Visits an attribute.
+    fn visit_attribute(&mut self, _attr: &Attribute) {}
+
+    /// Visits an item.
+    fn visit_item(&mut self, item: &Item) {
+        walk_item(self, item);
+    }
+
+    /// Visits a function.
+    fn visit_fun(&mut self, fun: &Fun) {
+        walk_fun(self, fun);
+    }
+
+    /// Visits a type.
+    fn visit_type(&mut self, ty: &Type) {
+        walk_type(self, ty);
+    }
+
+    /// Visits a block.
+    fn visit_block(&mut self, block: &Block) {
+        walk_block(self, block);
+    }
+
+    /// Visits a statement.
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt);
+    }
+
+    /// Visits an expression.
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+}
+
+/// Visits the items of `module`.
+pub fn walk_module<V: Visitor + ?Sized>(visitor: &mut V, module: &Module) {
+    for item in &module.items {
+        visitor.visit_item(item);
+    }
+}
+
+/// Visits the attributes and contents of `item`.
+pub fn walk_item<V: Visitor + ?Sized>(visitor: &mut V, item: &Item) {
+    for attr in &item.attrs {
+        visitor.visit_attribute(attr);
+    }
+
+    match &item.kind {
+        ItemKind::Fun(fun) => visitor.visit_fun(fun),
+        ItemKind::Struct(struct_) | ItemKind::Union(struct_) => {
+            for field in &struct_.fields {
+                visitor.visit_type(&field.ty);
+            }
+        }
+        ItemKind::Enum(enum_) => {
+            for value in enum_
+                .variants
+                .iter()
+                .filter_map(|variant| variant.value.as_ref())
+            {
+                visitor.visit_expr(value);
+            }
+        }
+        ItemKind::Trait(trait_) => {
+            for item in &trait_.items {
+                visitor.visit_item(item);
+            }
+        }
+        ItemKind::Impl(impl_) => {
+            visitor.visit_type(&impl_.target);
+            for item in &impl_.items {
+                visitor.visit_item(item);
+            }
+        }
+        ItemKind::Alias(alias) => visitor.visit_type(&alias.target),
+        ItemKind::Import(_) | ItemKind::Error => {}
+    }
+}
+
+/// Visits the parameter types, return type and body of `fun`.
+pub fn walk_fun<V: Visitor + ?Sized>(visitor: &mut V, fun: &Fun) {
+    for param in &fun.params {
+        visitor.visit_type(&param.ty);
+    }
+    if let Some(ret) = &fun.ret {
+        visitor.visit_type(ret);
+    }
+    if let Some(body) = &fun.body {
+        visitor.visit_block(body);
+    }
+}
+
+/// Visits the types inside `ty`.
+pub fn walk_type<V: Visitor + ?Sized>(visitor: &mut V, ty: &Type) {
+    match &ty.kind {
+        TypeKind::Path(_) => {}
+        TypeKind::Mixin(_, args) => {
+            for arg in args {
+                visitor.visit_type(&arg.ty);
+            }
+        }
+        TypeKind::Ptr(_, ty) | TypeKind::Ref(_, ty) => visitor.visit_type(ty),
+    }
+}
+
+/// Visits the statements of `block`.
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, block: &Block) {
+    for stmt in &block.stmts {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+/// Visits the attributes and contents of `stmt`.
+pub fn walk_stmt<V: Visitor + ?Sized>(visitor: &mut V, stmt: &Stmt) {
+    for attr in &stmt.attrs {
+        visitor.visit_attribute(attr);
+    }
+
+    match &stmt.kind {
+        StmtKind::Val(val) => {
+            if let Some(ty) = &val.ty {
+                visitor.visit_type(ty);
+            }
+            if let Some(value) = &val.value {
+                visitor.visit_expr(value);
+            }
+        }
+        StmtKind::Assign(assign) => {
+            visitor.visit_expr(&assign.target);
+            visitor.visit_expr(&assign.value);
+        }
+        StmtKind::Expr(expr) | StmtKind::Return(Some(expr)) => visitor.visit_expr(expr),
+        StmtKind::If(if_) => walk_if(visitor, if_),
+        StmtKind::While(while_) => {
+            visitor.visit_expr(&while_.cond);
+            visitor.visit_block(&while_.body);
+        }
+        StmtKind::Block(block) => visitor.visit_block(block),
+        StmtKind::Return(None) | StmtKind::Break | StmtKind::Continue | StmtKind::Error => {}
+    }
+}
+
+/// Visits the condition and branches of `if_`.
+pub fn walk_if<V: Visitor + ?Sized>(visitor: &mut V, if_: &If) {
+    visitor.visit_expr(&if_.cond);
+    visitor.visit_block(&if_.then);
+    match &if_.else_ {
+        Some(Else::Block(block)) => visitor.visit_block(block),
+        Some(Else::If(if_)) => walk_if(visitor, if_),
+        None => {}
+    }
+}
+
+/// Visits the attributes and subexpressions of `expr`.
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    for attr in &expr.attrs {
+        visitor.visit_attribute(attr);
+    }
+
+    match &expr.kind {
+        ExprKind::Call(callee, args) => {
+            visitor.visit_expr(callee);
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        }
+        ExprKind::Method(recv, _, args) => {
+            visitor.visit_expr(recv);
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        }
+        ExprKind::Field(expr, _) | ExprKind::Unary(_, expr) => visitor.visit_expr(expr),
+        ExprKind::Index(expr, idx) => {
+            visitor.visit_expr(expr);
+            visitor.visit_expr(idx);
+        }
+        ExprKind::Binary(_, lhs, rhs) => {
+            visitor.visit_expr(lhs);
+            visitor.visit_expr(rhs);
+        }
+        ExprKind::Cast(expr, ty) => {
+            visitor.visit_expr(expr);
+            visitor.visit_type(ty);
+        }
+        ExprKind::Struct(lit) => {
+            for field in &lit.fields {
+                visitor.visit_expr(&field.value);
+            }
+        }
+        ExprKind::Lit(_) | ExprKind::Path(_) | ExprKind::Error => {}
+    }
+}